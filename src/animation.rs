@@ -0,0 +1,456 @@
+use nalgebra_glm::{quat_normalize, quat_slerp, Qua, TVec4};
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::SceneError;
+use crate::vertex::CompactVec3;
+
+/// How values between two keyframes are found
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Holds each keyframe's value until the next one
+    Step,
+    #[default]
+    Linear,
+    /// A Hermite spline through the keyframes, using their tangents
+    CubicSpline,
+}
+
+/// What happens once a clip reaches its end
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    /// Holds the last frame
+    Once,
+    #[default]
+    Loop,
+    /// Plays backwards to the start, then forwards again
+    PingPong,
+}
+
+impl LoopMode {
+    /// Maps a time since the clip started onto the clip's timeline
+    pub fn wrap(self: &Self, time: f32, duration: f32) -> f32 {
+        if duration <= 0.0 {
+            return 0.0;
+        }
+
+        match self {
+            LoopMode::Once => time.clamp(0.0, duration),
+            LoopMode::Loop => time.rem_euclid(duration),
+            LoopMode::PingPong => {
+                let t = time.rem_euclid(duration * 2.0);
+
+                if t > duration {
+                    duration * 2.0 - t
+                } else {
+                    t
+                }
+            }
+        }
+    }
+}
+
+/// A value which can be keyframed. Four component values are quaternions, stored as x, y, z, w
+pub trait Keyable: Copy {
+    fn lerp(a: Self, b: Self, t: f32) -> Self;
+
+    /// The sum of each value times its weight, for evaluating splines
+    fn weighted_sum(terms: &[(Self, f32)]) -> Self;
+
+    /// Brings a spline's result back into the valid range of values
+    fn normalise(self: Self) -> Self {
+        self
+    }
+}
+
+impl Keyable for f32 {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+
+    fn weighted_sum(terms: &[(Self, f32)]) -> Self {
+        terms.iter().map(|&(value, weight)| value * weight).sum()
+    }
+}
+
+impl Keyable for CompactVec3 {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t)
+    }
+
+    fn weighted_sum(terms: &[(Self, f32)]) -> Self {
+        [0, 1, 2].map(|i| terms.iter().map(|&(value, weight)| value[i] * weight).sum())
+    }
+}
+
+impl Keyable for [f32; 4] {
+    /// Spherical interpolation along the shortest arc
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        let a = Qua::from_vector(TVec4::from(a));
+        let mut b = Qua::from_vector(TVec4::from(b));
+
+        if a.coords.dot(&b.coords) < 0.0 {
+            b = -b;
+        }
+
+        quat_slerp(&a, &b, t).coords.into()
+    }
+
+    fn weighted_sum(terms: &[(Self, f32)]) -> Self {
+        [0, 1, 2, 3].map(|i| terms.iter().map(|&(value, weight)| value[i] * weight).sum())
+    }
+
+    /// Splines don't keep quaternions unit length
+    fn normalise(self: Self) -> Self {
+        quat_normalize(&Qua::from_vector(TVec4::from(self)))
+            .coords
+            .into()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe<T> {
+    /// Seconds from the start of the clip
+    pub time: f32,
+    pub value: T,
+    /// Tangents for cubic spline interpolation, in units per second. Missing tangents are taken
+    /// from the neighbouring keyframes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_tangent: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_tangent: Option<T>,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T) -> Self {
+        Self {
+            time,
+            value,
+            in_tangent: None,
+            out_tangent: None,
+        }
+    }
+}
+
+/// Keyframes for one value, sorted by time
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "TrackData<T>")]
+pub struct Track<T> {
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    pub keyframes: Vec<Keyframe<T>>,
+}
+
+/// A track as written in a file, whose keyframes may be in any order
+#[derive(Deserialize)]
+struct TrackData<T> {
+    #[serde(default)]
+    interpolation: Interpolation,
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> TryFrom<TrackData<T>> for Track<T> {
+    type Error = String;
+
+    fn try_from(data: TrackData<T>) -> Result<Self, Self::Error> {
+        let mut keyframes = data.keyframes;
+
+        if let Some(keyframe) = keyframes.iter().find(|keyframe| !keyframe.time.is_finite()) {
+            return Err(format!("invalid keyframe time {}", keyframe.time));
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Ok(Self {
+            interpolation: data.interpolation,
+            keyframes,
+        })
+    }
+}
+
+impl<T: Keyable> Track<T> {
+    pub fn new(interpolation: Interpolation, mut keyframes: Vec<Keyframe<T>>) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        Self {
+            interpolation,
+            keyframes,
+        }
+    }
+
+    /// The time of the last keyframe
+    pub fn duration(self: &Self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// The value at `time`, holding the first and last values outside the keyframes. `None` if
+    /// there are no keyframes
+    pub fn sample(self: &Self, time: f32) -> Option<T> {
+        let keyframes = &self.keyframes;
+        let next = keyframes.partition_point(|keyframe| keyframe.time <= time);
+
+        if next == 0 {
+            return keyframes.first().map(|keyframe| keyframe.value);
+        }
+        if next == keyframes.len() {
+            return keyframes.last().map(|keyframe| keyframe.value);
+        }
+
+        let (a, b) = (&keyframes[next - 1], &keyframes[next]);
+        let span = b.time - a.time;
+        let t = (time - a.time) / span;
+
+        Some(match self.interpolation {
+            Interpolation::Step => a.value,
+            Interpolation::Linear => T::lerp(a.value, b.value, t),
+            Interpolation::CubicSpline => {
+                let out_tangent = a.out_tangent.unwrap_or_else(|| self.tangent(next - 1));
+                let in_tangent = b.in_tangent.unwrap_or_else(|| self.tangent(next));
+
+                let t2 = t * t;
+                let t3 = t2 * t;
+
+                T::weighted_sum(&[
+                    (a.value, 2.0 * t3 - 3.0 * t2 + 1.0),
+                    (out_tangent, (t3 - 2.0 * t2 + t) * span),
+                    (b.value, -2.0 * t3 + 3.0 * t2),
+                    (in_tangent, (t3 - t2) * span),
+                ])
+                .normalise()
+            }
+        })
+    }
+
+    /// A Catmull-Rom tangent from the keyframes either side, one sided at the ends
+    fn tangent(self: &Self, i: usize) -> T {
+        let before = &self.keyframes[i.saturating_sub(1)];
+        let after = &self.keyframes[(i + 1).min(self.keyframes.len() - 1)];
+        let span = after.time - before.time;
+
+        let scale = if span > 0.0 { 1.0 / span } else { 0.0 };
+
+        T::weighted_sum(&[(after.value, scale), (before.value, -scale)])
+    }
+}
+
+/// A keyframed property of a named model or light
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "property", rename_all = "snake_case")]
+pub enum Channel {
+    Translation {
+        target: String,
+        track: Track<CompactVec3>,
+    },
+    /// Quaternions as x, y, z, w
+    Rotation {
+        target: String,
+        track: Track<[f32; 4]>,
+    },
+    Scale {
+        target: String,
+        track: Track<CompactVec3>,
+    },
+    LightPosition {
+        target: String,
+        track: Track<CompactVec3>,
+    },
+    LightColour {
+        target: String,
+        track: Track<CompactVec3>,
+    },
+    LightIntensity {
+        target: String,
+        track: Track<f32>,
+    },
+    /// The weight of one of a mesh's morph targets
+    MorphWeight {
+        target: String,
+        index: usize,
+        track: Track<f32>,
+    },
+}
+
+impl Channel {
+    pub fn target(self: &Self) -> &str {
+        match self {
+            Channel::Translation { target, .. }
+            | Channel::Rotation { target, .. }
+            | Channel::Scale { target, .. }
+            | Channel::LightPosition { target, .. }
+            | Channel::LightColour { target, .. }
+            | Channel::LightIntensity { target, .. }
+            | Channel::MorphWeight { target, .. } => target,
+        }
+    }
+
+    pub fn duration(self: &Self) -> f32 {
+        match self {
+            Channel::Translation { track, .. }
+            | Channel::Scale { track, .. }
+            | Channel::LightPosition { track, .. }
+            | Channel::LightColour { track, .. } => track.duration(),
+            Channel::Rotation { track, .. } => track.duration(),
+            Channel::LightIntensity { track, .. } | Channel::MorphWeight { track, .. } => {
+                track.duration()
+            }
+        }
+    }
+
+    /// Writes the value at `time` into the pose
+    fn sample_into(self: &Self, time: f32, pose: &mut Pose) {
+        match self {
+            Channel::Translation { track, .. } => pose.translation = track.sample(time),
+            Channel::Rotation { track, .. } => pose.rotation = track.sample(time),
+            Channel::Scale { track, .. } => pose.scale = track.sample(time),
+            Channel::LightPosition { track, .. } => pose.light_position = track.sample(time),
+            Channel::LightColour { track, .. } => pose.light_colour = track.sample(time),
+            Channel::LightIntensity { track, .. } => pose.light_intensity = track.sample(time),
+            Channel::MorphWeight { index, track, .. } => {
+                if let Some(weight) = track.sample(time) {
+                    pose.morph_weights.retain(|&(i, _)| i != *index);
+                    pose.morph_weights.push((*index, weight));
+                }
+            }
+        }
+    }
+}
+
+/// The animated properties of one target at a point in time. Anything not animated is `None`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pose {
+    pub translation: Option<CompactVec3>,
+    pub rotation: Option<[f32; 4]>,
+    pub scale: Option<CompactVec3>,
+    pub light_position: Option<CompactVec3>,
+    pub light_colour: Option<CompactVec3>,
+    pub light_intensity: Option<f32>,
+    /// Morph target indices and their weights
+    pub morph_weights: Vec<(usize, f32)>,
+}
+
+fn default_speed() -> f32 {
+    1.0
+}
+
+/// A set of channels played together
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Clip {
+    pub name: String,
+    #[serde(default)]
+    pub mode: LoopMode,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl Clip {
+    pub fn new(name: impl Into<String>, mode: LoopMode, channels: Vec<Channel>) -> Self {
+        Self {
+            name: name.into(),
+            mode,
+            speed: 1.0,
+            channels,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(self: &Self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// The time of the last keyframe in any channel
+    pub fn duration(self: &Self) -> f32 {
+        self.channels
+            .iter()
+            .map(Channel::duration)
+            .fold(0.0, f32::max)
+    }
+
+    /// Where in the clip playback is after `time` seconds, allowing for speed and looping
+    pub fn local_time(self: &Self, time: f32) -> f32 {
+        self.mode.wrap(time * self.speed, self.duration())
+    }
+
+    /// Adds the target's animated properties at `time` seconds of playback to `pose`, replacing
+    /// any it already has
+    pub fn pose(self: &Self, target: &str, time: f32, pose: &mut Pose) {
+        let time = self.local_time(time);
+
+        for channel in self.channels.iter().filter(|c| c.target() == target) {
+            channel.sample_into(time, pose);
+        }
+    }
+}
+
+/// Play, pause and seek state for a clip driven independently of the scene's clock
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Playback {
+    time: f32,
+    speed: f32,
+    playing: bool,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            time: 0.0,
+            speed: 1.0,
+            playing: true,
+        }
+    }
+}
+
+impl Playback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves playback on by `dt` if playing, and returns the new time
+    pub fn advance(self: &mut Self, dt: Duration) -> f32 {
+        if self.playing {
+            self.time += dt.as_secs_f32() * self.speed;
+        }
+
+        self.time
+    }
+
+    /// Seconds of playback, before the clip's own speed and looping are applied
+    pub fn time(self: &Self) -> f32 {
+        self.time
+    }
+
+    pub fn seek(self: &mut Self, time: f32) {
+        self.time = time;
+    }
+
+    pub fn is_playing(self: &Self) -> bool {
+        self.playing
+    }
+
+    pub fn play(self: &mut Self) {
+        self.playing = true;
+    }
+
+    pub fn pause(self: &mut Self) {
+        self.playing = false;
+    }
+
+    /// Negative speeds play backwards
+    pub fn speed(self: &Self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(self: &mut Self, speed: f32) {
+        self.speed = speed;
+    }
+}
@@ -0,0 +1,239 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::error::SceneError;
+use crate::gltf::load_gltf;
+use crate::model::{Model, ModelCollection};
+use crate::obj::load_obj;
+use crate::ply::load_ply;
+use crate::scene_file::SceneFile;
+use crate::stl::load_stl;
+use crate::texture::{load_texture, Texture};
+
+/// How often watched files are checked for changes, here and by the shader watcher
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SceneHandle(usize);
+
+/// An asset whose file was re-imported by `AssetRegistry::poll`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AssetChange {
+    Mesh(MeshHandle),
+    Texture(TextureHandle),
+    Scene(SceneHandle),
+}
+
+/// How each kind of asset is read from a file
+trait Import: Sized {
+    fn import(path: &Path) -> Result<Self, SceneError>;
+}
+
+impl Import for Model {
+    fn import(path: &Path) -> Result<Self, SceneError> {
+        import_mesh(path)
+    }
+}
+
+impl Import for Texture {
+    fn import(path: &Path) -> Result<Self, SceneError> {
+        load_texture(path)
+    }
+}
+
+impl Import for SceneFile {
+    fn import(path: &Path) -> Result<Self, SceneError> {
+        SceneFile::load(path)
+    }
+}
+
+/// A loaded file and when it was written
+struct Asset<T> {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    value: T,
+}
+
+/// Loads meshes, textures and scene files by path, each only once, and re-imports them when they
+/// change on disk
+pub struct AssetRegistry {
+    meshes: Vec<Asset<Model>>,
+    textures: Vec<Asset<Texture>>,
+    scenes: Vec<Asset<SceneFile>>,
+    last_poll: Instant,
+}
+
+#[allow(dead_code)]
+impl AssetRegistry {
+    pub fn new() -> Self {
+        Self {
+            meshes: Vec::new(),
+            textures: Vec::new(),
+            scenes: Vec::new(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Returns the existing handle if the path is already loaded
+    pub fn load_mesh(self: &mut Self, path: impl AsRef<Path>) -> Result<MeshHandle, SceneError> {
+        load(&mut self.meshes, path.as_ref()).map(MeshHandle)
+    }
+
+    /// Returns the existing handle if the path is already loaded
+    pub fn load_texture(
+        self: &mut Self,
+        path: impl AsRef<Path>,
+    ) -> Result<TextureHandle, SceneError> {
+        load(&mut self.textures, path.as_ref()).map(TextureHandle)
+    }
+
+    /// Returns the existing handle if the path is already loaded
+    pub fn load_scene(self: &mut Self, path: impl AsRef<Path>) -> Result<SceneHandle, SceneError> {
+        load(&mut self.scenes, path.as_ref()).map(SceneHandle)
+    }
+
+    pub fn mesh(self: &Self, handle: MeshHandle) -> Model {
+        self.meshes[handle.0].value.clone()
+    }
+
+    pub fn texture(self: &Self, handle: TextureHandle) -> &Texture {
+        &self.textures[handle.0].value
+    }
+
+    pub fn scene(self: &Self, handle: SceneHandle) -> SceneFile {
+        self.scenes[handle.0].value.clone()
+    }
+
+    pub fn mesh_path(self: &Self, handle: MeshHandle) -> PathBuf {
+        self.meshes[handle.0].path.clone()
+    }
+
+    pub fn texture_path(self: &Self, handle: TextureHandle) -> PathBuf {
+        self.textures[handle.0].path.clone()
+    }
+
+    pub fn scene_path(self: &Self, handle: SceneHandle) -> PathBuf {
+        self.scenes[handle.0].path.clone()
+    }
+
+    /// Re-imports every file written since it was last loaded. A file that fails to import keeps
+    /// its previous contents and is tried again when it's next written
+    pub fn poll(self: &mut Self) -> Vec<AssetChange> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let meshes = reload(&mut self.meshes);
+        let textures = reload(&mut self.textures);
+        let scenes = reload(&mut self.scenes);
+
+        meshes
+            .into_iter()
+            .map(|i| AssetChange::Mesh(MeshHandle(i)))
+            .chain(
+                textures
+                    .into_iter()
+                    .map(|i| AssetChange::Texture(TextureHandle(i))),
+            )
+            .chain(
+                scenes
+                    .into_iter()
+                    .map(|i| AssetChange::Scene(SceneHandle(i))),
+            )
+            .collect()
+    }
+}
+
+impl Default for AssetRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Loads a mesh file by its extension
+pub fn import_mesh(path: impl AsRef<Path>) -> Result<Model, SceneError> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    match extension.as_deref() {
+        Some("obj") => load_obj(path),
+        Some("ply") => load_ply(path),
+        Some("stl") => load_stl(path),
+        Some("gltf" | "glb") => load_gltf_mesh(path),
+        _ => Err(SceneError::UnsupportedFormat(path.to_path_buf())),
+    }
+}
+
+/// A glTF file's only mesh with its skin and morph targets, for clips to animate. Otherwise its
+/// meshes are merged into one as first posed, with the node transforms baked in
+fn load_gltf_mesh(path: &Path) -> Result<Model, SceneError> {
+    let mut models = load_gltf(path)?.graph.scene().models();
+
+    match models.as_slice() {
+        [] => Err(SceneError::Gltf("file has no meshes".to_string())),
+        [model] if model.skin().is_some() || model.morph().is_some() => Ok(models.remove(0)),
+        _ => Ok(ModelCollection::from_vec(models).posed_model()),
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// The index of the asset loaded from `path`, loading it if it's new
+fn load<T: Import>(assets: &mut Vec<Asset<T>>, path: &Path) -> Result<usize, SceneError> {
+    if let Some(i) = assets.iter().position(|asset| asset.path == path) {
+        return Ok(i);
+    }
+
+    // Read before importing, so a write during the import is picked up by the next poll
+    let modified = modified_time(path);
+    let value = T::import(path)?;
+
+    assets.push(Asset {
+        path: path.to_path_buf(),
+        modified,
+        value,
+    });
+
+    Ok(assets.len() - 1)
+}
+
+/// The indices of the assets that were re-imported
+fn reload<T: Import>(assets: &mut [Asset<T>]) -> Vec<usize> {
+    let mut changed = Vec::new();
+
+    for (i, asset) in assets.iter_mut().enumerate() {
+        let modified = modified_time(&asset.path);
+
+        // Missing files are skipped, editors often delete and recreate a file to save it
+        if modified.is_none() || modified == asset.modified {
+            continue;
+        }
+        asset.modified = modified;
+
+        match T::import(&asset.path) {
+            Ok(value) => {
+                log::info!("Reloaded {}", asset.path.display());
+
+                asset.value = value;
+                changed.push(i);
+            }
+            Err(e) => log::warn!("Failed to reload {}: {}", asset.path.display(), e),
+        }
+    }
+
+    changed
+}
@@ -1,3 +1,5 @@
+use crate::clock::Clock;
+
 #[repr(C)]
 #[derive(Default, Clone)]
 pub struct Camera {
@@ -6,8 +8,12 @@ pub struct Camera {
 }
 
 impl Camera {
-    pub fn new(position: [f32; 3], dt: u32) -> Self {
-        Self { position, dt }
+    /// `dt` is the whole number of seconds the clock has been running
+    pub fn new(position: [f32; 3], clock: &Clock) -> Self {
+        Self {
+            position,
+            dt: clock.time().as_secs() as u32,
+        }
     }
 
     pub fn position(self: &Self) -> [f32; 3] {
@@ -0,0 +1,149 @@
+use vulkano::format::Format;
+
+use image::{ImageFormat, ImageResult, Rgba32FImage, RgbaImage};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// An output image read back from the GPU, as 8 bit RGBA rows from the top
+#[derive(Clone)]
+pub struct CapturedFrame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl CapturedFrame {
+    /// Whether frames in this format can be read back
+    pub fn supports_format(format: Format) -> bool {
+        matches!(
+            format,
+            Format::R8G8B8A8_UNORM
+                | Format::R8G8B8A8_SRGB
+                | Format::B8G8R8A8_UNORM
+                | Format::B8G8R8A8_SRGB
+        )
+    }
+
+    /// Converts tightly packed pixels from one of the supported formats
+    pub fn from_raw(data: &[u8], width: u32, height: u32, format: Format) -> Option<Self> {
+        if !Self::supports_format(format) || data.len() < (width * height * 4) as usize {
+            return None;
+        }
+
+        let mut pixels = data[..(width * height * 4) as usize].to_vec();
+
+        if matches!(format, Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn width(self: &Self) -> u32 {
+        self.width
+    }
+
+    pub fn height(self: &Self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(self: &Self) -> Vec<u8> {
+        self.pixels.clone()
+    }
+
+    /// Writes a PNG, or an EXR with linear colour if the path ends in `.exr`
+    pub fn save(self: &Self, path: impl AsRef<Path>) -> ImageResult<()> {
+        let path = path.as_ref();
+        let is_exr = path
+            .extension()
+            .map_or(false, |extension| extension.eq_ignore_ascii_case("exr"));
+
+        if is_exr {
+            // The output holds display values, EXR expects linear light
+            let pixels = self
+                .pixels
+                .chunks_exact(4)
+                .flat_map(|pixel| {
+                    [
+                        srgb_to_linear(pixel[0]),
+                        srgb_to_linear(pixel[1]),
+                        srgb_to_linear(pixel[2]),
+                        pixel[3] as f32 / 255.0,
+                    ]
+                })
+                .collect();
+
+            Rgba32FImage::from_raw(self.width, self.height, pixels)
+                .unwrap()
+                .save_with_format(path, ImageFormat::OpenExr)
+        } else {
+            RgbaImage::from_raw(self.width, self.height, self.pixels.clone())
+                .unwrap()
+                .save_with_format(path, ImageFormat::Png)
+        }
+    }
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Saves a numbered image per frame of a recording at a fixed frame rate
+pub struct Recorder {
+    directory: PathBuf,
+    extension: String,
+    fps: f64,
+    frame: u64,
+}
+
+impl Recorder {
+    /// Creates `directory` if needed. `extension` is `png` or `exr`
+    pub fn new(directory: impl Into<PathBuf>, fps: f64, extension: &str) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(Self {
+            directory,
+            extension: extension.to_string(),
+            fps,
+            frame: 0,
+        })
+    }
+
+    /// The number of the next frame to be saved
+    pub fn frame(self: &Self) -> u64 {
+        self.frame
+    }
+
+    /// The simulated time between frames, for a `Clock` in fixed step mode
+    pub fn timestep(self: &Self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.fps)
+    }
+
+    /// Saves the frame and moves on to the next, returning where it was written
+    pub fn save(self: &mut Self, frame: &CapturedFrame) -> ImageResult<PathBuf> {
+        let path = self
+            .directory
+            .join(format!("frame_{:06}.{}", self.frame, self.extension));
+
+        frame.save(&path)?;
+        self.frame += 1;
+
+        Ok(path)
+    }
+}
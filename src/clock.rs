@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+
+/// The step taken when stepping a paused real-time clock
+const DEFAULT_STEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Where a `Clock` gets the time that passes each tick from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClockMode {
+    /// The wall clock time since the previous tick
+    RealTime,
+    /// The same step every tick, however long the frame took, so runs are reproducible
+    FixedStep(Duration),
+}
+
+/// Simulated time for animation, advanced once per frame. Can be paused, stepped, scrubbed and
+/// scaled without affecting the wall clock
+#[derive(Clone, Debug)]
+pub struct Clock {
+    mode: ClockMode,
+    time: Duration,
+    dt: Duration,
+    scale: f64,
+    paused: bool,
+    pending_steps: u32,
+    frame: u64,
+    last_tick: Option<Instant>,
+}
+
+impl Clock {
+    pub fn new(mode: ClockMode) -> Self {
+        Self {
+            mode,
+            time: Duration::ZERO,
+            dt: Duration::ZERO,
+            scale: 1.0,
+            paused: false,
+            pending_steps: 0,
+            frame: 0,
+            last_tick: None,
+        }
+    }
+
+    pub fn real_time() -> Self {
+        Self::new(ClockMode::RealTime)
+    }
+
+    pub fn fixed_step(step: Duration) -> Self {
+        Self::new(ClockMode::FixedStep(step))
+    }
+
+    /// Advances the clock for a new frame and returns how much simulated time passed. The first
+    /// tick of a real-time clock doesn't advance it
+    pub fn tick(self: &mut Self) -> Duration {
+        let now = Instant::now();
+        let wall_dt = self
+            .last_tick
+            .map_or(Duration::ZERO, |last_tick| now - last_tick);
+        self.last_tick = Some(now);
+
+        self.dt = if !self.paused {
+            let dt = match self.mode {
+                ClockMode::RealTime => wall_dt,
+                ClockMode::FixedStep(step) => step,
+            };
+
+            dt.mul_f64(self.scale)
+        } else if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+
+            self.step_size()
+        } else {
+            Duration::ZERO
+        };
+
+        self.time += self.dt;
+        self.frame += 1;
+
+        self.dt
+    }
+
+    /// Simulated time since the clock started
+    pub fn time(self: &Self) -> Duration {
+        self.time
+    }
+
+    /// Simulated time that passed in the last tick
+    pub fn dt(self: &Self) -> Duration {
+        self.dt
+    }
+
+    /// How many times the clock has ticked
+    pub fn frame(self: &Self) -> u64 {
+        self.frame
+    }
+
+    pub fn mode(self: &Self) -> ClockMode {
+        self.mode
+    }
+
+    pub fn set_mode(self: &mut Self, mode: ClockMode) {
+        self.mode = mode;
+    }
+
+    pub fn is_paused(self: &Self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(self: &mut Self, paused: bool) {
+        self.paused = paused;
+        self.pending_steps = 0;
+    }
+
+    /// While paused, advances by a single step on the next tick. The step is the fixed step, or a
+    /// 60th of a second for a real-time clock, and isn't scaled
+    pub fn step(self: &mut Self) {
+        if self.paused {
+            self.pending_steps += 1;
+        }
+    }
+
+    pub fn scale(self: &Self) -> f64 {
+        self.scale
+    }
+
+    /// Multiplies how fast time passes, negative scales are treated as 0
+    pub fn set_scale(self: &mut Self, scale: f64) {
+        self.scale = scale.max(0.0);
+    }
+
+    /// Jumps straight to a time
+    pub fn seek(self: &mut Self, time: Duration) {
+        self.time = time;
+    }
+
+    /// Moves the time forwards, or backwards for negative seconds, stopping at 0
+    pub fn scrub(self: &mut Self, seconds: f64) {
+        let offset = Duration::from_secs_f64(seconds.abs());
+
+        self.time = if seconds < 0.0 {
+            self.time.saturating_sub(offset)
+        } else {
+            self.time + offset
+        };
+    }
+
+    fn step_size(self: &Self) -> Duration {
+        match self.mode {
+            ClockMode::RealTime => DEFAULT_STEP,
+            ClockMode::FixedStep(step) => step,
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::real_time()
+    }
+}
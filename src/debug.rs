@@ -0,0 +1,112 @@
+use vulkano::device::DeviceOwned;
+use vulkano::instance::debug::{DebugCallback, Message, MessageSeverity, MessageType};
+use vulkano::instance::{Instance, InstanceCreateInfo, InstanceExtensions};
+use vulkano::VulkanObject;
+
+use std::ffi::CString;
+use std::sync::Arc;
+
+use crate::error::RenderError;
+
+pub const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Creates an instance with the validation layer and `ext_debug_utils` enabled where they're
+/// available, so messages can be logged and objects named
+pub fn create_debug_instance() -> Result<Arc<Instance>, RenderError> {
+    let mut enabled_extensions = vulkano_win::required_extensions();
+    enabled_extensions.ext_debug_utils = InstanceExtensions::supported_by_core()
+        .map(|supported| supported.ext_debug_utils)
+        .unwrap_or(false);
+
+    if !enabled_extensions.ext_debug_utils {
+        log::warn!("ext_debug_utils not supported, debug messages and object names are disabled");
+    }
+
+    let validation_available = vulkano::instance::layers_list()
+        .map(|mut layers| layers.any(|layer| layer.name() == VALIDATION_LAYER))
+        .unwrap_or(false);
+
+    let enabled_layers = if validation_available {
+        vec![VALIDATION_LAYER.to_string()]
+    } else {
+        log::warn!(
+            "{} not installed, running without validation",
+            VALIDATION_LAYER
+        );
+
+        vec![]
+    };
+
+    Ok(Instance::new(InstanceCreateInfo {
+        enabled_extensions,
+        enabled_layers,
+        ..Default::default()
+    })?)
+}
+
+/// Forwards debug messages at or above `level` to the `log` crate under the `vulkan` target.
+/// Messages are only forwarded while the returned callback is alive
+pub fn log_messages(
+    instance: &Arc<Instance>,
+    level: log::LevelFilter,
+) -> Result<DebugCallback, RenderError> {
+    let severity = MessageSeverity {
+        error: level >= log::LevelFilter::Error,
+        warning: level >= log::LevelFilter::Warn,
+        information: level >= log::LevelFilter::Info,
+        verbose: level >= log::LevelFilter::Debug,
+    };
+
+    Ok(DebugCallback::new(
+        instance,
+        severity,
+        MessageType::all(),
+        |message: &Message| {
+            let level = if message.severity.error {
+                log::Level::Error
+            } else if message.severity.warning {
+                log::Level::Warn
+            } else if message.severity.information {
+                log::Level::Info
+            } else {
+                log::Level::Debug
+            };
+
+            let ty = if message.ty.validation {
+                "validation"
+            } else if message.ty.performance {
+                "performance"
+            } else {
+                "general"
+            };
+
+            log::log!(
+                target: "vulkan",
+                level,
+                "[{} {}] {}",
+                ty,
+                message.layer_prefix.unwrap_or("unknown"),
+                message.description
+            );
+        },
+    )?)
+}
+
+/// Names an object so validation messages and captures can refer to it. Does nothing unless the
+/// instance has `ext_debug_utils` enabled
+pub fn set_name<T>(object: &T, name: &str)
+where
+    T: VulkanObject + DeviceOwned,
+{
+    let device = object.device();
+
+    if !device.instance().enabled_extensions().ext_debug_utils {
+        return;
+    }
+
+    let name = CString::new(name).unwrap();
+
+    if let Err(e) = device.set_object_name(object, &name) {
+        log::warn!("Failed to name {:?}: {}", name, e);
+    }
+}
@@ -0,0 +1,177 @@
+use nalgebra_glm::{TMat4, TVec3, TVec4};
+
+use std::f32::consts::TAU;
+
+use crate::vertex::{CompactVec3, LineVertex};
+
+const SPHERE_SEGMENTS: usize = 32;
+
+/// Collects lines during a frame, which the renderer draws after lighting and then clears
+#[derive(Clone)]
+pub struct DebugDraw {
+    depth_tested: Vec<LineVertex>,
+    overlay: Vec<LineVertex>,
+    depth_test: bool,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the primitives added from now on are hidden behind the scene's geometry
+    pub fn set_depth_test(self: &mut Self, depth_test: bool) {
+        self.depth_test = depth_test;
+    }
+
+    pub fn line(self: &mut Self, start: CompactVec3, end: CompactVec3, colour: CompactVec3) {
+        let lines = if self.depth_test {
+            &mut self.depth_tested
+        } else {
+            &mut self.overlay
+        };
+
+        lines.push(LineVertex::new(start, colour));
+        lines.push(LineVertex::new(end, colour));
+    }
+
+    /// An axis aligned box between two opposite corners
+    pub fn aabb(self: &mut Self, min: CompactVec3, max: CompactVec3, colour: CompactVec3) {
+        let corner = |i: usize| {
+            [
+                if i & 1 == 0 { min[0] } else { max[0] },
+                if i & 2 == 0 { min[1] } else { max[1] },
+                if i & 4 == 0 { min[2] } else { max[2] },
+            ]
+        };
+
+        self.box_edges(corner, colour);
+    }
+
+    /// Three circles, one around each axis
+    pub fn sphere(self: &mut Self, centre: CompactVec3, radius: f32, colour: CompactVec3) {
+        let centre = TVec3::from(centre);
+        let axes = [TVec3::x(), TVec3::y(), TVec3::z()];
+
+        for i in 0..3 {
+            let u = axes[(i + 1) % 3] * radius;
+            let v = axes[(i + 2) % 3] * radius;
+            let point = |segment: usize| {
+                let angle = segment as f32 / SPHERE_SEGMENTS as f32 * TAU;
+                let point = centre + u * angle.cos() + v * angle.sin();
+
+                [point.x, point.y, point.z]
+            };
+
+            for segment in 0..SPHERE_SEGMENTS {
+                self.line(point(segment), point(segment + 1), colour);
+            }
+        }
+    }
+
+    /// The volume seen by a view projection matrix, such as one from `vp::get_vp`
+    pub fn frustum(self: &mut Self, view_proj: TMat4<f32>, colour: CompactVec3) {
+        let inverse = match view_proj.try_inverse() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+
+        // The projection is OpenGL style, so the near plane is at -1 rather than 0
+        let corner = |i: usize| {
+            let ndc = TVec4::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+                1.0,
+            );
+            let world = inverse * ndc;
+
+            [world.x / world.w, world.y / world.w, world.z / world.w]
+        };
+
+        self.box_edges(corner, colour);
+    }
+
+    /// The x, y and z axes of a transform in red, green and blue
+    pub fn axes(self: &mut Self, matrix: TMat4<f32>, length: f32) {
+        let origin = matrix * TVec4::new(0.0, 0.0, 0.0, 1.0);
+        let origin = [origin.x, origin.y, origin.z];
+
+        for (axis, colour) in [
+            (TVec4::new(length, 0.0, 0.0, 1.0), [1.0, 0.0, 0.0]),
+            (TVec4::new(0.0, length, 0.0, 1.0), [0.0, 1.0, 0.0]),
+            (TVec4::new(0.0, 0.0, length, 1.0), [0.0, 0.0, 1.0]),
+        ] {
+            let end = matrix * axis;
+
+            self.line(origin, [end.x, end.y, end.z], colour);
+        }
+    }
+
+    /// A square grid on the xz plane with `divisions` cells along each side
+    pub fn grid(
+        self: &mut Self,
+        centre: CompactVec3,
+        size: f32,
+        divisions: u32,
+        colour: CompactVec3,
+    ) {
+        let half = size * 0.5;
+        let divisions = divisions.max(1);
+
+        for i in 0..=divisions {
+            let offset = i as f32 / divisions as f32 * size - half;
+
+            self.line(
+                [centre[0] + offset, centre[1], centre[2] - half],
+                [centre[0] + offset, centre[1], centre[2] + half],
+                colour,
+            );
+            self.line(
+                [centre[0] - half, centre[1], centre[2] + offset],
+                [centre[0] + half, centre[1], centre[2] + offset],
+                colour,
+            );
+        }
+    }
+
+    /// A line list of everything hidden by the scene, or drawn over it
+    pub fn vertices(self: &Self, depth_tested: bool) -> Vec<LineVertex> {
+        if depth_tested {
+            self.depth_tested.clone()
+        } else {
+            self.overlay.clone()
+        }
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        self.depth_tested.is_empty() && self.overlay.is_empty()
+    }
+
+    /// Removes everything drawn so far, keeping the depth test setting
+    pub fn clear(self: &mut Self) {
+        self.depth_tested.clear();
+        self.overlay.clear();
+    }
+
+    /// The twelve edges of a box, with corners numbered by their bits as x, y, z
+    fn box_edges(self: &mut Self, corner: impl Fn(usize) -> CompactVec3, colour: CompactVec3) {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), colour);
+                }
+            }
+        }
+    }
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self {
+            depth_tested: Vec::new(),
+            overlay: Vec::new(),
+            depth_test: true,
+        }
+    }
+}
@@ -0,0 +1,73 @@
+/// What the lighting subpass writes to the output, for inspecting the G-buffer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugView {
+    /// The normal lit output
+    Lit,
+    Normals,
+    Albedo,
+    /// Depth scaled so the far plane is white
+    Depth,
+    WorldPosition,
+    /// Diffuse and specular light from a single light in the scene, without ambient or albedo
+    Light(usize),
+}
+
+impl DebugView {
+    /// The mode number understood by the lighting shader
+    pub fn mode(self: &Self) -> u32 {
+        match self {
+            DebugView::Lit => 0,
+            DebugView::Normals => 1,
+            DebugView::Albedo => 2,
+            DebugView::Depth => 3,
+            DebugView::WorldPosition => 4,
+            DebugView::Light(_) => 5,
+        }
+    }
+
+    /// 1 is lit, 2 to 5 are the G-buffer views and 6 to 9 show the first four lights
+    pub fn from_number(number: u32) -> Option<Self> {
+        match number {
+            1 => Some(DebugView::Lit),
+            2 => Some(DebugView::Normals),
+            3 => Some(DebugView::Albedo),
+            4 => Some(DebugView::Depth),
+            5 => Some(DebugView::WorldPosition),
+            6..=9 => Some(DebugView::Light((number - 6) as usize)),
+            _ => None,
+        }
+    }
+}
+
+impl Default for DebugView {
+    fn default() -> Self {
+        DebugView::Lit
+    }
+}
+
+/// How mesh edges are drawn on top of, or instead of, the lit output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wireframe {
+    Off,
+    /// Only the edges, including those hidden behind other faces
+    Only,
+    /// Visible edges drawn over the lit output
+    Overlay,
+}
+
+impl Wireframe {
+    /// Cycles off, wireframe only and overlay
+    pub fn next(self: &Self) -> Self {
+        match self {
+            Wireframe::Off => Wireframe::Only,
+            Wireframe::Only => Wireframe::Overlay,
+            Wireframe::Overlay => Wireframe::Off,
+        }
+    }
+}
+
+impl Default for Wireframe {
+    fn default() -> Self {
+        Wireframe::Off
+    }
+}
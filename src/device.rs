@@ -0,0 +1,187 @@
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::instance::Instance;
+use vulkano::{Version, VulkanObject};
+
+use std::convert::Infallible;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Overrides the automatic device choice when no selector is passed in
+pub const DEVICE_ENV_VAR: &str = "GRAPHICS_DEVICE";
+
+/// Formats the G-buffer needs, and whether each is used as a colour or depth attachment
+const GBUFFER_FORMATS: [(Format, bool); 4] = [
+    (Format::D16_UNORM, true),
+    (Format::R16G16B16A16_SFLOAT, false),
+    (Format::A2B10G10R10_UNORM_PACK32, false),
+    (Format::R8G8B8A8_UNORM, false),
+];
+
+/// Picks a physical device by its enumeration index or by a case insensitive part of its name
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeviceSelector {
+    Index(usize),
+    Name(String),
+}
+
+impl DeviceSelector {
+    /// Reads the selector from `GRAPHICS_DEVICE`, if it's set
+    pub fn from_env() -> Option<Self> {
+        env::var(DEVICE_ENV_VAR)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| value.parse().unwrap())
+    }
+
+    pub fn matches(self: &Self, physical_device: &PhysicalDevice) -> bool {
+        match self {
+            DeviceSelector::Index(index) => physical_device.index() == *index,
+            DeviceSelector::Name(name) => physical_device
+                .properties()
+                .device_name
+                .to_lowercase()
+                .contains(&name.to_lowercase()),
+        }
+    }
+}
+
+impl FromStr for DeviceSelector {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        Ok(match s.parse() {
+            Ok(index) => DeviceSelector::Index(index),
+            Err(_) => DeviceSelector::Name(s.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for DeviceSelector {
+    fn fmt(self: &Self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeviceSelector::Index(index) => write!(f, "device {}", index),
+            DeviceSelector::Name(name) => write!(f, "device named \"{}\"", name),
+        }
+    }
+}
+
+/// Describes every physical device, in the order used by `DeviceSelector::Index`
+pub fn device_report(instance: &Arc<Instance>) -> String {
+    let mut report = String::new();
+
+    for physical_device in PhysicalDevice::enumerate(instance) {
+        let properties = physical_device.properties();
+
+        report += &format!(
+            "{}: {} ({:?})\n",
+            physical_device.index(),
+            properties.device_name,
+            properties.device_type
+        );
+
+        report += &format!(
+            "    driver: {} {} (version {:#x}), api {}\n",
+            properties.driver_name.as_deref().unwrap_or("unknown"),
+            properties.driver_info.as_deref().unwrap_or(""),
+            properties.driver_version,
+            physical_device.api_version()
+        );
+
+        for (i, heap) in physical_device.memory_heaps().enumerate() {
+            report += &format!(
+                "    heap {}: {} MiB{}\n",
+                i,
+                heap.size() / (1024 * 1024),
+                if heap.is_device_local() {
+                    ", device local"
+                } else {
+                    ""
+                }
+            );
+        }
+
+        for (format, depth) in GBUFFER_FORMATS {
+            let features = physical_device
+                .format_properties(format)
+                .optimal_tiling_features;
+            let supported = if depth {
+                features.depth_stencil_attachment
+            } else {
+                features.color_attachment
+            };
+
+            report += &format!(
+                "    {:?} {} attachment: {}\n",
+                format,
+                if depth { "depth" } else { "colour" },
+                if supported { "yes" } else { "no" }
+            );
+        }
+    }
+
+    report
+}
+
+/// Device local memory in bytes, as reported by `ext_memory_budget`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GpuMemory {
+    /// Allocated by this process
+    pub used: u64,
+    /// How much this process can allocate before performance suffers
+    pub budget: u64,
+}
+
+/// Current device local memory use, or `None` if the device doesn't have `ext_memory_budget`
+/// enabled
+pub fn gpu_memory_usage(device: &Arc<Device>) -> Option<GpuMemory> {
+    let instance = device.instance();
+
+    if !device.enabled_extensions().ext_memory_budget {
+        return None;
+    }
+
+    let mut budget = ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut properties = ash::vk::PhysicalDeviceMemoryProperties2 {
+        p_next: &mut budget as *mut _ as *mut _,
+        ..Default::default()
+    };
+
+    let physical_device = device.physical_device().internal_object();
+    let fns = instance.fns();
+
+    unsafe {
+        if instance.api_version() >= Version::V1_1 {
+            fns.v1_1
+                .get_physical_device_memory_properties2(physical_device, &mut properties);
+        } else if instance
+            .enabled_extensions()
+            .khr_get_physical_device_properties2
+        {
+            fns.khr_get_physical_device_properties2
+                .get_physical_device_memory_properties2_khr(physical_device, &mut properties);
+        } else {
+            return None;
+        }
+    }
+
+    let memory_properties = properties.memory_properties;
+    let mut usage = GpuMemory::default();
+
+    for i in 0..memory_properties.memory_heap_count as usize {
+        if memory_properties.memory_heaps[i]
+            .flags
+            .contains(ash::vk::MemoryHeapFlags::DEVICE_LOCAL)
+        {
+            usage.used += budget.heap_usage[i];
+            usage.budget += budget.heap_budget[i];
+        }
+    }
+
+    Some(usage)
+}
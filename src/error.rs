@@ -0,0 +1,352 @@
+use vulkano::buffer::cpu_access::ReadLockError;
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilderContextError, BeginRenderPassError, BuildError, CommandBufferExecError,
+    CopyBufferImageError, DrawError, DrawIndexedError, ResetQueryPoolError, WriteTimestampError,
+};
+use vulkano::descriptor_set::DescriptorSetCreationError;
+use vulkano::device::physical::SurfacePropertiesError;
+use vulkano::device::DeviceCreationError;
+use vulkano::image::view::ImageViewCreationError;
+use vulkano::image::ImageCreationError;
+use vulkano::instance::debug::DebugCallbackCreationError;
+use vulkano::instance::InstanceCreationError;
+use vulkano::memory::DeviceMemoryAllocationError;
+use vulkano::pipeline::graphics::GraphicsPipelineCreationError;
+use vulkano::query::{GetResultsError, QueryPoolCreationError};
+use vulkano::render_pass::{FramebufferCreationError, RenderPassCreationError};
+use vulkano::sampler::SamplerCreationError;
+use vulkano::shader::ShaderCreationError;
+use vulkano::swapchain::{AcquireError, SurfaceCreationError, SwapchainCreationError};
+use vulkano::sync::FlushError;
+use vulkano::OomError;
+
+use std::path::PathBuf;
+use std::{error, fmt, io};
+
+use crate::device::DeviceSelector;
+
+#[derive(Debug)]
+pub enum RenderError {
+    /// No physical device supports the required extensions and queues
+    NoDevice,
+    /// The selected device doesn't exist or doesn't support the required extensions and queues
+    DeviceNotFound(DeviceSelector),
+    /// The surface has no area, such as when the window is minimised
+    ZeroExtent,
+    /// The device was created without the queue it was asked for
+    NoQueue,
+    /// A shader module has no `main` function
+    MissingEntryPoint,
+    /// The render pass doesn't have a subpass the pipelines are built for
+    MissingSubpass(u32),
+    /// A pipeline's layout doesn't have the descriptor set the renderer binds
+    MissingDescriptorSet(u32),
+    Io(io::Error),
+    Oom(OomError),
+    InstanceCreation(InstanceCreationError),
+    DebugCallbackCreation(DebugCallbackCreationError),
+    DeviceCreation(DeviceCreationError),
+    SurfaceCreation(SurfaceCreationError),
+    SurfaceProperties(SurfacePropertiesError),
+    SwapchainCreation(SwapchainCreationError),
+    RenderPassCreation(RenderPassCreationError),
+    FramebufferCreation(FramebufferCreationError),
+    ImageCreation(ImageCreationError),
+    ImageViewCreation(ImageViewCreationError),
+    SamplerCreation(SamplerCreationError),
+    ShaderCreation(ShaderCreationError),
+    /// A reloaded shader's descriptors or push constants differ from the ones the renderer binds
+    ShaderLayout(String),
+    PipelineCreation(GraphicsPipelineCreationError),
+    DescriptorSetCreation(DescriptorSetCreationError),
+    QueryPoolCreation(QueryPoolCreationError),
+    QueryResults(GetResultsError),
+    MemoryAllocation(DeviceMemoryAllocationError),
+    BufferRead(ReadLockError),
+    CommandBufferContext(AutoCommandBufferBuilderContextError),
+    BeginRenderPass(BeginRenderPassError),
+    Draw(DrawIndexedError),
+    DrawNonIndexed(DrawError),
+    CopyImage(CopyBufferImageError),
+    WriteTimestamp(WriteTimestampError),
+    ResetQueryPool(ResetQueryPoolError),
+    CommandBufferBuild(BuildError),
+    CommandBufferExec(CommandBufferExecError),
+    Acquire(AcquireError),
+    Flush(FlushError),
+}
+
+impl RenderError {
+    /// The device has to be recreated, along with everything that was created from it
+    pub fn is_device_lost(self: &Self) -> bool {
+        matches!(
+            self,
+            RenderError::DeviceCreation(DeviceCreationError::DeviceLost)
+                | RenderError::SwapchainCreation(SwapchainCreationError::DeviceLost)
+                | RenderError::Acquire(AcquireError::DeviceLost)
+                | RenderError::Flush(FlushError::DeviceLost)
+                | RenderError::QueryResults(GetResultsError::DeviceLost)
+        )
+    }
+
+    /// Memory may be freed up once the frames in flight have finished, so the frame can be skipped
+    pub fn is_out_of_memory(self: &Self) -> bool {
+        matches!(
+            self,
+            RenderError::Oom(_)
+                | RenderError::MemoryAllocation(DeviceMemoryAllocationError::OomError(_))
+                | RenderError::ImageCreation(ImageCreationError::AllocError(
+                    DeviceMemoryAllocationError::OomError(_)
+                ))
+                | RenderError::SwapchainCreation(SwapchainCreationError::OomError(_))
+                | RenderError::DescriptorSetCreation(DescriptorSetCreationError::OomError(_))
+                | RenderError::CommandBufferBuild(BuildError::OomError(_))
+                | RenderError::Acquire(AcquireError::OomError(_))
+                | RenderError::Flush(FlushError::OomError(_))
+        )
+    }
+
+    /// The swapchain no longer matches the surface and has to be recreated
+    pub fn is_out_of_date(self: &Self) -> bool {
+        matches!(
+            self,
+            RenderError::Acquire(AcquireError::OutOfDate)
+                | RenderError::Flush(FlushError::OutOfDate)
+                | RenderError::SwapchainCreation(
+                    SwapchainCreationError::ImageExtentNotSupported { .. }
+                )
+        )
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(self: &Self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::NoDevice => write!(f, "no device available"),
+            RenderError::DeviceNotFound(selector) => write!(f, "no suitable {} found", selector),
+            RenderError::ZeroExtent => write!(f, "surface has no area"),
+            RenderError::NoQueue => write!(f, "device has no queue"),
+            RenderError::MissingEntryPoint => write!(f, "shader has no main entry point"),
+            RenderError::MissingSubpass(i) => write!(f, "render pass has no subpass {}", i),
+            RenderError::MissingDescriptorSet(i) => {
+                write!(f, "pipeline layout has no descriptor set {}", i)
+            }
+            RenderError::Io(e) => write!(f, "io error: {}", e),
+            RenderError::Oom(e) => write!(f, "out of memory: {}", e),
+            RenderError::InstanceCreation(e) => write!(f, "failed to create instance: {}", e),
+            RenderError::DebugCallbackCreation(e) => {
+                write!(f, "failed to create debug callback: {}", e)
+            }
+            RenderError::DeviceCreation(e) => write!(f, "failed to create device: {}", e),
+            RenderError::SurfaceCreation(e) => write!(f, "failed to create surface: {}", e),
+            RenderError::SurfaceProperties(e) => {
+                write!(f, "failed to get surface properties: {}", e)
+            }
+            RenderError::SwapchainCreation(e) => write!(f, "failed to create swapchain: {}", e),
+            RenderError::RenderPassCreation(e) => {
+                write!(f, "failed to create render pass: {}", e)
+            }
+            RenderError::FramebufferCreation(e) => {
+                write!(f, "failed to create framebuffer: {}", e)
+            }
+            RenderError::ImageCreation(e) => write!(f, "failed to create image: {}", e),
+            RenderError::ImageViewCreation(e) => write!(f, "failed to create image view: {}", e),
+            RenderError::SamplerCreation(e) => write!(f, "failed to create sampler: {}", e),
+            RenderError::ShaderCreation(e) => write!(f, "failed to load shader: {}", e),
+            RenderError::ShaderLayout(reason) => write!(f, "shader layout changed: {}", reason),
+            RenderError::PipelineCreation(e) => write!(f, "failed to create pipeline: {}", e),
+            RenderError::DescriptorSetCreation(e) => {
+                write!(f, "failed to create descriptor set: {}", e)
+            }
+            RenderError::QueryPoolCreation(e) => write!(f, "failed to create query pool: {}", e),
+            RenderError::QueryResults(e) => write!(f, "failed to read query results: {}", e),
+            RenderError::MemoryAllocation(e) => write!(f, "failed to allocate buffer: {}", e),
+            RenderError::BufferRead(e) => write!(f, "failed to read buffer: {}", e),
+            RenderError::CommandBufferContext(e) => {
+                write!(f, "failed to record command buffer: {}", e)
+            }
+            RenderError::BeginRenderPass(e) => write!(f, "failed to begin render pass: {}", e),
+            RenderError::Draw(e) => write!(f, "failed to record draw: {}", e),
+            RenderError::DrawNonIndexed(e) => write!(f, "failed to record draw: {}", e),
+            RenderError::CopyImage(e) => write!(f, "failed to record image copy: {}", e),
+            RenderError::WriteTimestamp(e) => write!(f, "failed to record timestamp: {}", e),
+            RenderError::ResetQueryPool(e) => write!(f, "failed to reset queries: {}", e),
+            RenderError::CommandBufferBuild(e) => {
+                write!(f, "failed to build command buffer: {}", e)
+            }
+            RenderError::CommandBufferExec(e) => {
+                write!(f, "failed to execute command buffer: {}", e)
+            }
+            RenderError::Acquire(e) => write!(f, "failed to acquire next image: {}", e),
+            RenderError::Flush(e) => write!(f, "failed to flush future: {}", e),
+        }
+    }
+}
+
+impl error::Error for RenderError {
+    fn source(self: &Self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RenderError::NoDevice => None,
+            RenderError::DeviceNotFound(_) => None,
+            RenderError::ZeroExtent => None,
+            RenderError::NoQueue => None,
+            RenderError::MissingEntryPoint => None,
+            RenderError::MissingSubpass(_) => None,
+            RenderError::MissingDescriptorSet(_) => None,
+            RenderError::Io(e) => Some(e),
+            RenderError::Oom(e) => Some(e),
+            RenderError::InstanceCreation(e) => Some(e),
+            RenderError::DebugCallbackCreation(e) => Some(e),
+            RenderError::DeviceCreation(e) => Some(e),
+            RenderError::SurfaceCreation(e) => Some(e),
+            RenderError::SurfaceProperties(e) => Some(e),
+            RenderError::SwapchainCreation(e) => Some(e),
+            RenderError::RenderPassCreation(e) => Some(e),
+            RenderError::FramebufferCreation(e) => Some(e),
+            RenderError::ImageCreation(e) => Some(e),
+            RenderError::ImageViewCreation(e) => Some(e),
+            RenderError::SamplerCreation(e) => Some(e),
+            RenderError::ShaderCreation(e) => Some(e),
+            RenderError::ShaderLayout(_) => None,
+            RenderError::PipelineCreation(e) => Some(e),
+            RenderError::DescriptorSetCreation(e) => Some(e),
+            RenderError::QueryPoolCreation(e) => Some(e),
+            RenderError::QueryResults(e) => Some(e),
+            RenderError::MemoryAllocation(e) => Some(e),
+            RenderError::BufferRead(e) => Some(e),
+            RenderError::CommandBufferContext(e) => Some(e),
+            RenderError::BeginRenderPass(e) => Some(e),
+            RenderError::Draw(e) => Some(e),
+            RenderError::DrawNonIndexed(e) => Some(e),
+            RenderError::CopyImage(e) => Some(e),
+            RenderError::WriteTimestamp(e) => Some(e),
+            RenderError::ResetQueryPool(e) => Some(e),
+            RenderError::CommandBufferBuild(e) => Some(e),
+            RenderError::CommandBufferExec(e) => Some(e),
+            RenderError::Acquire(e) => Some(e),
+            RenderError::Flush(e) => Some(e),
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($($variant:ident($error:ty)),+ $(,)?) => {
+        $(
+            impl From<$error> for RenderError {
+                fn from(e: $error) -> Self {
+                    RenderError::$variant(e)
+                }
+            }
+        )+
+    };
+}
+
+impl_from!(
+    Io(io::Error),
+    Oom(OomError),
+    InstanceCreation(InstanceCreationError),
+    DebugCallbackCreation(DebugCallbackCreationError),
+    DeviceCreation(DeviceCreationError),
+    SurfaceCreation(SurfaceCreationError),
+    SurfaceProperties(SurfacePropertiesError),
+    SwapchainCreation(SwapchainCreationError),
+    RenderPassCreation(RenderPassCreationError),
+    FramebufferCreation(FramebufferCreationError),
+    ImageCreation(ImageCreationError),
+    ImageViewCreation(ImageViewCreationError),
+    SamplerCreation(SamplerCreationError),
+    ShaderCreation(ShaderCreationError),
+    PipelineCreation(GraphicsPipelineCreationError),
+    DescriptorSetCreation(DescriptorSetCreationError),
+    QueryPoolCreation(QueryPoolCreationError),
+    QueryResults(GetResultsError),
+    MemoryAllocation(DeviceMemoryAllocationError),
+    BufferRead(ReadLockError),
+    CommandBufferContext(AutoCommandBufferBuilderContextError),
+    BeginRenderPass(BeginRenderPassError),
+    Draw(DrawIndexedError),
+    DrawNonIndexed(DrawError),
+    CopyImage(CopyBufferImageError),
+    WriteTimestamp(WriteTimestampError),
+    ResetQueryPool(ResetQueryPoolError),
+    CommandBufferBuild(BuildError),
+    CommandBufferExec(CommandBufferExecError),
+    Acquire(AcquireError),
+    Flush(FlushError),
+);
+
+/// Failures loading or saving scenes and the meshes they refer to
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// A mesh file is malformed, with the line the problem is on counting from 1
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// A binary mesh file is malformed
+    InvalidMesh(String),
+    /// A glTF file is malformed or uses something which isn't supported
+    Gltf(String),
+    /// A skeleton's joints don't form a hierarchy
+    InvalidSkeleton(String),
+    /// A scene file's values are out of range
+    InvalidScene(String),
+    /// An image file couldn't be decoded
+    Image(image::ImageError),
+    /// A file's extension isn't one of the formats that can be loaded
+    UnsupportedFormat(PathBuf),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(self: &Self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "io error: {}", e),
+            SceneError::Json(e) => write!(f, "invalid scene: {}", e),
+            SceneError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            SceneError::InvalidMesh(message) => write!(f, "invalid mesh: {}", message),
+            SceneError::Gltf(message) => write!(f, "invalid glTF: {}", message),
+            SceneError::InvalidSkeleton(message) => write!(f, "invalid skeleton: {}", message),
+            SceneError::InvalidScene(message) => write!(f, "invalid scene: {}", message),
+            SceneError::Image(e) => write!(f, "invalid image: {}", e),
+            SceneError::UnsupportedFormat(path) => {
+                write!(f, "unsupported file format: {}", path.display())
+            }
+        }
+    }
+}
+
+impl error::Error for SceneError {
+    fn source(self: &Self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SceneError::Io(e) => Some(e),
+            SceneError::Json(e) => Some(e),
+            SceneError::Image(e) => Some(e),
+            SceneError::Parse { .. }
+            | SceneError::InvalidMesh(_)
+            | SceneError::Gltf(_)
+            | SceneError::InvalidSkeleton(_)
+            | SceneError::InvalidScene(_)
+            | SceneError::UnsupportedFormat(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(e: io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for SceneError {
+    fn from(e: image::ImageError) -> Self {
+        SceneError::Image(e)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(e: serde_json::Error) -> Self {
+        SceneError::Json(e)
+    }
+}
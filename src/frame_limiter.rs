@@ -0,0 +1,39 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Sleeps between frames to hold a target frame rate
+pub struct FrameLimiter {
+    frame_time: Option<Duration>,
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    /// A limiter which never waits if `target_fps` is `None`
+    pub fn new(target_fps: Option<f64>) -> Self {
+        Self {
+            frame_time: target_fps
+                .filter(|&fps| fps > 0.0)
+                .map(|fps| Duration::from_secs_f64(1.0 / fps)),
+            next_frame: Instant::now(),
+        }
+    }
+
+    /// Blocks until the next frame is due
+    pub fn wait(self: &mut Self) {
+        let frame_time = match self.frame_time {
+            Some(frame_time) => frame_time,
+            None => return,
+        };
+
+        let now = Instant::now();
+
+        if now < self.next_frame {
+            thread::sleep(self.next_frame - now);
+
+            self.next_frame += frame_time;
+        } else {
+            // Running behind, start again from now rather than catching up with a burst of frames
+            self.next_frame = now + frame_time;
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use nalgebra_glm::{TVec2, TVec3};
+
+use std::collections::HashMap;
+
+use crate::model::Model;
+use crate::morph::{Morph, MorphTarget};
+use crate::skeleton::Skin;
+use crate::vertex::{CompactVec3, Index, Vertex};
+
+/// Which way round a triangle's corners go when seen from the front
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Winding {
+    /// The cube's winding, which the pipelines treat as front facing
+    #[default]
+    Clockwise,
+    /// Used by OBJ, glTF, PLY and STL files
+    CounterClockwise,
+}
+
+impl Winding {
+    /// Points out of the front of the triangle, with a length of twice its area
+    pub fn face_normal(self: Self, [a, b, c]: [TVec3<f32>; 3]) -> TVec3<f32> {
+        let normal = (b - a).cross(&(c - a));
+
+        match self {
+            Winding::Clockwise => -normal,
+            Winding::CounterClockwise => normal,
+        }
+    }
+}
+
+/// The same triangle wound the other way, starting from the same corner. OBJ, glTF, PLY and STL
+/// triangles are counter-clockwise from the front, the opposite of the pipelines, so they're
+/// reversed as they're read and reversed back as they're written
+pub fn reverse_triangle<T>([a, b, c]: [T; 3]) -> [T; 3] {
+    [a, c, b]
+}
+
+/// How the faces around a vertex are combined into its normal
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NormalWeighting {
+    /// Larger faces count for more
+    #[default]
+    Area,
+    /// Faces count by the angle of their corner at the vertex, so the result doesn't depend on how
+    /// the faces are split into triangles
+    Angle,
+}
+
+/// Something wrong with a model's triangles, found by `validate`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshIssue {
+    /// The index count isn't a multiple of three, so the last triangle is incomplete
+    IncompleteTriangle,
+    IndexOutOfRange {
+        triangle: usize,
+        index: Index,
+    },
+    /// The corners are the same vertex, in the same place or in a line, so it has no area
+    DegenerateTriangle {
+        triangle: usize,
+    },
+}
+
+/// Merges vertices whose position, normal, colour and texture coordinates are all within
+/// `epsilon` of each other, keeping the first. Skin weights must match exactly and morph deltas
+/// within `epsilon` too
+pub fn weld(model: &Model, epsilon: f32) -> Model {
+    let vertices = model.vertices();
+    let skin_vertices = model.skin().map(Skin::vertices);
+    let morph_targets = model.morph().map(Morph::targets);
+
+    let close = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(a, b)| (a - b).abs() <= epsilon);
+    let same = |i: usize, j: usize| {
+        let (a, b) = (&vertices[i], &vertices[j]);
+
+        close(&a.normal, &b.normal)
+            && close(&a.colour, &b.colour)
+            && close(&a.uv, &b.uv)
+            && skin_vertices
+                .as_ref()
+                .map(|skin| skin[i] == skin[j])
+                .unwrap_or(true)
+            && morph_targets
+                .map(|targets| {
+                    targets.iter().all(|target| {
+                        let (a, b) = (target.delta(i), target.delta(j));
+
+                        close(&a.0, &b.0) && close(&a.1, &b.1)
+                    })
+                })
+                .unwrap_or(true)
+    };
+
+    let positions: Vec<CompactVec3> = vertices.iter().map(|v| v.position).collect();
+    let (kept, remap) = merge_close(&positions, epsilon, same);
+
+    let indices = model
+        .indices()
+        .iter()
+        .map(|&i| remap[i as usize] as Index)
+        .collect();
+
+    let kept_vertices = kept.iter().map(|&i| vertices[i]).collect();
+
+    with_vertex_subset(model, &kept, kept_vertices, indices)
+}
+
+/// The normal of each vertex from the faces which use it. Vertices which aren't used by a
+/// triangle with area get a zero normal
+pub fn vertex_normals(
+    positions: &[CompactVec3],
+    indices: &[Index],
+    weighting: NormalWeighting,
+    winding: Winding,
+) -> Vec<CompactVec3> {
+    let mut normals = vec![TVec3::<f32>::zeros(); positions.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let corners = [0, 1, 2].map(|i| TVec3::from(positions[triangle[i] as usize]));
+        let face_normal = winding.face_normal(corners);
+
+        for corner in 0..3 {
+            let weight = match weighting {
+                NormalWeighting::Area => face_normal,
+                NormalWeighting::Angle => match face_normal.try_normalize(f32::EPSILON) {
+                    Some(unit) => unit * corner_angle(corners, corner),
+                    None => continue,
+                },
+            };
+
+            normals[triangle[corner] as usize] += weight;
+        }
+    }
+
+    normals
+        .into_iter()
+        .map(|normal| {
+            normal
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(TVec3::zeros)
+                .into()
+        })
+        .collect()
+}
+
+/// Replaces the model's normals with ones from its faces. Only vertices shared between faces are
+/// smoothed, so `weld` first to smooth across duplicated vertices
+pub fn recompute_normals(model: &Model, weighting: NormalWeighting, winding: Winding) -> Model {
+    let positions: Vec<CompactVec3> = model.vertices().iter().map(|v| v.position).collect();
+    let normals = vertex_normals(&positions, &model.indices(), weighting, winding);
+
+    let vertices = model
+        .vertices()
+        .iter()
+        .zip(normals)
+        .map(|(v, normal)| Vertex { normal, ..*v })
+        .collect();
+
+    with_geometry(model, vertices, model.indices())
+}
+
+/// Reverses the order of every triangle's corners, turning the faces around. The normals are left
+/// as they are
+pub fn flip_winding(model: &Model) -> Model {
+    let indices = model
+        .indices()
+        .chunks_exact(3)
+        .flat_map(|triangle| reverse_triangle([triangle[0], triangle[1], triangle[2]]))
+        .collect();
+
+    with_geometry(model, model.vertices(), indices)
+}
+
+/// A tangent for each vertex pointing along increasing u, with the sign of the bitangent in w,
+/// following the MikkTSpace conventions: the bitangent is `w * cross(normal, tangent)` and faces
+/// are weighted by their corner angles. Tangents are averaged over each vertex rather than split
+/// where they disagree, so results can differ from MikkTSpace along mirrored UV seams. Vertices
+/// without usable texture coordinates get an arbitrary tangent at right angles to the normal
+pub fn tangents(model: &Model) -> Vec<[f32; 4]> {
+    let vertices = model.vertices();
+
+    let mut tangents = vec![TVec3::<f32>::zeros(); vertices.len()];
+    let mut bitangents = vec![TVec3::<f32>::zeros(); vertices.len()];
+
+    for triangle in model.indices().chunks_exact(3) {
+        let corners = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+        let positions = corners.map(|v| TVec3::from(v.position));
+        let uvs = corners.map(|v| TVec2::from(v.uv));
+
+        let (edge_1, edge_2) = (positions[1] - positions[0], positions[2] - positions[0]);
+        let (uv_1, uv_2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+
+        let determinant = uv_1.x * uv_2.y - uv_2.x * uv_1.y;
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+
+        let tangent = (edge_1 * uv_2.y - edge_2 * uv_1.y) / determinant;
+        let bitangent = (edge_2 * uv_1.x - edge_1 * uv_2.x) / determinant;
+
+        for corner in 0..3 {
+            let angle = corner_angle(positions, corner);
+
+            tangents[triangle[corner] as usize] += tangent * angle;
+            bitangents[triangle[corner] as usize] += bitangent * angle;
+        }
+    }
+
+    vertices
+        .iter()
+        .zip(tangents.iter().zip(&bitangents))
+        .map(|(v, (tangent, bitangent))| {
+            let normal = TVec3::from(v.normal);
+
+            // Gram-Schmidt, so the tangent is at right angles to the normal
+            let tangent = (tangent - normal * normal.dot(tangent))
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(|| perpendicular(&normal));
+
+            let sign = if normal.cross(&tangent).dot(bitangent) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+
+            [tangent.x, tangent.y, tangent.z, sign]
+        })
+        .collect()
+}
+
+/// Every problem with the model's triangles, in order
+pub fn validate(model: &Model) -> Vec<MeshIssue> {
+    let vertices = model.vertices();
+    let indices = model.indices();
+
+    let mut issues = Vec::new();
+
+    for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+        if let Some(&index) = corners.iter().find(|&&i| i as usize >= vertices.len()) {
+            issues.push(MeshIssue::IndexOutOfRange { triangle, index });
+
+            continue;
+        }
+
+        let positions = [0, 1, 2].map(|i| TVec3::from(vertices[corners[i] as usize].position));
+        let longest = (0..3)
+            .map(|i| (positions[(i + 1) % 3] - positions[i]).norm())
+            .fold(0.0, f32::max);
+        let area = (positions[1] - positions[0])
+            .cross(&(positions[2] - positions[0]))
+            .norm();
+
+        if area <= f32::EPSILON * longest * longest {
+            issues.push(MeshIssue::DegenerateTriangle { triangle });
+        }
+    }
+
+    if !indices.chunks_exact(3).remainder().is_empty() {
+        issues.push(MeshIssue::IncompleteTriangle);
+    }
+
+    issues
+}
+
+/// The angle inside the triangle at one of its corners
+fn corner_angle(positions: [TVec3<f32>; 3], corner: usize) -> f32 {
+    let at = positions[corner];
+    let to_next = positions[(corner + 1) % 3] - at;
+    let to_previous = positions[(corner + 2) % 3] - at;
+
+    match (
+        to_next.try_normalize(f32::EPSILON),
+        to_previous.try_normalize(f32::EPSILON),
+    ) {
+        (Some(a), Some(b)) => a.dot(&b).clamp(-1.0, 1.0).acos(),
+        _ => 0.0,
+    }
+}
+
+/// Any unit vector at right angles to `normal`
+fn perpendicular(normal: &TVec3<f32>) -> TVec3<f32> {
+    let axis = if normal.x.abs() < 0.9 {
+        TVec3::x()
+    } else {
+        TVec3::y()
+    };
+
+    normal
+        .cross(&axis)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or(axis)
+}
+
+/// Groups positions within `epsilon` of each other on every axis, if `same` also agrees. Returns
+/// the first of each group, and which group each position is in
+pub(crate) fn merge_close(
+    positions: &[CompactVec3],
+    epsilon: f32,
+    same: impl Fn(usize, usize) -> bool,
+) -> (Vec<usize>, Vec<usize>) {
+    let close =
+        |a: &CompactVec3, b: &CompactVec3| a.iter().zip(b).all(|(a, b)| (a - b).abs() <= epsilon);
+
+    // Positions are bucketed into cells `epsilon` wide, so only the neighbouring cells need
+    // searching. Without a tolerance only identical positions merge, so they're bucketed by their
+    // bits, with -0 made +0
+    let exact = epsilon <= 0.0 || epsilon.is_nan();
+    let cell = |position: CompactVec3| {
+        position.map(|x| {
+            if exact {
+                (x + 0.0).to_bits() as i64
+            } else {
+                (x / epsilon).floor() as i64
+            }
+        })
+    };
+    let offsets: &[i64] = if exact { &[0] } else { &[-1, 0, 1] };
+
+    let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    let mut kept: Vec<usize> = Vec::new();
+    let mut groups: Vec<usize> = Vec::with_capacity(positions.len());
+
+    for (i, position) in positions.iter().enumerate() {
+        let [x, y, z] = cell(*position);
+
+        // Cells past the ends of i64 can't have anything in them, as the cast saturates
+        let existing = offsets
+            .iter()
+            .flat_map(|&dx| {
+                offsets
+                    .iter()
+                    .flat_map(move |&dy| offsets.iter().map(move |&dz| [dx, dy, dz]))
+            })
+            .filter_map(|[dx, dy, dz]| {
+                Some([x.checked_add(dx)?, y.checked_add(dy)?, z.checked_add(dz)?])
+            })
+            .filter_map(|neighbour| cells.get(&neighbour))
+            .flatten()
+            .find(|&&group| close(&positions[kept[group]], position) && same(kept[group], i));
+
+        match existing {
+            Some(&group) => groups.push(group),
+            None => {
+                cells.entry([x, y, z]).or_default().push(kept.len());
+                groups.push(kept.len());
+                kept.push(i);
+            }
+        }
+    }
+
+    (kept, groups)
+}
+
+/// A copy of the model with only some of its vertices, which may have been changed. `vertices[i]`
+/// replaces the original vertex `kept[i]`, and the skin and morph targets are cut down to match
+pub(crate) fn with_vertex_subset(
+    model: &Model,
+    kept: &[usize],
+    vertices: Vec<Vertex>,
+    indices: Vec<Index>,
+) -> Model {
+    let mut new_model = Model::new(vertices, indices);
+    new_model.set_matrix(model.matrix());
+    new_model.set_material(model.material());
+
+    if let Some(skin) = model.skin() {
+        let skin_vertices = skin.vertices();
+
+        new_model.set_skin(Some(Skin::new(
+            skin.skeleton().clone(),
+            kept.iter().map(|&i| skin_vertices[i]).collect(),
+        )));
+    }
+
+    if let Some(morph) = model.morph() {
+        let targets = morph
+            .targets()
+            .iter()
+            .map(|target| MorphTarget {
+                name: target.name.clone(),
+                positions: kept.iter().map(|&i| target.positions[i]).collect(),
+                normals: if target.normals.is_empty() {
+                    Vec::new()
+                } else {
+                    kept.iter().map(|&i| target.normals[i]).collect()
+                },
+            })
+            .collect();
+
+        let mut new_morph = Morph::new(targets);
+        new_morph.set_weights(&morph.weights());
+        new_model.set_morph(Some(new_morph));
+    }
+
+    new_model
+}
+
+/// A copy of the model with new vertices and indices, for changes which keep every vertex
+fn with_geometry(model: &Model, vertices: Vec<Vertex>, indices: Vec<Index>) -> Model {
+    let mut new_model = Model::new(vertices, indices);
+
+    new_model.set_matrix(model.matrix());
+    new_model.set_material(model.material());
+    new_model.set_skin(model.skin().cloned());
+    new_model.set_morph(model.morph().cloned());
+
+    new_model
+}
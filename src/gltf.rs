@@ -0,0 +1,1002 @@
+use nalgebra_glm::{identity, to_quat, Qua, TMat4, TVec3, TVec4};
+
+use serde::Deserialize;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::animation::{Channel, Clip, Interpolation, Keyable, Keyframe, LoopMode, Track};
+use crate::error::SceneError;
+use crate::geometry::{reverse_triangle, vertex_normals, NormalWeighting, Winding};
+use crate::model::{Material, Model};
+use crate::morph::{Morph, MorphTarget};
+use crate::scene_graph::{NodeId, SceneGraph, Trs};
+use crate::skeleton::{Joint, Skeleton, Skin};
+use crate::vertex::{CompactVec3, Index, SkinVertex, Vertex};
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_JSON_CHUNK: u32 = 0x4E4F534A;
+const GLB_BIN_CHUNK: u32 = 0x004E4942;
+const TRIANGLES: u32 = 4;
+
+/// The node hierarchy of a glTF file with its meshes attached, and its animations
+pub struct GltfScene {
+    pub graph: SceneGraph,
+    /// Channels target nodes, and skeleton joints, by name
+    pub clips: Vec<Clip>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneDef>,
+    #[serde(default)]
+    nodes: Vec<NodeDef>,
+    #[serde(default)]
+    meshes: Vec<MeshDef>,
+    #[serde(default)]
+    materials: Vec<MaterialDef>,
+    #[serde(default)]
+    skins: Vec<SkinDef>,
+    #[serde(default)]
+    animations: Vec<AnimationDef>,
+    #[serde(default)]
+    accessors: Vec<AccessorDef>,
+    #[serde(default)]
+    buffer_views: Vec<BufferViewDef>,
+    #[serde(default)]
+    buffers: Vec<BufferDef>,
+}
+
+#[derive(Deserialize)]
+struct SceneDef {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct NodeDef {
+    /// Overrides the mesh's default morph target weights
+    weights: Option<Vec<f32>>,
+    name: Option<String>,
+    #[serde(default)]
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    skin: Option<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<CompactVec3>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<CompactVec3>,
+}
+
+#[derive(Deserialize)]
+struct MeshDef {
+    primitives: Vec<PrimitiveDef>,
+    weights: Option<Vec<f32>>,
+    extras: Option<MeshExtras>,
+}
+
+/// Where exporters conventionally put morph target names
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MeshExtras {
+    #[serde(default)]
+    target_names: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PrimitiveDef {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+    /// Position and normal deltas for each morph target
+    #[serde(default)]
+    targets: Vec<HashMap<String, usize>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialDef {
+    pbr_metallic_roughness: Option<PbrDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrDef {
+    base_color_factor: Option<[f32; 4]>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SkinDef {
+    joints: Vec<usize>,
+    inverse_bind_matrices: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct AnimationDef {
+    name: Option<String>,
+    channels: Vec<ChannelDef>,
+    samplers: Vec<SamplerDef>,
+}
+
+#[derive(Deserialize)]
+struct ChannelDef {
+    sampler: usize,
+    target: TargetDef,
+}
+
+#[derive(Deserialize)]
+struct TargetDef {
+    node: Option<usize>,
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct SamplerDef {
+    input: usize,
+    output: usize,
+    interpolation: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessorDef {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferViewDef {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferDef {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+fn error(message: impl Into<String>) -> SceneError {
+    SceneError::Gltf(message.into())
+}
+
+/// Loads a `.gltf` file, with its buffers embedded or alongside it, or a binary `.glb` file
+pub fn load_gltf(path: impl AsRef<Path>) -> Result<GltfScene, SceneError> {
+    let path = path.as_ref();
+
+    parse_gltf(&fs::read(path)?, path.parent().unwrap_or(Path::new("")))
+}
+
+/// Parses glTF or GLB data, with external buffers relative to `directory`. Triangle meshes,
+/// skins, morph targets, materials' base colours and translation, rotation, scale and morph
+/// weight animations are read. Textures and cameras are ignored
+pub fn parse_gltf(data: &[u8], directory: &Path) -> Result<GltfScene, SceneError> {
+    let (json, bin) = if data.starts_with(GLB_MAGIC) {
+        split_glb(data)?
+    } else {
+        (data, None)
+    };
+
+    let document: Document = serde_json::from_slice(json)?;
+
+    let buffers = document
+        .buffers
+        .iter()
+        .map(|buffer| {
+            let data = match &buffer.uri {
+                None => bin
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| error("buffer has no uri outside a GLB file"))?,
+                Some(uri) if uri.starts_with("data:") => {
+                    let (_, encoded) = uri
+                        .split_once(";base64,")
+                        .ok_or_else(|| error("data uri isn't base64"))?;
+
+                    decode_base64(encoded)?
+                }
+                Some(uri) => fs::read(directory.join(uri))?,
+            };
+
+            if data.len() < buffer.byte_length {
+                return Err(error("buffer is shorter than its byteLength"));
+            }
+
+            Ok(data)
+        })
+        .collect::<Result<Vec<_>, SceneError>>()?;
+
+    Importer {
+        document: &document,
+        buffers,
+    }
+    .import()
+}
+
+/// The JSON and binary chunks of a GLB file
+fn split_glb(data: &[u8]) -> Result<(&[u8], Option<&[u8]>), SceneError> {
+    let read_u32 = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .ok_or_else(|| error("GLB file is truncated"))
+    };
+
+    let length = (read_u32(8)? as usize).min(data.len());
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+
+    while offset + 8 <= length {
+        let chunk_length = read_u32(offset)? as usize;
+        let chunk_type = read_u32(offset + 4)?;
+        let chunk = data
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| error("GLB chunk is truncated"))?;
+
+        match chunk_type {
+            GLB_JSON_CHUNK => json = Some(chunk),
+            GLB_BIN_CHUNK => bin = Some(chunk),
+            _ => (),
+        }
+
+        offset += 8 + chunk_length;
+    }
+
+    Ok((
+        json.ok_or_else(|| error("GLB file has no JSON chunk"))?,
+        bin,
+    ))
+}
+
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, SceneError> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Ok(c - b'A'),
+        b'a'..=b'z' => Ok(c - b'a' + 26),
+        b'0'..=b'9' => Ok(c - b'0' + 52),
+        b'+' | b'-' => Ok(62),
+        b'/' | b'_' => Ok(63),
+        _ => Err(error("invalid base64")),
+    };
+
+    let digits = encoded
+        .bytes()
+        .filter(|&c| c != b'=' && !c.is_ascii_whitespace())
+        .map(value)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+
+    for chunk in digits.chunks(4) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &digit)| {
+            bits | ((digit as u32) << (18 - 6 * i))
+        });
+
+        bytes.extend(&bits.to_be_bytes()[1..chunk.len()]);
+    }
+
+    Ok(bytes)
+}
+
+struct Importer<'a> {
+    document: &'a Document,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<'a> Importer<'a> {
+    fn import(self: &Self) -> Result<GltfScene, SceneError> {
+        let document = self.document;
+        let names = self.node_names();
+
+        let mut parents = vec![None; document.nodes.len()];
+        for (i, node) in document.nodes.iter().enumerate() {
+            for &child in &node.children {
+                *parents
+                    .get_mut(child)
+                    .ok_or_else(|| error("child node out of range"))? = Some(i);
+            }
+        }
+
+        // The default scene, or every root node if there isn't one
+        let roots = match document.scene.or(if document.scenes.is_empty() {
+            None
+        } else {
+            Some(0)
+        }) {
+            Some(scene) => document
+                .scenes
+                .get(scene)
+                .ok_or_else(|| error("scene out of range"))?
+                .nodes
+                .clone(),
+            None => (0..document.nodes.len())
+                .filter(|&i| parents[i].is_none())
+                .collect(),
+        };
+
+        let mut graph = SceneGraph::new();
+        let mut ids: HashMap<usize, NodeId> = HashMap::new();
+        let mut stack = roots
+            .into_iter()
+            .rev()
+            .map(|node| (node, None))
+            .collect::<Vec<_>>();
+
+        while let Some((i, parent)) = stack.pop() {
+            let node = document
+                .nodes
+                .get(i)
+                .ok_or_else(|| error("node out of range"))?;
+
+            if ids.contains_key(&i) {
+                return Err(error("node hierarchy has a cycle"));
+            }
+
+            let id = graph.add_node(names[i].clone(), node_trs(node), parent);
+            ids.insert(i, id);
+
+            stack.extend(node.children.iter().rev().map(|&child| (child, Some(id))));
+        }
+
+        graph.update();
+
+        // The name of the graph node each glTF node's mesh ended up on, for morph weight channels
+        let mut mesh_nodes = HashMap::new();
+
+        for (i, node) in document.nodes.iter().enumerate() {
+            let (id, mesh) = match (ids.get(&i), node.mesh) {
+                (Some(&id), Some(mesh)) => (id, mesh),
+                _ => continue,
+            };
+
+            let (mut model, skin_vertices) = self.mesh(mesh)?;
+
+            if let (Some(morph), Some(weights)) = (model.morph_mut(), &node.weights) {
+                morph.set_weights(weights);
+            }
+
+            match node.skin {
+                Some(skin) => {
+                    let skeleton = self.skeleton(skin, &names, &parents, &graph, &ids)?;
+                    let skin_vertices =
+                        skin_vertices.ok_or_else(|| error("skinned mesh has no joints"))?;
+
+                    // The mesh's weights can only be checked against the skin it's used with
+                    let out_of_range = skin_vertices.iter().any(|v| {
+                        v.joints.iter().zip(&v.weights).any(|(&joint, &weight)| {
+                            weight != 0.0 && joint as usize >= skeleton.len()
+                        })
+                    });
+                    if out_of_range {
+                        return Err(error("vertex weighted to a joint the skin doesn't have"));
+                    }
+
+                    model.set_skin(Some(Skin::new(skeleton, skin_vertices)));
+
+                    // Skinned meshes are placed by their joints, not the node they're on
+                    let skin_node_name = format!("{} skin", names[i]);
+                    let skin_node = graph.add_node(skin_node_name.clone(), Trs::default(), None);
+                    graph.attach_mesh(skin_node, model);
+
+                    mesh_nodes.insert(i, skin_node_name);
+                }
+                None => {
+                    graph.attach_mesh(id, model);
+
+                    mesh_nodes.insert(i, names[i].clone());
+                }
+            }
+        }
+
+        let clips = document
+            .animations
+            .iter()
+            .enumerate()
+            .map(|(i, animation)| self.clip(i, animation, &names, &mesh_nodes))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(GltfScene { graph, clips })
+    }
+
+    /// Node names, made unique so clips can refer to nodes by name
+    fn node_names(self: &Self) -> Vec<String> {
+        let mut used = HashSet::new();
+
+        self.document
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let mut name = node.name.clone().unwrap_or_else(|| format!("node {}", i));
+                if !used.insert(name.clone()) {
+                    name = format!("{} {}", name, i);
+                    used.insert(name.clone());
+                }
+
+                name
+            })
+            .collect()
+    }
+
+    /// Every primitive of a mesh merged into one model, with the skin weights if it has any
+    fn mesh(self: &Self, mesh: usize) -> Result<(Model, Option<Vec<SkinVertex>>), SceneError> {
+        let mesh = self
+            .document
+            .meshes
+            .get(mesh)
+            .ok_or_else(|| error("mesh out of range"))?;
+
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut indices: Vec<Index> = Vec::new();
+        let mut skin_vertices: Vec<SkinVertex> = Vec::new();
+        let mut skinned = false;
+        let mut material = None;
+        let target_count = mesh.primitives.first().map_or(0, |p| p.targets.len());
+        let mut targets = vec![MorphTarget::default(); target_count];
+
+        for primitive in &mesh.primitives {
+            if primitive.mode.unwrap_or(TRIANGLES) != TRIANGLES {
+                return Err(error("only triangle primitives are supported"));
+            }
+
+            let attribute = |name: &str| primitive.attributes.get(name).copied();
+
+            let positions = self.read_vec3(
+                attribute("POSITION").ok_or_else(|| error("primitive has no positions"))?,
+            )?;
+            let first = vertices.len() as Index;
+
+            let primitive_indices = match primitive.indices {
+                Some(accessor) => self
+                    .read(accessor, 1)?
+                    .into_iter()
+                    .map(|i| i as Index)
+                    .collect::<Vec<_>>(),
+                None => (0..positions.len() as Index).collect(),
+            };
+
+            if primitive_indices
+                .iter()
+                .any(|&i| i as usize >= positions.len())
+            {
+                return Err(error("index out of range"));
+            }
+
+            let normals = match attribute("NORMAL") {
+                Some(accessor) => self.read_vec3(accessor)?,
+                None => vertex_normals(
+                    &positions,
+                    &primitive_indices,
+                    NormalWeighting::Area,
+                    Winding::CounterClockwise,
+                ),
+            };
+
+            if normals.len() != positions.len() {
+                return Err(error(
+                    "primitive has a different number of normals and positions",
+                ));
+            }
+
+            let uvs = match attribute("TEXCOORD_0") {
+                Some(accessor) => self
+                    .read(accessor, 2)?
+                    .chunks_exact(2)
+                    .map(|chunk| [chunk[0], chunk[1]])
+                    .collect(),
+                None => vec![[0.0, 0.0]; positions.len()],
+            };
+
+            if uvs.len() != positions.len() {
+                return Err(error(
+                    "primitive has a different number of texture coordinates and positions",
+                ));
+            }
+
+            vertices.extend(
+                positions
+                    .iter()
+                    .zip(&normals)
+                    .zip(&uvs)
+                    .map(|((&position, &normal), &uv)| Vertex::new(position, normal).with_uv(uv)),
+            );
+            indices.extend(
+                primitive_indices
+                    .chunks_exact(3)
+                    .flat_map(|triangle| reverse_triangle([triangle[0], triangle[1], triangle[2]]))
+                    .map(|i| i + first),
+            );
+
+            match (attribute("JOINTS_0"), attribute("WEIGHTS_0")) {
+                (Some(joints), Some(weights)) => {
+                    let joints = self.read(joints, 4)?;
+                    let weights = self.read(weights, 4)?;
+
+                    if joints.len() != positions.len() * 4 || weights.len() != joints.len() {
+                        return Err(error(
+                            "primitive has a different number of joints and positions",
+                        ));
+                    }
+
+                    skin_vertices.extend(joints.chunks_exact(4).zip(weights.chunks_exact(4)).map(
+                        |(joints, weights)| {
+                            SkinVertex::new(
+                                [0, 1, 2, 3].map(|i| joints[i] as u32),
+                                [0, 1, 2, 3].map(|i| weights[i]),
+                            )
+                        },
+                    ));
+                    skinned = true;
+                }
+                _ => skin_vertices.extend(positions.iter().map(|_| SkinVertex::default())),
+            }
+
+            if primitive.targets.len() != target_count {
+                return Err(error("primitives have different numbers of morph targets"));
+            }
+
+            for (target, attributes) in targets.iter_mut().zip(&primitive.targets) {
+                let read_deltas = |name: &str| match attributes.get(name) {
+                    Some(&accessor) => {
+                        let deltas = self.read_vec3(accessor)?;
+
+                        if deltas.len() != positions.len() {
+                            return Err(error("morph target has the wrong number of deltas"));
+                        }
+
+                        Ok(deltas)
+                    }
+                    None => Ok(vec![[0.0; 3]; positions.len()]),
+                };
+
+                target.positions.extend(read_deltas("POSITION")?);
+                target.normals.extend(read_deltas("NORMAL")?);
+            }
+
+            // Models have a single material, so the first primitive's is used
+            if material.is_none() {
+                material = primitive.material;
+            }
+        }
+
+        let mut model = Model::new(vertices, indices);
+        if let Some(material) = material {
+            model.set_material(self.material(material)?);
+        }
+
+        if !targets.is_empty() {
+            let names = mesh.extras.as_ref().map(|extras| &extras.target_names);
+
+            for (i, target) in targets.iter_mut().enumerate() {
+                target.name = names
+                    .and_then(|names| names.get(i).cloned())
+                    .unwrap_or_else(|| format!("target {}", i));
+            }
+
+            let mut morph = Morph::new(targets);
+            if let Some(weights) = &mesh.weights {
+                morph.set_weights(weights);
+            }
+
+            model.set_morph(Some(morph));
+        }
+
+        Ok((model, skinned.then_some(skin_vertices)))
+    }
+
+    fn material(self: &Self, material: usize) -> Result<Material, SceneError> {
+        let material = self
+            .document
+            .materials
+            .get(material)
+            .ok_or_else(|| error("material out of range"))?;
+
+        let base_colour = material
+            .pbr_metallic_roughness
+            .as_ref()
+            .and_then(|pbr| pbr.base_color_factor);
+
+        Ok(match base_colour {
+            Some([r, g, b, _]) => Material { albedo: [r, g, b] },
+            None => Material::default(),
+        })
+    }
+
+    fn skeleton(
+        self: &Self,
+        skin: usize,
+        names: &[String],
+        parents: &[Option<usize>],
+        graph: &SceneGraph,
+        ids: &HashMap<usize, NodeId>,
+    ) -> Result<Skeleton, SceneError> {
+        let skin = self
+            .document
+            .skins
+            .get(skin)
+            .ok_or_else(|| error("skin out of range"))?;
+
+        let inverse_binds = match skin.inverse_bind_matrices {
+            Some(accessor) => self
+                .read(accessor, 16)?
+                .chunks_exact(16)
+                .map(TMat4::from_column_slice)
+                .collect(),
+            None => vec![identity(); skin.joints.len()],
+        };
+
+        if inverse_binds.len() < skin.joints.len() {
+            return Err(error("skin has fewer inverse bind matrices than joints"));
+        }
+
+        // Joints are parented to their nearest ancestor in the same skin. A walk longer than there
+        // are nodes has gone round a cycle
+        let joint_parent = |node: usize| {
+            let mut ancestor = parents[node];
+            for _ in 0..parents.len() {
+                let a = match ancestor {
+                    Some(a) => a,
+                    None => return Ok((None, parents[node])),
+                };
+                if let Some(joint) = skin.joints.iter().position(|&j| j == a) {
+                    return Ok((Some(joint), None));
+                }
+                ancestor = parents[a];
+            }
+
+            Err(error("node hierarchy has a cycle"))
+        };
+
+        let mut root = identity();
+        let mut joints = Vec::new();
+
+        for (&node, &inverse_bind) in skin.joints.iter().zip(&inverse_binds) {
+            let node_def = self
+                .document
+                .nodes
+                .get(node)
+                .ok_or_else(|| error("joint out of range"))?;
+            let (parent, outside_parent) = joint_parent(node)?;
+
+            // Anything above the top of the skeleton is static, so it's baked into the root
+            if let Some(id) = outside_parent.and_then(|parent| ids.get(&parent)) {
+                root = graph.world_matrix(*id);
+            }
+
+            joints.push(Joint {
+                name: names[node].clone(),
+                parent,
+                local: node_trs(node_def),
+                inverse_bind,
+            });
+        }
+
+        Skeleton::new(joints, root)
+    }
+
+    fn clip(
+        self: &Self,
+        i: usize,
+        animation: &AnimationDef,
+        names: &[String],
+        mesh_nodes: &HashMap<usize, String>,
+    ) -> Result<Clip, SceneError> {
+        let mut channels = Vec::new();
+
+        for channel in &animation.channels {
+            let (node, target) = match channel.target.node {
+                Some(node) => (
+                    node,
+                    names
+                        .get(node)
+                        .ok_or_else(|| error("animation target out of range"))?
+                        .clone(),
+                ),
+                None => continue,
+            };
+
+            let sampler = animation
+                .samplers
+                .get(channel.sampler)
+                .ok_or_else(|| error("animation sampler out of range"))?;
+
+            let interpolation = match sampler.interpolation.as_deref() {
+                None | Some("LINEAR") => Interpolation::Linear,
+                Some("STEP") => Interpolation::Step,
+                Some("CUBICSPLINE") => Interpolation::CubicSpline,
+                Some(other) => return Err(error(format!("unknown interpolation {}", other))),
+            };
+
+            // Keyframes would otherwise be sorted, and a NaN time would pose everything as NaN
+            let times = self.read(sampler.input, 1)?;
+            if !times.iter().all(|time| time.is_finite())
+                || !times.windows(2).all(|pair| pair[0] < pair[1])
+            {
+                return Err(error("animation times must be finite and increasing"));
+            }
+
+            if channel.target.path == "weights" {
+                let target = match mesh_nodes.get(&node) {
+                    Some(mesh_node) => mesh_node.clone(),
+                    None => continue,
+                };
+                let target_count = self.document.nodes[node]
+                    .mesh
+                    .and_then(|mesh| self.document.meshes.get(mesh))
+                    .and_then(|mesh| mesh.primitives.first())
+                    .map_or(0, |primitive| primitive.targets.len());
+
+                let tracks =
+                    self.weight_tracks(&times, sampler.output, interpolation, target_count)?;
+
+                channels.extend(tracks.into_iter().enumerate().map(|(index, track)| {
+                    Channel::MorphWeight {
+                        target: target.clone(),
+                        index,
+                        track,
+                    }
+                }));
+
+                continue;
+            }
+
+            channels.push(match channel.target.path.as_str() {
+                "translation" => Channel::Translation {
+                    target,
+                    track: self.track(&times, sampler.output, interpolation)?,
+                },
+                "rotation" => Channel::Rotation {
+                    target,
+                    track: self.track(&times, sampler.output, interpolation)?,
+                },
+                "scale" => Channel::Scale {
+                    target,
+                    track: self.track(&times, sampler.output, interpolation)?,
+                },
+                _ => continue,
+            });
+        }
+
+        let name = animation
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("animation {}", i));
+
+        Ok(Clip::new(name, LoopMode::Loop, channels))
+    }
+
+    /// Cubic spline outputs hold an in tangent, value and out tangent for each keyframe
+    fn track<const N: usize>(
+        self: &Self,
+        times: &[f32],
+        output: usize,
+        interpolation: Interpolation,
+    ) -> Result<Track<[f32; N]>, SceneError>
+    where
+        [f32; N]: Keyable,
+    {
+        let values = self
+            .read(output, N)?
+            .chunks_exact(N)
+            .map(|chunk| <[f32; N]>::try_from(chunk).unwrap())
+            .collect::<Vec<_>>();
+
+        let per_keyframe = if interpolation == Interpolation::CubicSpline {
+            3
+        } else {
+            1
+        };
+
+        if values.len() < times.len() * per_keyframe {
+            return Err(error("animation has fewer values than keyframes"));
+        }
+
+        let keyframes = times
+            .iter()
+            .enumerate()
+            .map(|(i, &time)| {
+                if per_keyframe == 3 {
+                    Keyframe {
+                        time,
+                        value: values[i * 3 + 1],
+                        in_tangent: Some(values[i * 3]),
+                        out_tangent: Some(values[i * 3 + 2]),
+                    }
+                } else {
+                    Keyframe::new(time, values[i])
+                }
+            })
+            .collect();
+
+        Ok(Track::new(interpolation, keyframes))
+    }
+
+    /// One track per morph target, from an output which holds every target's weight for each
+    /// keyframe in turn
+    fn weight_tracks(
+        self: &Self,
+        times: &[f32],
+        output: usize,
+        interpolation: Interpolation,
+        target_count: usize,
+    ) -> Result<Vec<Track<f32>>, SceneError> {
+        let values = self.read(output, 1)?;
+
+        let per_keyframe = if interpolation == Interpolation::CubicSpline {
+            3
+        } else {
+            1
+        };
+
+        if values.len() < times.len() * per_keyframe * target_count {
+            return Err(error("animation has fewer weights than keyframes"));
+        }
+
+        // Cubic spline outputs hold every in tangent, then every value, then every out tangent
+        let value = |keyframe: usize, part: usize, target: usize| {
+            values[(keyframe * per_keyframe + part) * target_count + target]
+        };
+
+        Ok((0..target_count)
+            .map(|target| {
+                let keyframes = times
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &time)| {
+                        if per_keyframe == 3 {
+                            Keyframe {
+                                time,
+                                value: value(i, 1, target),
+                                in_tangent: Some(value(i, 0, target)),
+                                out_tangent: Some(value(i, 2, target)),
+                            }
+                        } else {
+                            Keyframe::new(time, value(i, 0, target))
+                        }
+                    })
+                    .collect();
+
+                Track::new(interpolation, keyframes)
+            })
+            .collect())
+    }
+
+    fn read_vec3(self: &Self, accessor: usize) -> Result<Vec<CompactVec3>, SceneError> {
+        Ok(self
+            .read(accessor, 3)?
+            .chunks_exact(3)
+            .map(|chunk| [chunk[0], chunk[1], chunk[2]])
+            .collect())
+    }
+
+    /// Every element of an accessor as floats, checking it has `components` per element.
+    /// Normalised integers are scaled into 0 to 1, or -1 to 1 if signed
+    fn read(self: &Self, accessor: usize, components: usize) -> Result<Vec<f32>, SceneError> {
+        let accessor = self
+            .document
+            .accessors
+            .get(accessor)
+            .ok_or_else(|| error("accessor out of range"))?;
+
+        if accessor.sparse.is_some() {
+            return Err(error("sparse accessors aren't supported"));
+        }
+
+        let accessor_components = match accessor.kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" | "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            other => return Err(error(format!("unknown accessor type {}", other))),
+        };
+
+        if accessor_components != components {
+            return Err(error(format!(
+                "expected {} components but accessor has {}",
+                components, accessor_components
+            )));
+        }
+
+        let (size, read): (usize, fn(&[u8]) -> f32) =
+            match (accessor.component_type, accessor.normalized) {
+                (5120, false) => (1, |b| b[0] as i8 as f32),
+                (5120, true) => (1, |b| (b[0] as i8 as f32 / 127.0).max(-1.0)),
+                (5121, false) => (1, |b| b[0] as f32),
+                (5121, true) => (1, |b| b[0] as f32 / 255.0),
+                (5122, false) => (2, |b| i16::from_le_bytes([b[0], b[1]]) as f32),
+                (5122, true) => (2, |b| {
+                    (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0).max(-1.0)
+                }),
+                (5123, false) => (2, |b| u16::from_le_bytes([b[0], b[1]]) as f32),
+                (5123, true) => (2, |b| u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0),
+                (5125, _) => (4, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32),
+                (5126, _) => (4, |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                (other, _) => return Err(error(format!("unknown component type {}", other))),
+            };
+
+        let view = match accessor.buffer_view {
+            Some(view) => self
+                .document
+                .buffer_views
+                .get(view)
+                .ok_or_else(|| error("buffer view out of range"))?,
+            // Accessors without a buffer view are all zeros
+            None => return Ok(vec![0.0; accessor.count * components]),
+        };
+
+        let buffer = self
+            .buffers
+            .get(view.buffer)
+            .ok_or_else(|| error("buffer out of range"))?;
+        let view_end = view.byte_offset + view.byte_length;
+        let data = buffer
+            .get(view.byte_offset..view_end)
+            .ok_or_else(|| error("buffer view is outside its buffer"))?;
+
+        let element_size = size * components;
+        let stride = view.byte_stride.unwrap_or(element_size);
+
+        let mut values = Vec::with_capacity(accessor.count * components);
+
+        for element in 0..accessor.count {
+            let start = accessor.byte_offset + element * stride;
+            let bytes = data
+                .get(start..start + element_size)
+                .ok_or_else(|| error("accessor is outside its buffer view"))?;
+
+            values.extend(bytes.chunks_exact(size).map(read));
+        }
+
+        Ok(values)
+    }
+}
+
+fn node_trs(node: &NodeDef) -> Trs {
+    match node.matrix {
+        Some(matrix) => {
+            let matrix = TMat4::from_column_slice(&matrix);
+            let column = |i: usize| TVec3::new(matrix[(0, i)], matrix[(1, i)], matrix[(2, i)]);
+            let scale = TVec3::new(column(0).norm(), column(1).norm(), column(2).norm());
+
+            let mut rotation = matrix;
+            for i in 0..3 {
+                for row in 0..3 {
+                    if scale[i] != 0.0 {
+                        rotation[(row, i)] /= scale[i];
+                    }
+                }
+            }
+
+            Trs {
+                translation: column(3),
+                rotation: to_quat(&rotation),
+                scale,
+            }
+        }
+        None => Trs {
+            translation: TVec3::from(node.translation.unwrap_or([0.0; 3])),
+            rotation: Qua::from_vector(TVec4::from(node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]))),
+            scale: TVec3::from(node.scale.unwrap_or([1.0; 3])),
+        },
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::device::GpuMemory;
+use crate::font::GLYPH_SIZE;
+use crate::renderer::RenderStats;
+use crate::text::TextBatch;
+
+/// How many frames the graph and averages cover
+const HISTORY: usize = 120;
+const SCALE: f32 = 2.0;
+const MARGIN: f32 = 8.0;
+const BAR_WIDTH: f32 = 2.0;
+const GRAPH_HEIGHT: f32 = 64.0;
+/// The frame time at the top of the graph, longer frames are clipped
+const GRAPH_MAX_MS: f32 = 50.0;
+const TARGET_60_MS: f32 = 1000.0 / 60.0;
+const TARGET_30_MS: f32 = 1000.0 / 30.0;
+
+const TEXT_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKGROUND_COLOUR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const GOOD_COLOUR: [f32; 4] = [0.2, 0.9, 0.2, 1.0];
+const SLOW_COLOUR: [f32; 4] = [0.9, 0.9, 0.2, 1.0];
+const BAD_COLOUR: [f32; 4] = [0.9, 0.2, 0.2, 1.0];
+const LINE_COLOUR: [f32; 4] = [1.0, 1.0, 1.0, 0.3];
+
+/// An overlay of frame timings and renderer statistics, drawn with a `TextBatch`
+#[derive(Clone, Default)]
+pub struct Hud {
+    /// Milliseconds, oldest first
+    frame_times: VecDeque<f32>,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_frame(self: &mut Self, dt: Duration) {
+        if self.frame_times.len() == HISTORY {
+            self.frame_times.pop_front();
+        }
+
+        self.frame_times.push_back(dt.as_secs_f32() * 1000.0);
+    }
+
+    /// The mean frame time in milliseconds over the recorded frames
+    pub fn frame_time(self: &Self) -> Option<f32> {
+        if self.frame_times.is_empty() {
+            return None;
+        }
+
+        Some(self.frame_times.iter().sum::<f32>() / self.frame_times.len() as f32)
+    }
+
+    pub fn fps(self: &Self) -> Option<f32> {
+        self.frame_time()
+            .filter(|&frame_time| frame_time > 0.0)
+            .map(|frame_time| 1000.0 / frame_time)
+    }
+
+    /// Draws the stats and frame time graph in the top left corner
+    pub fn draw(
+        self: &Self,
+        text: &mut TextBatch,
+        stats: RenderStats,
+        light_count: usize,
+        gpu_memory: Option<GpuMemory>,
+    ) {
+        let frame_time = self.frame_time().unwrap_or(0.0);
+        let worst = self.frame_times.iter().cloned().fold(0.0, f32::max);
+
+        let memory = match gpu_memory {
+            Some(memory) => format!(
+                "{} / {} MiB",
+                memory.used / (1024 * 1024),
+                memory.budget / (1024 * 1024)
+            ),
+            None => "unavailable".to_string(),
+        };
+
+        let lines = [
+            format!("FPS    {:.0}", self.fps().unwrap_or(0.0)),
+            format!("Frame  {:.2} ms (max {:.2})", frame_time, worst),
+            format!("Draws  {}", stats.draw_calls),
+            format!("Tris   {}", stats.triangles),
+            format!("Lights {}", light_count),
+            format!("GPU    {}", memory),
+        ];
+
+        let line_height = GLYPH_SIZE as f32 * SCALE;
+        let width = lines
+            .iter()
+            .map(|line| line.len() as f32 * line_height)
+            .fold(HISTORY as f32 * BAR_WIDTH, f32::max);
+        let height = lines.len() as f32 * line_height + MARGIN + GRAPH_HEIGHT;
+
+        text.rect(
+            [0.0, 0.0],
+            [width + MARGIN * 2.0, height + MARGIN * 2.0],
+            BACKGROUND_COLOUR,
+        );
+
+        let mut position = [MARGIN, MARGIN];
+        for line in &lines {
+            position = text.text(position, SCALE, TEXT_COLOUR, line);
+        }
+
+        let graph_bottom = position[1] + MARGIN + GRAPH_HEIGHT;
+        let graph_y = |ms: f32| graph_bottom - ms.min(GRAPH_MAX_MS) / GRAPH_MAX_MS * GRAPH_HEIGHT;
+
+        for (i, &ms) in self.frame_times.iter().enumerate() {
+            let x = MARGIN + i as f32 * BAR_WIDTH;
+            let colour = if ms <= TARGET_60_MS {
+                GOOD_COLOUR
+            } else if ms <= TARGET_30_MS {
+                SLOW_COLOUR
+            } else {
+                BAD_COLOUR
+            };
+
+            text.rect([x, graph_y(ms)], [x + BAR_WIDTH, graph_bottom], colour);
+        }
+
+        // Reference lines at 60 and 30 fps
+        for ms in [TARGET_60_MS, TARGET_30_MS] {
+            let y = graph_y(ms);
+
+            text.rect(
+                [MARGIN, y],
+                [MARGIN + HISTORY as f32 * BAR_WIDTH, y + 1.0],
+                LINE_COLOUR,
+            );
+        }
+    }
+}
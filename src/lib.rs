@@ -0,0 +1,43 @@
+// Modules
+
+pub mod animation;
+pub mod assets;
+pub mod camera;
+pub mod capture;
+pub mod clock;
+pub mod debug;
+pub mod debug_draw;
+pub mod debug_view;
+pub mod device;
+pub mod error;
+pub mod font;
+mod frame_limiter;
+pub mod geometry;
+pub mod gltf;
+pub mod hud;
+pub mod light;
+pub mod lod;
+pub mod model;
+pub mod morph;
+pub mod obj;
+mod pipeline_commands;
+pub mod ply;
+pub mod profiler;
+pub mod renderer;
+pub mod scene;
+pub mod scene_file;
+pub mod scene_graph;
+mod shader;
+pub mod shader_reload;
+pub mod shapes;
+pub mod skeleton;
+pub mod stl;
+pub mod swapchain;
+pub mod text;
+pub mod texture;
+pub mod vertex;
+pub mod vp;
+
+pub use error::{RenderError, SceneError};
+pub use pipeline_commands::{create_instance, get_device_queue};
+pub use renderer::{Mesh, RenderSettings, RenderStats, RenderTarget, Renderer};
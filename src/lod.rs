@@ -0,0 +1,611 @@
+use nalgebra_glm::{TMat3, TMat4, TVec3, TVec4};
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::geometry::{merge_close, with_vertex_subset};
+use crate::model::Model;
+use crate::vertex::{CompactVec3, Index, Vertex};
+use crate::vp::VP;
+
+/// How much more an edge on the border of a mesh costs to move than an interior one, so holes and
+/// open edges keep their outline
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+/// Removes triangles by collapsing edges until at most `target_triangles` are left, choosing the
+/// edges whose removal changes the shape least by the quadric error metric. Vertices in the same
+/// place are moved together, so seams in normals or texture coordinates don't open up. Vertices
+/// keep their other attributes, so normals are only approximate after heavy simplification
+pub fn simplify(model: &Model, target_triangles: usize) -> Model {
+    let vertices = model.vertices();
+    let indices = model.indices();
+
+    let mut simplifier = Simplifier::new(&vertices, &indices);
+    simplifier.collapse_until(target_triangles);
+
+    // Only the vertices still used by a triangle are kept, moved to where their position ended up
+    let mut new_index: Vec<Option<Index>> = vec![None; vertices.len()];
+    let mut kept: Vec<usize> = Vec::new();
+    let mut new_vertices: Vec<Vertex> = Vec::new();
+    let mut new_indices: Vec<Index> = Vec::new();
+
+    for (triangle, alive) in simplifier.triangles.iter().zip(&simplifier.alive) {
+        if !alive {
+            continue;
+        }
+
+        for &vertex in &triangle.vertices {
+            let index = *new_index[vertex].get_or_insert_with(|| {
+                let position =
+                    simplifier.positions[simplifier.root(simplifier.vertex_position[vertex])];
+
+                kept.push(vertex);
+                new_vertices.push(Vertex {
+                    position: [position.x as f32, position.y as f32, position.z as f32],
+                    ..vertices[vertex]
+                });
+
+                (new_vertices.len() - 1) as Index
+            });
+
+            new_indices.push(index);
+        }
+    }
+
+    with_vertex_subset(model, &kept, new_vertices, new_indices)
+}
+
+/// Levels of detail which each have about `ratio` times the triangles of the one before, starting
+/// with the model itself
+pub fn generate_lods(model: &Model, levels: usize, ratio: f32) -> Vec<Model> {
+    assert!(levels >= 1 && ratio > 0.0 && ratio < 1.0);
+
+    let mut lods = vec![model.clone()];
+
+    for _ in 1..levels {
+        let previous = lods.last().unwrap();
+        let target = (previous.indices().len() as f32 / 3.0 * ratio) as usize;
+        let simplified = simplify(previous, target.max(1));
+
+        // Nothing more can be removed without folding the surface over
+        if simplified.indices().len() >= previous.indices().len() {
+            break;
+        }
+
+        lods.push(simplified);
+    }
+
+    lods
+}
+
+/// A model's levels of detail and which one is drawn, chosen each frame by how much of the screen
+/// the model covers
+#[derive(Clone)]
+pub struct LodGroup {
+    levels: Vec<Model>,
+    /// The smallest screen size each level is drawn at, from the most detailed
+    screen_sizes: Vec<f32>,
+    hysteresis: f32,
+    current: usize,
+    centre: TVec3<f32>,
+    radius: f32,
+}
+
+#[allow(dead_code)]
+impl LodGroup {
+    /// `screen_sizes` are the fractions of the screen's height the model's bounding sphere must
+    /// cover for each level to be drawn, decreasing from the most detailed level. The last level is
+    /// drawn however small the model gets
+    pub fn new(levels: Vec<Model>, screen_sizes: Vec<f32>) -> Self {
+        assert!(!levels.is_empty());
+        assert_eq!(levels.len(), screen_sizes.len());
+        assert!(screen_sizes.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        let (centre, radius) = bounding_sphere(&levels[0].vertices());
+
+        Self {
+            levels,
+            screen_sizes,
+            hysteresis: 0.1,
+            current: 0,
+            centre,
+            radius,
+        }
+    }
+
+    /// Generates the levels with `generate_lods`. The most detailed is drawn while the model covers
+    /// at least half the screen, and each level after at the size where it has about as many
+    /// triangles per pixel as the one before
+    pub fn generate(model: &Model, levels: usize, ratio: f32) -> Self {
+        let levels = generate_lods(model, levels, ratio);
+
+        // Triangles per pixel depend on area, which goes with the square of the size
+        let screen_sizes = (0..levels.len())
+            .map(|i| 0.5 * ratio.sqrt().powi(i as i32))
+            .collect();
+
+        Self::new(levels, screen_sizes)
+    }
+
+    pub fn levels(self: &Self) -> &[Model] {
+        &self.levels
+    }
+
+    pub fn screen_sizes(self: &Self) -> Vec<f32> {
+        self.screen_sizes.clone()
+    }
+
+    pub fn hysteresis(self: &Self) -> f32 {
+        self.hysteresis
+    }
+
+    /// How far past a level's screen size the model must go before switching, as a fraction of
+    /// that size, so a model near the boundary doesn't switch back and forth every frame. Panics
+    /// unless it's at least 0 and less than 1
+    pub fn set_hysteresis(self: &mut Self, hysteresis: f32) {
+        assert!((0.0..1.0).contains(&hysteresis));
+
+        self.hysteresis = hysteresis;
+    }
+
+    pub fn current(self: &Self) -> usize {
+        self.current
+    }
+
+    /// The level being drawn
+    pub fn model(self: &Self) -> &Model {
+        &self.levels[self.current]
+    }
+
+    /// The fraction of the screen's height covered by the bounding sphere, which is greater than
+    /// 1 when the model fills the screen
+    pub fn screen_size(self: &Self, matrix: &TMat4<f32>, vp: &VP) -> f32 {
+        let centre =
+            vp.view * matrix * TVec4::new(self.centre.x, self.centre.y, self.centre.z, 1.0);
+
+        // The largest scale of any axis, so a stretched model is never too coarse
+        let scale = (0..3)
+            .map(|axis| matrix.column(axis).xyz().norm())
+            .fold(0.0, f32::max);
+        let radius = self.radius * scale;
+        let distance = centre.xyz().norm();
+
+        if distance <= radius {
+            return f32::INFINITY;
+        }
+
+        // proj[(1, 1)] is the cotangent of half the vertical field of view
+        radius * vp.proj[(1, 1)].abs() / distance
+    }
+
+    /// Chooses the level to draw with the model at `matrix`, returning true if it changed
+    pub fn select(self: &mut Self, matrix: &TMat4<f32>, vp: &VP) -> bool {
+        let size = self.screen_size(matrix, vp);
+        let previous = self.current;
+
+        // Moves at most one level towards the right one each step, so it's applied in a loop
+        loop {
+            let current = self.current;
+
+            if current > 0 && size >= self.screen_sizes[current - 1] * (1.0 + self.hysteresis) {
+                self.current -= 1;
+            } else if current + 1 < self.levels.len()
+                && size < self.screen_sizes[current] * (1.0 - self.hysteresis)
+            {
+                self.current += 1;
+            } else {
+                break;
+            }
+        }
+
+        self.current != previous
+    }
+}
+
+/// A sphere around every vertex, centred on their bounding box
+fn bounding_sphere(vertices: &[Vertex]) -> (TVec3<f32>, f32) {
+    if vertices.is_empty() {
+        return (TVec3::zeros(), 0.0);
+    }
+
+    let mut min = TVec3::from(vertices[0].position);
+    let mut max = min;
+
+    for v in vertices {
+        min = min.inf(&TVec3::from(v.position));
+        max = max.sup(&TVec3::from(v.position));
+    }
+
+    let centre = (min + max) * 0.5;
+    let radius = vertices
+        .iter()
+        .map(|v| (TVec3::from(v.position) - centre).norm())
+        .fold(0.0, f32::max);
+
+    (centre, radius)
+}
+
+/// The sum of squared distances to a set of planes, as a symmetric 4x4 matrix
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// The plane through `point` facing along the unit `normal`
+    fn plane(normal: TVec3<f64>, point: TVec3<f64>, weight: f64) -> Self {
+        let [a, b, c] = [normal.x, normal.y, normal.z];
+        let d = -normal.dot(&point);
+
+        Quadric(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|x| x * weight),
+        )
+    }
+
+    fn add(self: Self, other: Self) -> Self {
+        let mut sum = self.0;
+        for (x, y) in sum.iter_mut().zip(other.0) {
+            *x += y;
+        }
+
+        Quadric(sum)
+    }
+
+    fn error(self: &Self, v: TVec3<f64>) -> f64 {
+        let [a2, ab, ac, ad, b2, bc, bd, c2, cd, d2] = self.0;
+        let (x, y, z) = (v.x, v.y, v.z);
+
+        a2 * x * x
+            + 2.0 * ab * x * y
+            + 2.0 * ac * x * z
+            + 2.0 * ad * x
+            + b2 * y * y
+            + 2.0 * bc * y * z
+            + 2.0 * bd * y
+            + c2 * z * z
+            + 2.0 * cd * z
+            + d2
+    }
+
+    /// The point with the least error, if there's only one
+    fn minimum(self: &Self) -> Option<TVec3<f64>> {
+        let [a2, ab, ac, ad, b2, bc, bd, c2, cd, _] = self.0;
+
+        let matrix = TMat3::new(a2, ab, ac, ab, b2, bc, ac, bc, c2);
+        if matrix.determinant().abs() < 1e-12 {
+            return None;
+        }
+
+        matrix
+            .try_inverse()
+            .map(|inverse| -(inverse * TVec3::new(ad, bd, cd)))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Triangle {
+    /// The original vertices at the corners
+    vertices: [usize; 3],
+    /// The positions at the corners, updated as positions are merged
+    positions: [usize; 3],
+}
+
+/// An edge which could be collapsed, valid while neither position has changed since
+struct Candidate {
+    cost: f64,
+    a: usize,
+    b: usize,
+    versions: [u32; 2],
+}
+
+impl PartialEq for Candidate {
+    fn eq(self: &Self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(self: &Self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    /// Reversed, so the heap gives the cheapest first
+    fn cmp(self: &Self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+/// Edge collapse over the distinct positions of a mesh
+struct Simplifier {
+    positions: Vec<TVec3<f64>>,
+    vertex_position: Vec<usize>,
+    /// The position each was merged into, or itself if it's still in use
+    merged_into: Vec<usize>,
+    versions: Vec<u32>,
+    quadrics: Vec<Quadric>,
+    triangles: Vec<Triangle>,
+    alive: Vec<bool>,
+    alive_count: usize,
+    /// The triangles around each position, some of which may have since collapsed
+    adjacent: Vec<Vec<usize>>,
+    heap: BinaryHeap<Candidate>,
+}
+
+impl Simplifier {
+    fn new(vertices: &[Vertex], indices: &[Index]) -> Self {
+        // Copies of a vertex along a seam can be a rounding error apart, as where a sphere's
+        // longitude wraps around
+        let (_, radius) = bounding_sphere(vertices);
+        let vertex_positions: Vec<CompactVec3> = vertices.iter().map(|v| v.position).collect();
+        let (first, vertex_position) = merge_close(&vertex_positions, radius * 1e-5, |_, _| true);
+
+        let positions: Vec<TVec3<f64>> = first
+            .iter()
+            .map(|&i| to_f64(vertices[i].position))
+            .collect();
+
+        let triangles: Vec<Triangle> = indices
+            .chunks_exact(3)
+            .map(|corners| {
+                let vertices = [0, 1, 2].map(|i| corners[i] as usize);
+
+                Triangle {
+                    vertices,
+                    positions: vertices.map(|v| vertex_position[v]),
+                }
+            })
+            .collect();
+
+        let alive: Vec<bool> = triangles
+            .iter()
+            .map(|t| {
+                t.positions[0] != t.positions[1]
+                    && t.positions[1] != t.positions[2]
+                    && t.positions[2] != t.positions[0]
+            })
+            .collect();
+
+        let mut simplifier = Self {
+            merged_into: (0..positions.len()).collect(),
+            versions: vec![0; positions.len()],
+            quadrics: vec![Quadric::default(); positions.len()],
+            adjacent: vec![Vec::new(); positions.len()],
+            alive_count: alive.iter().filter(|&&alive| alive).count(),
+            positions,
+            vertex_position,
+            triangles,
+            alive,
+            heap: BinaryHeap::new(),
+        };
+
+        simplifier.add_quadrics();
+
+        for position in 0..simplifier.positions.len() {
+            for neighbour in simplifier.neighbours(position) {
+                if position < neighbour {
+                    simplifier.push_candidate(position, neighbour);
+                }
+            }
+        }
+
+        simplifier
+    }
+
+    /// Each face's plane for its corners, weighted by area so tiny slivers count for little, and
+    /// planes at right angles along the border to hold it in place
+    fn add_quadrics(self: &mut Self) {
+        let mut edge_faces: HashMap<(usize, usize), usize> = HashMap::new();
+
+        for t in 0..self.triangles.len() {
+            if !self.alive[t] {
+                continue;
+            }
+
+            let corners = self.triangles[t].positions;
+            let [a, b, c] = corners.map(|p| self.positions[p]);
+            let normal = (b - a).cross(&(c - a));
+            let area = normal.norm() * 0.5;
+
+            if let Some(unit) = normal.try_normalize(f64::EPSILON) {
+                let quadric = Quadric::plane(unit, a, area);
+
+                for &p in &corners {
+                    self.quadrics[p] = self.quadrics[p].add(quadric);
+                    self.adjacent[p].push(t);
+                }
+            } else {
+                for &p in &corners {
+                    self.adjacent[p].push(t);
+                }
+            }
+
+            for i in 0..3 {
+                let (p, q) = (corners[i], corners[(i + 1) % 3]);
+                *edge_faces.entry((p.min(q), p.max(q))).or_default() += 1;
+            }
+        }
+
+        for t in 0..self.triangles.len() {
+            if !self.alive[t] {
+                continue;
+            }
+
+            let corners = self.triangles[t].positions;
+            let [a, b, c] = corners.map(|p| self.positions[p]);
+            let normal = (b - a).cross(&(c - a));
+
+            for i in 0..3 {
+                let (p, q) = (corners[i], corners[(i + 1) % 3]);
+                if edge_faces[&(p.min(q), p.max(q))] != 1 {
+                    continue;
+                }
+
+                let edge = self.positions[q] - self.positions[p];
+                if let Some(unit) = edge.cross(&normal).try_normalize(f64::EPSILON) {
+                    let quadric = Quadric::plane(
+                        unit,
+                        self.positions[p],
+                        BOUNDARY_WEIGHT * edge.norm_squared(),
+                    );
+
+                    self.quadrics[p] = self.quadrics[p].add(quadric);
+                    self.quadrics[q] = self.quadrics[q].add(quadric);
+                }
+            }
+        }
+    }
+
+    fn root(self: &Self, mut position: usize) -> usize {
+        while self.merged_into[position] != position {
+            position = self.merged_into[position];
+        }
+
+        position
+    }
+
+    fn neighbours(self: &Self, position: usize) -> Vec<usize> {
+        let mut neighbours: Vec<usize> = self.adjacent[position]
+            .iter()
+            .filter(|&&t| self.alive[t])
+            .flat_map(|&t| self.triangles[t].positions)
+            .filter(|&p| p != position)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+
+        neighbours
+    }
+
+    /// Where the merged position goes and its error
+    fn target(self: &Self, a: usize, b: usize) -> (TVec3<f64>, f64) {
+        let quadric = self.quadrics[a].add(self.quadrics[b]);
+
+        let midpoint = (self.positions[a] + self.positions[b]) * 0.5;
+        let choices = [self.positions[a], self.positions[b], midpoint];
+
+        quadric
+            .minimum()
+            .into_iter()
+            .chain(choices)
+            .map(|point| (point, quadric.error(point)))
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap()
+    }
+
+    fn push_candidate(self: &mut Self, a: usize, b: usize) {
+        let (_, cost) = self.target(a, b);
+
+        self.heap.push(Candidate {
+            cost,
+            a,
+            b,
+            versions: [self.versions[a], self.versions[b]],
+        });
+    }
+
+    /// Whether moving `a` and `b` to `target` would turn any of their other triangles over
+    fn flips(self: &Self, a: usize, b: usize, target: TVec3<f64>) -> bool {
+        self.adjacent[a]
+            .iter()
+            .chain(&self.adjacent[b])
+            .filter(|&&t| self.alive[t])
+            .map(|&t| self.triangles[t].positions)
+            .filter(|corners| !(corners.contains(&a) && corners.contains(&b)))
+            .any(|corners| {
+                let before = corners.map(|p| self.positions[p]);
+                let after = corners.map(|p| {
+                    if p == a || p == b {
+                        target
+                    } else {
+                        self.positions[p]
+                    }
+                });
+
+                let normal_before = (before[1] - before[0]).cross(&(before[2] - before[0]));
+                let normal_after = (after[1] - after[0]).cross(&(after[2] - after[0]));
+
+                normal_before.dot(&normal_after) <= 0.0
+            })
+    }
+
+    fn collapse_until(self: &mut Self, target_triangles: usize) {
+        while self.alive_count > target_triangles {
+            let candidate = match self.heap.pop() {
+                Some(candidate) => candidate,
+                None => break,
+            };
+            let (a, b) = (candidate.a, candidate.b);
+
+            if candidate.versions != [self.versions[a], self.versions[b]]
+                || self.merged_into[a] != a
+                || self.merged_into[b] != b
+            {
+                continue;
+            }
+
+            let (target, _) = self.target(a, b);
+
+            // Tried again if either end changes
+            if self.flips(a, b, target) {
+                continue;
+            }
+
+            self.positions[a] = target;
+            self.quadrics[a] = self.quadrics[a].add(self.quadrics[b]);
+            self.merged_into[b] = a;
+            self.versions[a] += 1;
+            self.versions[b] += 1;
+
+            let moved = std::mem::take(&mut self.adjacent[b]);
+            for t in moved {
+                if !self.alive[t] {
+                    continue;
+                }
+
+                let triangle = &mut self.triangles[t];
+                for position in &mut triangle.positions {
+                    if *position == b {
+                        *position = a;
+                    }
+                }
+
+                let [p, q, r] = triangle.positions;
+                if p == q || q == r || r == p {
+                    self.alive[t] = false;
+                    self.alive_count -= 1;
+                } else {
+                    self.adjacent[a].push(t);
+                }
+            }
+
+            self.adjacent[a].retain(|&t| self.alive[t]);
+
+            for neighbour in self.neighbours(a) {
+                self.versions[neighbour] += 1;
+            }
+            for neighbour in self.neighbours(a) {
+                for second in self.neighbours(neighbour) {
+                    self.push_candidate(neighbour, second);
+                }
+            }
+        }
+    }
+}
+
+fn to_f64(position: CompactVec3) -> TVec3<f64> {
+    TVec3::new(position[0] as f64, position[1] as f64, position[2] as f64)
+}
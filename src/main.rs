@@ -1,317 +1,557 @@
-use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, CpuBufferPool};
-use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
-use vulkano::format::Format;
-use vulkano::pipeline::{graphics::viewport::Viewport, Pipeline};
-use vulkano::render_pass::Subpass;
-use vulkano::swapchain::AcquireError;
-use vulkano::sync::{self, FenceSignalFuture, FlushError, GpuFuture};
-
-use winit::event::{Event, WindowEvent};
+use vulkano::swapchain::PresentMode;
+
+use vulkano_win::VkSurfaceBuild;
+
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowBuilder;
+
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use graphics::assets::{AssetChange, AssetRegistry, TextureHandle};
+use graphics::capture::Recorder;
+use graphics::clock::Clock;
+use graphics::debug::{create_debug_instance, log_messages};
+use graphics::debug_draw::DebugDraw;
+use graphics::debug_view::DebugView;
+use graphics::device::device_report;
+use graphics::hud::Hud;
+use graphics::profiler::{TimingsAverage, TimingsCsv};
+use graphics::scene::Scene;
+use graphics::scene_file::SceneFile;
+use graphics::shader_reload::{ShaderWatcher, SHADER_DIRECTORY};
+use graphics::vp;
+use graphics::{create_instance, RenderSettings, RenderTarget, Renderer};
+
+/// Command line options for the example
+struct Options {
+    settings: RenderSettings,
+    list_devices: bool,
+    debug: bool,
+    log_level: log::LevelFilter,
+    gpu_timings: bool,
+    gpu_timings_csv: Option<PathBuf>,
+    record: Option<PathBuf>,
+    record_fps: f64,
+    record_format: String,
+    record_frames: Option<u64>,
+    scene: Option<PathBuf>,
+    watch_shaders: bool,
+    watch_assets: bool,
+}
+
+/// Where the scene is saved when it wasn't loaded from a file
+const DEFAULT_SCENE_PATH: &str = "scene.json";
+
+/// How often averaged GPU timings are printed
+const TIMING_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Prints log records to stderr
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(self: &Self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(self: &Self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(self: &Self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let Options {
+        settings,
+        list_devices,
+        debug,
+        log_level,
+        gpu_timings,
+        gpu_timings_csv,
+        record,
+        record_fps,
+        record_format,
+        record_frames,
+        scene: scene_path,
+        watch_shaders,
+        watch_assets,
+    } = parse_options(env::args().skip(1))?;
+
+    log::set_logger(&LOGGER).map_err(|e| e.to_string())?;
+    log::set_max_level(log_level);
+
+    let instance = if debug {
+        create_debug_instance()?
+    } else {
+        create_instance()?
+    };
+
+    // The event loop never returns, so this lives for the rest of the program
+    let _debug_callback = if debug && instance.enabled_extensions().ext_debug_utils {
+        Some(log_messages(&instance, log_level)?)
+    } else {
+        None
+    };
+
+    if list_devices {
+        print!("{}", device_report(&instance));
+
+        return Ok(());
+    }
 
-use std::sync::Arc;
-use std::time::Instant;
-
-// Modules
-
-mod camera;
-mod light;
-mod model;
-mod pipeline_commands;
-mod shader;
-pub mod vertex;
-mod vp;
-
-use camera::Camera;
-use light::Light;
-use model::{Model, ModelCollection};
-use pipeline_commands::{
-    create_instance, get_command_buffers, get_devices_surface_queue, get_framebuffers,
-    get_pipeline, get_pipeline_with_depth, get_render_pass, new_attachment_image,
-    new_swapchain_images, recreate_swapchain,
-};
-use shader::{deferred_frag, deferred_vert, lighting_frag, lighting_vert};
-
-fn main() {
     let event_loop = EventLoop::new();
+    let surface = WindowBuilder::new().build_vk_surface(&event_loop, instance.clone())?;
+
+    let mut renderer = Renderer::with_settings(instance, RenderTarget::Window(surface), settings)?;
 
-    let instance = create_instance();
-    let (physical_device, device, queue, surface) =
-        get_devices_surface_queue(&event_loop, &instance);
-
-    let (mut swapchain, images, mut dimensions) =
-        new_swapchain_images(device.clone(), physical_device, &surface);
-
-    let mut render_pass = get_render_pass(device.clone(), swapchain.clone());
-
-    // Create attachment image buffers
-    let depth_buffer = new_attachment_image(device.clone(), dimensions, Format::D16_UNORM);
-    let mut normal_buffer =
-        new_attachment_image(device.clone(), dimensions, Format::R16G16B16A16_SFLOAT);
-    let mut colour_buffer =
-        new_attachment_image(device.clone(), dimensions, Format::A2B10G10R10_UNORM_PACK32);
-
-    let mut framebuffers = get_framebuffers(
-        &images,
-        render_pass.clone(),
-        colour_buffer.clone(),
-        normal_buffer.clone(),
-        depth_buffer.clone(),
-    );
-
-    let cube = Model::new_cube(vertex::CUBE_VERTICES.clone().to_vec());
-
-    let model_vec = vec![cube.clone(), cube.clone()];
-
-    let deferred_vert = deferred_vert::load(device.clone()).unwrap();
-    let deferred_frag = deferred_frag::load(device.clone()).unwrap();
-    let lighting_vert = lighting_vert::load(device.clone()).unwrap();
-    let lighting_frag = lighting_frag::load(device.clone()).unwrap();
-
-    let vp_buffer = CpuBufferPool::<deferred_vert::ty::VpData>::uniform_buffer(device.clone());
-    let lighting_buffer =
-        CpuBufferPool::<lighting_frag::ty::LightData>::uniform_buffer(device.clone());
-    let camera_buffer =
-        CpuBufferPool::<lighting_frag::ty::CameraData>::uniform_buffer(device.clone());
-
-    let mut viewport = Viewport {
-        origin: [0.0, 0.0],
-        dimensions: surface.window().inner_size().into(),
-        depth_range: 0.0..1.0,
+    let mut assets = AssetRegistry::new();
+    let scene_handle = match &scene_path {
+        Some(path) => Some(assets.load_scene(path)?),
+        None => None,
     };
+    let mut scene_file = match scene_handle {
+        Some(handle) => assets.scene(handle),
+        None => SceneFile::demo(),
+    };
+    let scene_directory = scene_path
+        .as_deref()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let mut scene = scene_file.build_with_assets(&scene_directory, &mut assets)?;
+
+    for handle in scene_file.load_textures(&scene_directory, &mut assets)? {
+        renderer.upload_texture(handle, assets.texture(handle))?;
+    }
+
+    // Saving writes back to the loaded file, or to the working directory for the demo scene
+    let save_path = scene_path.unwrap_or_else(|| PathBuf::from(DEFAULT_SCENE_PATH));
+
+    let mut shader_watcher = if watch_shaders {
+        let watcher = ShaderWatcher::new(SHADER_DIRECTORY)
+            .ok_or("failed to initialise the shader compiler")?;
+        println!("Watching {} for changes", watcher.directory().display());
+
+        Some(watcher)
+    } else {
+        None
+    };
+
+    let mut show_debug_shapes = false;
+    let mut hud = Hud::new();
+    let mut show_hud = true;
+
+    let mut clock = Clock::real_time();
+
+    let mut recorder = match record {
+        Some(directory) => {
+            if !renderer.can_capture() {
+                return Err("the window's output can't be copied for recording".into());
+            }
+
+            println!(
+                "Recording {} frames at {} fps to {}",
+                record_format,
+                record_fps,
+                directory.display()
+            );
+
+            renderer.set_capture(true);
+
+            // Frame times are meaningless while every frame waits to be saved
+            show_hud = false;
 
-    let mut window_resized = false;
-    let mut recreate_swapchain_b = false;
+            let recorder = Recorder::new(directory, record_fps, &record_format)?;
 
-    let frames_in_flight = images.len();
-    let mut fences: Vec<Option<Arc<FenceSignalFuture<_>>>> = vec![None; frames_in_flight];
-    let mut previous_fence_i = 0;
+            // Only stepped once a frame has been saved, so a skipped frame is drawn again at the
+            // same time rather than leaving a jump in the recording
+            clock = Clock::fixed_step(recorder.timestep());
+            clock.set_paused(true);
+
+            Some(recorder)
+        }
+        None => None,
+    };
+
+    let mut timings_csv = match gpu_timings_csv {
+        Some(path) => Some(TimingsCsv::create(path)?),
+        None => None,
+    };
+    let mut timings_average = TimingsAverage::new();
+    let mut last_timing_report = Instant::now();
 
     let mut past_time = Instant::now();
-    let time = Instant::now();
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
             event: WindowEvent::CloseRequested,
             ..
         } => {
+            if let Err(e) = renderer.save_pipeline_cache() {
+                println!("Failed to save pipeline cache: {}", e);
+            }
+
+            if let Some(Err(e)) = timings_csv.as_mut().map(TimingsCsv::flush) {
+                println!("Failed to write GPU timings: {}", e);
+            }
+
             *control_flow = ControlFlow::Exit;
         }
         Event::WindowEvent {
-            event: WindowEvent::Resized(_),
+            event: WindowEvent::Resized(dimensions),
             ..
         } => {
-            window_resized = true;
+            renderer.resize(dimensions);
+        }
+        Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } => {
+            if let Some(debug_view) = number_key(key).and_then(DebugView::from_number) {
+                println!("Debug view: {:?}", debug_view);
+
+                renderer.set_debug_view(debug_view);
+            }
+
+            match key {
+                VirtualKeyCode::W => {
+                    let wireframe = renderer.wireframe().next();
+                    println!("Wireframe: {:?}", wireframe);
+
+                    renderer.set_wireframe(wireframe);
+                }
+                VirtualKeyCode::N => renderer.set_show_normals(!renderer.show_normals()),
+                VirtualKeyCode::B => show_debug_shapes = !show_debug_shapes,
+                VirtualKeyCode::H => show_hud = !show_hud,
+                VirtualKeyCode::S => match scene_file.save(&save_path) {
+                    Ok(()) => println!("Saved scene to {}", save_path.display()),
+                    Err(e) => println!("Failed to save scene: {}", e),
+                },
+                _ => (),
+            }
+
+            // The recording controls its own clock
+            if recorder.is_none() {
+                control_clock(&mut clock, key);
+            }
         }
         Event::RedrawEventsCleared => {
-            #[allow(unused_variables)]
             let dt = past_time.elapsed();
             past_time = Instant::now();
 
-            if recreate_swapchain_b {
-                recreate_swapchain_b = false;
+            hud.record_frame(dt);
+            clock.tick();
 
-                if window_resized || recreate_swapchain_b {
-                    recreate_swapchain_b = false;
+            let changes = if watch_assets {
+                assets.poll()
+            } else {
+                Vec::new()
+            };
 
-                    dimensions = surface.clone().window().inner_size();
+            // The renderer re-uploads the scene's buffers whenever its geometry changes
+            if let Some(handle) =
+                scene_handle.filter(|&handle| changes.contains(&AssetChange::Scene(handle)))
+            {
+                // The view stays where it was rather than jumping to the file's camera
+                let camera = scene_file.camera.clone();
+                scene_file = assets.scene(handle);
+                scene_file.camera = camera;
+
+                match scene_file.build_with_assets(&scene_directory, &mut assets) {
+                    Ok(new_scene) => scene = new_scene,
+                    Err(e) => println!("Failed to rebuild scene: {}", e),
+                }
 
-                    (
-                        swapchain,
-                        dimensions,
-                        framebuffers,
-                        render_pass,
-                        normal_buffer,
-                        colour_buffer,
-                    ) = recreate_swapchain(dimensions.clone(), device.clone(), swapchain.clone())
-                        .unwrap();
+                // Textures the scene didn't use before are uploaded, changed ones are below
+                match scene_file.load_textures(&scene_directory, &mut assets) {
+                    Ok(handles) => {
+                        for handle in handles {
+                            if renderer.texture(handle).is_none() {
+                                upload_texture(&mut renderer, &assets, handle);
+                            }
+                        }
+                    }
+                    Err(e) => println!("Failed to load textures: {}", e),
                 }
-            };
+            } else {
+                for &change in &changes {
+                    if let AssetChange::Mesh(handle) = change {
+                        let result = scene_file.reload_mesh(
+                            &mut scene,
+                            &scene_directory,
+                            &mut assets,
+                            handle,
+                        );
+
+                        if let Err(e) = result {
+                            println!("Failed to swap in mesh: {}", e);
+                        }
+                    }
+                }
+            }
 
-            let mut model_vec_clone = model_vec.clone();
+            for &change in &changes {
+                if let AssetChange::Texture(handle) = change {
+                    upload_texture(&mut renderer, &assets, handle);
+                }
+            }
 
-            model_vec_clone[0].set_matrix(vp::get_model(time.clone()));
-            model_vec_clone[1].set_matrix(vp::get_model_2(time.clone()));
+            if let Some(watcher) = &mut shader_watcher {
+                watcher.poll(&mut renderer);
 
-            let models = ModelCollection::from_vec(model_vec_clone.clone());
+                let dimensions = renderer.dimensions();
+                watcher.draw_errors(renderer.text_mut(), dimensions);
+            }
 
-            let vertex_buffer_e = CpuAccessibleBuffer::from_iter(
-                device.clone(),
-                BufferUsage::vertex_buffer(),
-                false,
-                models.vertices().into_iter(),
-            )
-            .unwrap();
+            scene_file.animate(&mut scene, &clock);
+            scene.select_lods(&vp::get_vp(renderer.dimensions()));
+            let camera = scene_file.camera(&clock);
 
-            let index_buffer_e = CpuAccessibleBuffer::from_iter(
-                device.clone(),
-                BufferUsage::index_buffer(),
-                false,
-                models.indices().into_iter(),
-            )
-            .unwrap();
+            if show_debug_shapes {
+                draw_debug_shapes(renderer.debug_draw_mut(), &scene);
+            }
 
-            viewport.dimensions = dimensions.into();
+            if show_hud {
+                let stats = renderer.stats();
+                let gpu_memory = renderer.gpu_memory();
 
-            let deferred_pass = Subpass::from(render_pass.clone(), 0).unwrap();
-            let lighting_pass = Subpass::from(render_pass.clone(), 1).unwrap();
+                hud.draw(renderer.text_mut(), stats, scene.lights().len(), gpu_memory);
+            }
 
-            let deferred_pipeline = get_pipeline_with_depth(
-                device.clone(),
-                deferred_vert.clone(),
-                deferred_frag.clone(),
-                deferred_pass.clone(),
-                viewport.clone(),
-            );
+            if let Err(e) = renderer.render(&scene, &camera) {
+                println!("Failed to render frame: {}", e);
 
-            let lighting_pipeline = get_pipeline(
-                device.clone(),
-                lighting_vert.clone(),
-                lighting_frag.clone(),
-                lighting_pass.clone(),
-                viewport.clone(),
-            );
+                *control_flow = ControlFlow::Exit;
+            }
 
-            let vp_buffer_subbuffer = {
-                let vp = vp::get_vp(dimensions);
-                let vp_data = deferred_vert::ty::VpData {
-                    view: vp.view.into(),
-                    proj: vp.proj.into(),
-                };
+            // Skipped frames aren't captured, so the same time is drawn again next frame
+            if let (Some(recorder), Some(frame)) = (&mut recorder, renderer.take_capture()) {
+                match recorder.save(&frame) {
+                    Ok(_) => clock.step(),
+                    Err(e) => {
+                        println!("Failed to save frame: {}", e);
 
-                vp_buffer.next(vp_data).unwrap()
-            };
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
 
-            let lighting_buffer_subbuffer = {
-                let light = Light::new(
-                    // Position
-                    [0.0, 0.0, -1.0],
-                    // Colour
-                    [
-                        ((time.elapsed().as_secs_f32() * 3f32).sin() + 1.0) * 0.5,
-                        ((time.elapsed().as_secs_f32()).cos() + 1.0) * 0.,
-                        1.0,
-                    ],
-                    // Intensity
-                    1.0,
-                );
-                let light_data = lighting_frag::ty::LightData {
-                    _dummy0: [0; 4],
-                    position: light.position().into(),
-                    colour: light.colour().into(),
-                    intensity: light.intensity().into(),
-                };
-
-                lighting_buffer.next(light_data).unwrap()
-            };
+                if record_frames.map_or(false, |frames| recorder.frame() >= frames) {
+                    println!("Recorded {} frames", recorder.frame());
 
-            let camera_buffer_subbuffer = {
-                let camera = Camera::new(
-                    [0.0, 0.0, 0.0],
-                    time.elapsed().as_secs().try_into().unwrap(),
-                );
-                let camera_data = lighting_frag::ty::CameraData {
-                    position: camera.position().into(),
-                    dt: camera.dt().into(),
-                };
-
-                camera_buffer.next(camera_data).unwrap()
-            };
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
 
-            let deferred_layout = deferred_pipeline
-                .layout()
-                .set_layouts()
-                .get(0)
-                .clone()
-                .unwrap();
-            let deferred_set = PersistentDescriptorSet::new(
-                deferred_layout.clone(),
-                [WriteDescriptorSet::buffer(0, vp_buffer_subbuffer.clone())],
-            )
-            .unwrap();
-
-            let lighting_layout = lighting_pipeline
-                .layout()
-                .set_layouts()
-                .get(0)
-                .clone()
-                .unwrap();
-            let lighting_set = PersistentDescriptorSet::new(
-                lighting_layout.clone(),
-                [
-                    WriteDescriptorSet::image_view(0, normal_buffer.clone()),
-                    WriteDescriptorSet::image_view(1, colour_buffer.clone()),
-                    WriteDescriptorSet::buffer(2, vp_buffer_subbuffer),
-                    WriteDescriptorSet::buffer(3, lighting_buffer_subbuffer),
-                    WriteDescriptorSet::buffer(4, camera_buffer_subbuffer),
-                ],
-            )
-            .unwrap();
-
-            let command_buffers = get_command_buffers(
-                device.clone(),
-                queue.clone(),
-                deferred_pipeline.clone(),
-                deferred_set.clone(),
-                lighting_pipeline.clone(),
-                lighting_set.clone(),
-                &framebuffers,
-                vertex_buffer_e.clone(),
-                index_buffer_e.clone(),
-            );
+            if let Some(timings) = renderer.gpu_timings() {
+                if let Some(csv) = &mut timings_csv {
+                    if let Err(e) = csv.write(&timings) {
+                        println!("Failed to write GPU timings: {}", e);
 
-            let (image_i, suboptimal, acquire_future) =
-                match vulkano::swapchain::acquire_next_image(swapchain.clone(), None) {
-                    Ok(r) => r,
-                    Err(AcquireError::OutOfDate) => {
-                        recreate_swapchain_b = true;
-                        return;
+                        timings_csv = None;
                     }
-                    Err(e) => panic!("Failed to acquire next image: {:?}", e),
-                };
+                }
 
-            if suboptimal {
-                recreate_swapchain_b = true;
+                if gpu_timings {
+                    timings_average.add(&timings);
+                }
             }
 
-            if let Some(image_fence) = &fences[image_i] {
-                image_fence.wait(None).unwrap();
+            if gpu_timings && last_timing_report.elapsed() >= TIMING_REPORT_INTERVAL {
+                last_timing_report = Instant::now();
+
+                if let Some(mean) = timings_average.take() {
+                    println!(
+                        "GPU: deferred {:.3} ms, lighting {:.3} ms, forward {:.3} ms, total {:.3} ms",
+                        mean.deferred,
+                        mean.lighting,
+                        mean.forward,
+                        mean.total()
+                    );
+                }
             }
+        }
+        Event::MainEventsCleared => {}
+        _ => (),
+    });
+}
 
-            let previous_future = match fences[previous_fence_i].clone() {
-                // Create a NowFuture
-                None => {
-                    let mut now = sync::now(device.clone());
-                    now.cleanup_finished();
+/// Uploads a loaded texture, carrying on with the previous image if it fails
+fn upload_texture(renderer: &mut Renderer, assets: &AssetRegistry, handle: TextureHandle) {
+    if let Err(e) = renderer.upload_texture(handle, assets.texture(handle)) {
+        println!("Failed to upload texture: {}", e);
+    }
+}
 
-                    now.boxed()
-                }
-                // Use the existing FenceSignalFuture
-                Some(fence) => fence.boxed(),
-            };
+/// Light positions, model bounds and a grid under the scene
+fn draw_debug_shapes(debug_draw: &mut DebugDraw, scene: &Scene) {
+    debug_draw.grid([0.0, 5.0, 20.0], 40.0, 20, [0.3, 0.3, 0.3]);
 
-            let future = previous_future
-                .join(acquire_future)
-                .then_execute(queue.clone(), command_buffers[image_i].clone())
-                .unwrap()
-                .then_swapchain_present(queue.clone(), swapchain.clone(), image_i)
-                .then_signal_fence_and_flush();
-
-            fences[image_i] = match future {
-                Ok(value) => Some(Arc::new(value)),
-                Err(FlushError::OutOfDate) => {
-                    recreate_swapchain_b = true;
-                    None
-                }
-                Err(e) => {
-                    println!("Failed to flush future: {:?}", e);
-                    None
-                }
-            };
+    for model in scene.models() {
+        let (min, max) = model.bounds();
+
+        debug_draw.aabb(min, max, [0.0, 1.0, 1.0]);
+        debug_draw.axes(model.matrix(), 1.0);
+    }
 
-            previous_fence_i = image_i;
+    // Lights are drawn over everything so they can be found
+    debug_draw.set_depth_test(false);
+    for light in scene.lights() {
+        debug_draw.sphere(light.position(), 0.25, light.colour());
+    }
+    debug_draw.set_depth_test(true);
+}
+
+/// P pauses, full stop steps while paused, left and right scrub by half a second and plus and
+/// minus double or halve the speed
+fn control_clock(clock: &mut Clock, key: VirtualKeyCode) {
+    match key {
+        VirtualKeyCode::P => {
+            clock.set_paused(!clock.is_paused());
+            println!("Paused: {}", clock.is_paused());
+        }
+        VirtualKeyCode::Period => clock.step(),
+        VirtualKeyCode::Left => clock.scrub(-0.5),
+        VirtualKeyCode::Right => clock.scrub(0.5),
+        VirtualKeyCode::Equals | VirtualKeyCode::Plus | VirtualKeyCode::NumpadAdd => {
+            clock.set_scale(clock.scale() * 2.0);
+            println!("Time scale: {}", clock.scale());
+        }
+        VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+            clock.set_scale(clock.scale() * 0.5);
+            println!("Time scale: {}", clock.scale());
         }
-        Event::MainEventsCleared => {}
         _ => (),
-    });
+    }
+}
+
+fn number_key(key: VirtualKeyCode) -> Option<u32> {
+    match key {
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some(0),
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some(1),
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some(2),
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some(3),
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some(4),
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some(5),
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some(6),
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some(7),
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some(8),
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some(9),
+        _ => None,
+    }
+}
+
+/// Reads `--present-mode <fifo|mailbox|immediate>`, `--frames-in-flight <n>`, `--fps <n>`,
+/// `--device <index|name>`, `--list-devices`, `--debug`, `--log-level <level>`, `--gpu-timings`,
+/// `--gpu-timings-csv <path>`, `--record <directory>`, `--record-fps <n>`,
+/// `--record-format <png|exr>`, `--record-frames <n>`, `--scene <path>`, `--watch-shaders` and
+/// `--watch-assets`
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut settings = RenderSettings::default();
+    let mut list_devices = false;
+    let mut debug = false;
+    let mut log_level = log::LevelFilter::Warn;
+    let mut gpu_timings = false;
+    let mut gpu_timings_csv = None;
+    let mut record = None;
+    let mut record_fps = 60.0;
+    let mut record_format = "png".to_string();
+    let mut record_frames = None;
+    let mut scene = None;
+    let mut watch_shaders = false;
+    let mut watch_assets = false;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "--present-mode" => {
+                settings.present_mode = match value()?.to_lowercase().as_str() {
+                    "fifo" => PresentMode::Fifo,
+                    "fifo-relaxed" => PresentMode::FifoRelaxed,
+                    "mailbox" => PresentMode::Mailbox,
+                    "immediate" => PresentMode::Immediate,
+                    other => return Err(format!("unknown present mode {}", other)),
+                }
+            }
+            "--frames-in-flight" => {
+                settings.frames_in_flight = match value()?.parse() {
+                    Ok(n) if n > 0 => n,
+                    _ => return Err("--frames-in-flight must be at least 1".to_string()),
+                }
+            }
+            "--fps" => {
+                settings.target_fps = match value()?.parse() {
+                    Ok(fps) if fps > 0.0 => Some(fps),
+                    _ => return Err("--fps must be a positive number".to_string()),
+                }
+            }
+            "--device" => settings.device = Some(value()?.parse().unwrap()),
+            "--list-devices" => list_devices = true,
+            "--debug" => debug = true,
+            "--log-level" => {
+                log_level = value()?
+                    .parse()
+                    .map_err(|_| "--log-level must be off, error, warn, info, debug or trace")?
+            }
+            "--gpu-timings" => gpu_timings = true,
+            "--gpu-timings-csv" => gpu_timings_csv = Some(PathBuf::from(value()?)),
+            "--record" => record = Some(PathBuf::from(value()?)),
+            "--record-fps" => {
+                record_fps = match value()?.parse() {
+                    Ok(fps) if fps > 0.0 => fps,
+                    _ => return Err("--record-fps must be a positive number".to_string()),
+                }
+            }
+            "--record-format" => {
+                record_format = match value()?.to_lowercase().as_str() {
+                    format @ ("png" | "exr") => format.to_string(),
+                    other => return Err(format!("unknown record format {}", other)),
+                }
+            }
+            "--record-frames" => {
+                record_frames = Some(
+                    value()?
+                        .parse()
+                        .map_err(|_| "--record-frames must be a whole number")?,
+                )
+            }
+            "--scene" => scene = Some(PathBuf::from(value()?)),
+            "--watch-shaders" => watch_shaders = true,
+            "--watch-assets" => watch_assets = true,
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    Ok(Options {
+        settings,
+        list_devices,
+        debug,
+        log_level,
+        gpu_timings,
+        gpu_timings_csv,
+        record,
+        record_fps,
+        record_format,
+        record_frames,
+        scene,
+        watch_shaders,
+        watch_assets,
+    })
 }
@@ -1,12 +1,36 @@
-use crate::vertex::{make_square_indices, Index, Vertex};
+use crate::morph::Morph;
+use crate::skeleton::{skin_matrix, Skin};
+use crate::vertex::{
+    make_square_indices, CompactVec3, Index, LineVertex, MorphDelta, MorphVertex, SkinVertex,
+    Vertex, WireVertex,
+};
 
 use nalgebra_glm::{identity, TMat4, TVec3, TVec4};
 
+use serde::{Deserialize, Serialize};
+
+/// How a model's surface is shaded
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Material {
+    pub albedo: CompactVec3,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            albedo: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct Model {
     vertices: Vec<Vertex>,
     indices: Vec<Index>,
     matrix: TMat4<f32>,
+    material: Material,
+    skin: Option<Skin>,
+    morph: Option<Morph>,
 }
 
 #[allow(dead_code)]
@@ -16,6 +40,9 @@ impl Model {
             vertices,
             indices,
             matrix: identity(),
+            material: Material::default(),
+            skin: None,
+            morph: None,
         }
     }
 
@@ -24,6 +51,9 @@ impl Model {
             vertices: vertices.clone(),
             indices: make_square_indices(&vertices),
             matrix: identity(),
+            material: Material::default(),
+            skin: None,
+            morph: None,
         }
     }
 
@@ -42,58 +72,199 @@ impl Model {
     pub fn set_matrix(self: &mut Self, matrix: TMat4<f32>) {
         self.matrix = matrix;
     }
+
+    pub fn material(self: &Self) -> Material {
+        self.material
+    }
+
+    pub fn set_material(self: &mut Self, material: Material) {
+        self.material = material;
+    }
+
+    pub fn skin(self: &Self) -> Option<&Skin> {
+        self.skin.as_ref()
+    }
+
+    /// For posing the skeleton
+    pub fn skin_mut(self: &mut Self) -> Option<&mut Skin> {
+        self.skin.as_mut()
+    }
+
+    /// Panics if the skin doesn't have weights for every vertex
+    pub fn set_skin(self: &mut Self, skin: Option<Skin>) {
+        if let Some(skin) = &skin {
+            assert_eq!(skin.vertices().len(), self.vertices.len());
+        }
+
+        self.skin = skin;
+    }
+
+    pub fn morph(self: &Self) -> Option<&Morph> {
+        self.morph.as_ref()
+    }
+
+    /// For setting the morph target weights
+    pub fn morph_mut(self: &mut Self) -> Option<&mut Morph> {
+        self.morph.as_mut()
+    }
+
+    /// Panics if a target doesn't have a delta for every vertex
+    pub fn set_morph(self: &mut Self, morph: Option<Morph>) {
+        if let Some(morph) = &morph {
+            for target in morph.targets() {
+                assert_eq!(target.positions.len(), self.vertices.len());
+                assert!(target.normals.is_empty() || target.normals.len() == self.vertices.len());
+            }
+        }
+
+        self.morph = morph;
+    }
+
+    /// The smallest axis aligned box around the transformed and posed vertices, as its min and
+    /// max corners
+    pub fn bounds(self: &Self) -> (CompactVec3, CompactVec3) {
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+
+        let skinning = self
+            .skin
+            .as_ref()
+            .map(|skin| (skin.vertices(), skin.skeleton().joint_matrices()));
+
+        let vertices = match &self.morph {
+            Some(morph) => morph.apply(&self.vertices),
+            None => self.vertices.clone(),
+        };
+
+        for (i, v) in vertices.iter().enumerate() {
+            let matrix = match &skinning {
+                Some((skin_vertices, joint_matrices)) => {
+                    self.matrix * skin_matrix(&skin_vertices[i], joint_matrices)
+                }
+                None => self.matrix,
+            };
+            let position =
+                matrix * TVec4::<f32>::new(v.position[0], v.position[1], v.position[2], 1.0);
+
+            for axis in 0..3 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+
+        (min, max)
+    }
 }
 
 #[derive(Default, Clone)]
 pub struct ModelCollection {
     vertices: Vec<Vertex>,
+    skin_vertices: Vec<SkinVertex>,
+    morph_vertices: Vec<MorphVertex>,
     indices: Vec<Index>,
+    joint_matrices: Vec<TMat4<f32>>,
+    morph_deltas: Vec<MorphDelta>,
+    morph_weights: Vec<f32>,
 }
 
 impl ModelCollection {
+    /// Vertices stay in model space, so a model that only moves changes the joint matrices rather
+    /// than the vertices. An unskinned model is posed on the GPU as a skin with one joint holding
+    /// its matrix, and a skinned model by its joint matrices, which include the model's matrix.
+    /// Morph targets are blended on the GPU before skinning
     pub fn from_vec(models: Vec<Model>) -> Self {
         let mut vertices: Vec<Vertex> = Vec::new();
+        let mut skin_vertices: Vec<SkinVertex> = Vec::new();
+        let mut morph_vertices: Vec<MorphVertex> = Vec::new();
         let mut indices: Vec<Index> = Vec::new();
+        let mut joint_matrices: Vec<TMat4<f32>> = Vec::new();
+        let mut morph_deltas: Vec<MorphDelta> = Vec::new();
+        let mut morph_weights: Vec<f32> = Vec::new();
 
-        for i in 0..models.len() {
-            let mut model_vertices: Vec<Vertex> = models[i]
-                .vertices()
-                .iter_mut()
-                .map(|v| {
-                    // Must use w coordinate so translation is applied
-                    let new_vertex_position = models[i].matrix()
-                        * TVec4::<f32>::new(v.position[0], v.position[1], v.position[2], 1.0);
-
-                    let new_vertex_normals = models[i].matrix().transform_vector(
-                        &TVec3::<f32>::new(v.normal[0], v.normal[1], v.normal[2]),
-                    );
+        for model in &models {
+            let albedo = model.material().albedo;
 
-                    Vertex::new(
-                        [
-                            new_vertex_position.x,
-                            new_vertex_position.y,
-                            new_vertex_position.z,
-                        ],
-                        [
-                            new_vertex_normals.x,
-                            new_vertex_normals.y,
-                            new_vertex_normals.z,
-                        ],
-                    )
-                })
-                .collect();
-
-            let mut model_indices: Vec<Index> = models[i]
+            let mut model_indices: Vec<Index> = model
                 .indices()
-                .iter_mut()
-                .map(|&mut i| i + vertices.len() as Index)
+                .iter()
+                .map(|&i| i + vertices.len() as Index)
                 .collect();
 
-            vertices.append(&mut model_vertices);
+            let first_joint = joint_matrices.len() as u32;
+            vertices.extend(model.vertices().iter().map(|v| v.with_colour(albedo)));
+
+            match model.skin() {
+                Some(skin) => {
+                    skin_vertices.extend(skin.vertices().iter().map(|v| {
+                        SkinVertex::new(v.joints.map(|joint| joint + first_joint), v.weights)
+                    }));
+                    joint_matrices.extend(
+                        skin.skeleton()
+                            .joint_matrices()
+                            .into_iter()
+                            .map(|joint| model.matrix() * joint),
+                    );
+                }
+                None => {
+                    let vertex = SkinVertex::new([first_joint, 0, 0, 0], [1.0, 0.0, 0.0, 0.0]);
+
+                    skin_vertices.extend(model.vertices().iter().map(|_| vertex));
+                    joint_matrices.push(model.matrix());
+                }
+            }
+
+            match model.morph() {
+                Some(morph) => {
+                    let first_weight = morph_weights.len() as u32;
+                    let target_count = morph.targets().len() as u32;
+
+                    for i in 0..model.vertices.len() {
+                        morph_vertices.push(MorphVertex::new(
+                            morph_deltas.len() as u32,
+                            first_weight,
+                            target_count,
+                        ));
+                        morph_deltas.extend(morph.targets().iter().map(|target| {
+                            let (position, normal) = target.delta(i);
+
+                            MorphDelta::new(position, normal)
+                        }));
+                    }
+
+                    morph_weights.extend(morph.weights());
+                }
+                None => {
+                    morph_vertices.extend(model.vertices().iter().map(|_| MorphVertex::default()))
+                }
+            }
+
             indices.append(&mut model_indices);
         }
 
-        Self { vertices, indices }
+        Self {
+            vertices,
+            skin_vertices,
+            morph_vertices,
+            indices,
+            joint_matrices,
+            morph_deltas,
+            morph_weights,
+        }
+    }
+
+    /// Whether the vertex and index buffers of `other` would be the same as these
+    pub fn same_geometry(self: &Self, other: &Self) -> bool {
+        self.vertices == other.vertices
+            && self.skin_vertices == other.skin_vertices
+            && self.morph_vertices == other.morph_vertices
+            && self.indices == other.indices
+    }
+
+    /// Whether the joint matrices, morph deltas and weights of `other` are the same as these
+    pub fn same_deformation(self: &Self, other: &Self) -> bool {
+        self.joint_matrices == other.joint_matrices
+            && self.morph_deltas == other.morph_deltas
+            && self.morph_weights == other.morph_weights
     }
 
     pub fn vertices(self: &Self) -> Vec<Vertex> {
@@ -107,4 +278,105 @@ impl ModelCollection {
 
         self.indices.clone()
     }
+
+    /// Parallel to `vertices`
+    pub fn skin_vertices(self: &Self) -> Vec<SkinVertex> {
+        self.skin_vertices.clone()
+    }
+
+    /// Every skinned model's joint matrices and every other model's matrix, which `skin_vertices`
+    /// index into
+    pub fn joint_matrices(self: &Self) -> Vec<TMat4<f32>> {
+        self.joint_matrices.clone()
+    }
+
+    /// Parallel to `vertices`
+    pub fn morph_vertices(self: &Self) -> Vec<MorphVertex> {
+        self.morph_vertices.clone()
+    }
+
+    /// Every morphed model's deltas, which `morph_vertices` index into
+    pub fn morph_deltas(self: &Self) -> Vec<MorphDelta> {
+        self.morph_deltas.clone()
+    }
+
+    /// Every morphed model's target weights, which `morph_vertices` index into
+    pub fn morph_weights(self: &Self) -> Vec<f32> {
+        self.morph_weights.clone()
+    }
+
+    /// The vertices in world space with morphing and skinning applied on the CPU, for debug
+    /// drawing
+    pub fn posed_vertices(self: &Self) -> Vec<Vertex> {
+        self.vertices
+            .iter()
+            .zip(&self.skin_vertices)
+            .zip(&self.morph_vertices)
+            .map(|((v, skin), morph)| {
+                let mut position = TVec3::from(v.position);
+                let mut normal = TVec3::from(v.normal);
+
+                for target in 0..morph.target_count as usize {
+                    let delta = self.morph_deltas[morph.first_delta as usize + target];
+                    let weight = self.morph_weights[morph.first_weight as usize + target];
+
+                    position += TVec3::new(delta.position[0], delta.position[1], delta.position[2])
+                        * weight;
+                    normal +=
+                        TVec3::new(delta.normal[0], delta.normal[1], delta.normal[2]) * weight;
+                }
+
+                let matrix = skin_matrix(skin, &self.joint_matrices);
+                let position = matrix * TVec4::<f32>::new(position.x, position.y, position.z, 1.0);
+                let normal = matrix.transform_vector(&normal);
+
+                Vertex::new(
+                    [position.x, position.y, position.z],
+                    [normal.x, normal.y, normal.z],
+                )
+                .with_colour(v.colour)
+                .with_uv(v.uv)
+            })
+            .collect()
+    }
+
+    /// Every model as one mesh in world space, posed as in `posed_vertices`, for exporting
+    pub fn posed_model(self: &Self) -> Model {
+        Model::new(self.posed_vertices(), self.indices.clone())
+    }
+
+    /// Every triangle with its own copy of its corners, for drawing as a wireframe
+    pub fn wireframe_vertices(self: &Self) -> Vec<WireVertex> {
+        const CORNERS: [CompactVec3; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+        let vertices = self.posed_vertices();
+
+        self.indices
+            .chunks_exact(3)
+            .flat_map(|triangle| {
+                triangle.iter().zip(CORNERS).map(|(&i, barycentric)| {
+                    WireVertex::new(vertices[i as usize].position, barycentric)
+                })
+            })
+            .collect()
+    }
+
+    /// A line list with a line of `length` along the normal of each vertex
+    pub fn normal_lines(self: &Self, length: f32, colour: CompactVec3) -> Vec<LineVertex> {
+        self.posed_vertices()
+            .iter()
+            .flat_map(|v| {
+                let start = TVec3::from(v.position);
+                let normal = TVec3::from(v.normal)
+                    .try_normalize(f32::EPSILON)
+                    .unwrap_or_else(TVec3::zeros);
+                let end = start + normal * length;
+
+                [
+                    LineVertex::new(v.position, colour),
+                    LineVertex::new([end.x, end.y, end.z], colour),
+                ]
+            })
+            .collect()
+    }
 }
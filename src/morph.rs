@@ -0,0 +1,83 @@
+use crate::vertex::{CompactVec3, Vertex};
+
+/// Offsets from a mesh's base shape, one for each vertex
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MorphTarget {
+    pub name: String,
+    pub positions: Vec<CompactVec3>,
+    /// Empty if the target doesn't change the normals
+    pub normals: Vec<CompactVec3>,
+}
+
+impl MorphTarget {
+    /// The position and normal delta of one vertex
+    pub fn delta(self: &Self, vertex: usize) -> (CompactVec3, CompactVec3) {
+        (
+            self.positions[vertex],
+            self.normals.get(vertex).copied().unwrap_or([0.0; 3]),
+        )
+    }
+}
+
+/// A mesh's morph targets and how much of each is blended in
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Morph {
+    targets: Vec<MorphTarget>,
+    weights: Vec<f32>,
+}
+
+#[allow(dead_code)]
+impl Morph {
+    /// Starts with every weight at zero
+    pub fn new(targets: Vec<MorphTarget>) -> Self {
+        Self {
+            weights: vec![0.0; targets.len()],
+            targets,
+        }
+    }
+
+    pub fn targets(self: &Self) -> &[MorphTarget] {
+        &self.targets
+    }
+
+    pub fn find(self: &Self, name: &str) -> Option<usize> {
+        self.targets.iter().position(|target| target.name == name)
+    }
+
+    pub fn weights(self: &Self) -> Vec<f32> {
+        self.weights.clone()
+    }
+
+    pub fn set_weight(self: &mut Self, target: usize, weight: f32) {
+        self.weights[target] = weight;
+    }
+
+    /// Extra weights are ignored, and missing ones are left as they are
+    pub fn set_weights(self: &mut Self, weights: &[f32]) {
+        for (weight, &new) in self.weights.iter_mut().zip(weights) {
+            *weight = new;
+        }
+    }
+
+    /// Blends the weighted targets into the vertices on the CPU, as the vertex shader does
+    pub fn apply(self: &Self, vertices: &[Vertex]) -> Vec<Vertex> {
+        vertices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let mut v = *v;
+
+                for (target, &weight) in self.targets.iter().zip(&self.weights) {
+                    let (position, normal) = target.delta(i);
+
+                    for axis in 0..3 {
+                        v.position[axis] += position[axis] * weight;
+                        v.normal[axis] += normal[axis] * weight;
+                    }
+                }
+
+                v
+            })
+            .collect()
+    }
+}
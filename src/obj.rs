@@ -0,0 +1,214 @@
+use nalgebra_glm::TVec3;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::error::SceneError;
+use crate::geometry::{reverse_triangle, Winding};
+use crate::model::Model;
+use crate::vertex::{CompactVec3, Index, Vertex};
+
+/// Loads the positions, normals and faces of a Wavefront OBJ file as a single model
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Model, SceneError> {
+    parse_obj(&fs::read_to_string(path)?)
+}
+
+/// Parses OBJ source. Polygons are split into triangle fans with their corners reversed to the
+/// renderer's clockwise winding, and faces without normals are given flat ones. Groups and
+/// materials are ignored. Fails if there are no faces
+pub fn parse_obj(source: &str) -> Result<Model, SceneError> {
+    let mut positions: Vec<CompactVec3> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<CompactVec3> = Vec::new();
+
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut indices: Vec<Index> = Vec::new();
+    // Corners sharing a position, texture coordinates and normal share a vertex
+    let mut shared: HashMap<Corner, Index> = HashMap::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |message: String| SceneError::Parse {
+            line: line_number,
+            message,
+        };
+
+        let line = line.split('#').next().unwrap().trim();
+        let mut parts = line.split_whitespace();
+
+        match parts.next() {
+            Some("v") => positions.push(parse_vec3(parts).map_err(error)?),
+            Some("vt") => uvs.push(parse_uv(parts).map_err(error)?),
+            Some("vn") => normals.push(parse_vec3(parts).map_err(error)?),
+            Some("f") => {
+                let corners = parts
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(error)?;
+
+                if corners.len() < 3 {
+                    return Err(error("face has fewer than 3 corners".to_string()));
+                }
+
+                for j in 1..corners.len() - 1 {
+                    let triangle = reverse_triangle([corners[0], corners[j], corners[j + 1]]);
+                    let uv = |corner: &Corner| corner.uv.map(|uv| uvs[uv]).unwrap_or_default();
+
+                    if triangle.iter().all(|corner| corner.normal.is_some()) {
+                        for corner in triangle {
+                            let index = *shared.entry(corner).or_insert_with(|| {
+                                vertices.push(
+                                    Vertex::new(
+                                        positions[corner.position],
+                                        normals[corner.normal.unwrap()],
+                                    )
+                                    .with_uv(uv(&corner)),
+                                );
+
+                                (vertices.len() - 1) as Index
+                            });
+
+                            indices.push(index);
+                        }
+                    } else {
+                        let normal = face_normal(triangle.map(|corner| positions[corner.position]));
+
+                        for corner in triangle {
+                            vertices.push(
+                                Vertex::new(positions[corner.position], normal)
+                                    .with_uv(uv(&corner)),
+                            );
+                            indices.push((vertices.len() - 1) as Index);
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if indices.is_empty() {
+        return Err(SceneError::InvalidMesh("file has no faces".to_string()));
+    }
+
+    Ok(Model::new(vertices, indices))
+}
+
+/// Writes the model's vertices in its own space, with each vertex's position, texture coordinates
+/// and normal. Skinning and morph targets are left out, so use `ModelCollection::posed_model` for
+/// a posed mesh
+pub fn save_obj(model: &Model, path: impl AsRef<Path>) -> Result<(), SceneError> {
+    Ok(fs::write(path, write_obj(model))?)
+}
+
+/// The OBJ source for a model, which `parse_obj` reads back as the same vertices and triangles
+pub fn write_obj(model: &Model) -> String {
+    let vertices = model.vertices();
+    let mut source = String::new();
+
+    for v in &vertices {
+        let [x, y, z] = v.position;
+        writeln!(source, "v {} {} {}", x, y, z).unwrap();
+    }
+
+    for v in &vertices {
+        let [u, v] = v.uv;
+        writeln!(source, "vt {} {}", u, 1.0 - v).unwrap();
+    }
+
+    for v in &vertices {
+        let [x, y, z] = v.normal;
+        writeln!(source, "vn {} {} {}", x, y, z).unwrap();
+    }
+
+    for triangle in model.indices().chunks_exact(3) {
+        let [a, b, c] = reverse_triangle([triangle[0], triangle[1], triangle[2]]).map(|i| i + 1);
+        writeln!(source, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c).unwrap();
+    }
+
+    source
+}
+
+/// Which of the positions, texture coordinates and normals read so far a face corner uses, from 0
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+pub(crate) fn parse_vec3<'a>(
+    mut parts: impl Iterator<Item = &'a str>,
+) -> Result<CompactVec3, String> {
+    let mut component = || {
+        let part = parts.next().ok_or("expected 3 numbers")?;
+
+        part.parse::<f32>()
+            .map_err(|_| format!("invalid number {}", part))
+    };
+
+    Ok([component()?, component()?, component()?])
+}
+
+/// OBJ texture coordinates have v going up from the bottom, the opposite of the renderer
+fn parse_uv<'a>(mut parts: impl Iterator<Item = &'a str>) -> Result<[f32; 2], String> {
+    let mut component = || {
+        let part = parts.next().ok_or("expected 2 numbers")?;
+
+        part.parse::<f32>()
+            .map_err(|_| format!("invalid number {}", part))
+    };
+
+    let u = component()?;
+
+    Ok([u, 1.0 - component()?])
+}
+
+/// Reads a `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner. Negative indices count back from the
+/// latest element
+fn parse_corner(
+    corner: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<Corner, String> {
+    let mut indices = corner.split('/');
+
+    let resolve = |index: &str, count: usize| -> Result<usize, String> {
+        let index: i64 = index
+            .parse()
+            .map_err(|_| format!("invalid index {}", index))?;
+        let resolved = if index < 0 {
+            count as i64 + index
+        } else {
+            index - 1
+        };
+
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(format!("index {} out of range", index));
+        }
+
+        Ok(resolved as usize)
+    };
+    let optional = |index: Option<&str>, count: usize| match index {
+        Some(index) if !index.is_empty() => resolve(index, count).map(Some),
+        _ => Ok(None),
+    };
+
+    Ok(Corner {
+        position: resolve(indices.next().unwrap_or(""), position_count)?,
+        uv: optional(indices.next(), uv_count)?,
+        normal: optional(indices.next(), normal_count)?,
+    })
+}
+
+fn face_normal(corners: [CompactVec3; 3]) -> CompactVec3 {
+    let normal = Winding::Clockwise
+        .face_normal(corners.map(TVec3::from))
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(TVec3::zeros);
+
+    [normal.x, normal.y, normal.z]
+}
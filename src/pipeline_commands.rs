@@ -1,50 +1,69 @@
-use vulkano::buffer::{CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::buffer::BufferAccess;
 use vulkano::command_buffer::{
     AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, SubpassContents,
 };
 use vulkano::descriptor_set::PersistentDescriptorSet;
 use vulkano::device::physical::{PhysicalDevice, PhysicalDeviceType, QueueFamily};
-use vulkano::device::{Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo};
+use vulkano::device::{
+    Device, DeviceCreateInfo, DeviceExtensions, Features, Queue, QueueCreateInfo,
+};
 use vulkano::format::Format;
-use vulkano::image::{view::ImageView, AttachmentImage, ImageUsage, SwapchainImage};
+use vulkano::image::view::{ImageView, ImageViewAbstract};
+use vulkano::image::AttachmentImage;
 use vulkano::instance::{Instance, InstanceCreateInfo};
-use vulkano::pipeline::graphics::depth_stencil::DepthStencilState;
-use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
-use vulkano::pipeline::graphics::rasterization::{CullMode, RasterizationState};
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
+use vulkano::pipeline::graphics::depth_stencil::{CompareOp, DepthState, DepthStencilState};
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::rasterization::{
+    CullMode, DepthBias, DepthBiasState, PolygonMode, RasterizationState,
+};
 use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
 use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
-use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint, StateMode};
 use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
-use vulkano::shader::ShaderModule;
-use vulkano::swapchain::{Surface, Swapchain, SwapchainCreateInfo, SwapchainCreationError};
-
-use vulkano_win::VkSurfaceBuild;
-
-use winit::event_loop::EventLoop;
-use winit::window::{Window, WindowBuilder};
+use vulkano::shader::{EntryPoint, ShaderModule};
+use vulkano::swapchain::Surface;
+use vulkano::sync::PipelineStage;
+use vulkano::Version;
 
 use std::sync::Arc;
 
-use crate::vertex::{Index, Vertex};
+use crate::device::DeviceSelector;
+use crate::error::RenderError;
+use crate::profiler::FrameQueries;
+use crate::renderer::Mesh;
+use crate::shader::lighting_frag;
+use crate::vertex::{LineVertex, MorphVertex, SkinVertex, TextVertex, Vertex, WireVertex};
 
-pub fn create_instance() -> Arc<Instance> {
-    Instance::new(InstanceCreateInfo {
+pub fn create_instance() -> Result<Arc<Instance>, RenderError> {
+    Ok(Instance::new(InstanceCreateInfo {
         enabled_extensions: vulkano_win::required_extensions(),
         ..Default::default()
-    })
-    .expect("failed to create instance")
+    })?)
 }
 
-pub fn select_physical_device<'a>(
+pub fn select_physical_device<'a, W>(
     instance: &'a Arc<Instance>,
-    surface: Arc<Surface<Window>>,
+    surface: Option<&Arc<Surface<W>>>,
     device_extensions: &DeviceExtensions,
-) -> (PhysicalDevice<'a>, QueueFamily<'a>) {
-    let (physical_device, queue_family) = PhysicalDevice::enumerate(&instance)
+    selector: Option<&DeviceSelector>,
+) -> Result<(PhysicalDevice<'a>, QueueFamily<'a>), RenderError> {
+    // An explicit selector takes priority over the environment
+    let env_selector = DeviceSelector::from_env();
+    let selector = selector.or(env_selector.as_ref());
+
+    let selected = PhysicalDevice::enumerate(&instance)
+        .filter(|p| selector.map_or(true, |selector| selector.matches(p)))
         .filter(|&p| p.supported_extensions().is_superset_of(&device_extensions))
         .filter_map(|p| {
             p.queue_families()
-                .find(|&q| q.supports_graphics() && q.supports_surface(&surface).unwrap_or(false))
+                .find(|&q| {
+                    // Headless rendering has no surface that needs presenting to
+                    q.supports_graphics()
+                        && surface
+                            .map_or(true, |surface| q.supports_surface(surface).unwrap_or(false))
+                })
                 .map(|q| (p, q))
         })
         .min_by_key(|(p, _)| match p.properties().device_type {
@@ -53,32 +72,45 @@ pub fn select_physical_device<'a>(
             PhysicalDeviceType::VirtualGpu => 2,
             PhysicalDeviceType::Cpu => 3,
             PhysicalDeviceType::Other => 4,
-        })
-        .expect("no device available");
+        });
 
-    (physical_device, queue_family)
+    match (selected, selector) {
+        (Some(selected), _) => Ok(selected),
+        (None, Some(selector)) => Err(RenderError::DeviceNotFound(selector.clone())),
+        (None, None) => Err(RenderError::NoDevice),
+    }
 }
 
-pub fn get_devices_surface_queue<'a>(
-    event_loop: &EventLoop<()>,
-    instance: &'a Arc<Instance>,
-) -> (
-    PhysicalDevice<'a>,
-    Arc<Device>,
-    Arc<Queue>,
-    Arc<Surface<Window>>,
-) {
-    let surface = WindowBuilder::new()
-        .build_vk_surface(&event_loop, instance.clone())
-        .unwrap();
-
+pub fn get_device_queue<W>(
+    instance: &Arc<Instance>,
+    surface: Option<&Arc<Surface<W>>>,
+    selector: Option<&DeviceSelector>,
+) -> Result<(Arc<Device>, Arc<Queue>), RenderError> {
     let device_extensions = DeviceExtensions {
-        khr_swapchain: true,
+        khr_swapchain: surface.is_some(),
         ..DeviceExtensions::none()
     };
 
     let (physical_device, queue_family) =
-        select_physical_device(&instance, surface.clone(), &device_extensions);
+        select_physical_device(&instance, surface, &device_extensions, selector)?;
+
+    log::info!("Using {}", physical_device.properties().device_name);
+
+    // Wireframes fall back to a shader when polygons can't be drawn as lines
+    let features = Features {
+        fill_mode_non_solid: physical_device.supported_features().fill_mode_non_solid,
+        ..Features::none()
+    };
+
+    // Only used to show memory use, so it's enabled if available rather than required
+    let optional_extensions = DeviceExtensions {
+        ext_memory_budget: physical_device.supported_extensions().ext_memory_budget
+            && (instance.api_version() >= Version::V1_1
+                || instance
+                    .enabled_extensions()
+                    .khr_get_physical_device_properties2),
+        ..DeviceExtensions::none()
+    };
 
     let (device, mut queues) = Device::new(
         physical_device,
@@ -86,68 +118,29 @@ pub fn get_devices_surface_queue<'a>(
             queue_create_infos: vec![QueueCreateInfo::family(queue_family)],
             enabled_extensions: physical_device
                 .required_extensions()
-                .union(&device_extensions), // new
+                .union(&device_extensions) // new
+                .union(&optional_extensions),
+            enabled_features: features,
             ..Default::default()
         },
-    )
-    .expect("failed to create device");
+    )?;
 
-    let queue = queues.next().unwrap();
+    let queue = queues.next().ok_or(RenderError::NoQueue)?;
 
-    (physical_device, device, queue, surface)
+    Ok((device, queue))
 }
 
-pub fn new_swapchain_images(
+pub fn get_render_pass(
     device: Arc<Device>,
-    physical_device: PhysicalDevice,
-    surface: &Arc<Surface<Window>>,
-) -> (
-    Arc<Swapchain<Window>>,
-    Vec<Arc<SwapchainImage<Window>>>,
-    winit::dpi::PhysicalSize<u32>,
-) {
-    let capabilities = physical_device
-        .surface_capabilities(&surface, Default::default())
-        .expect("failed to get surface capabilities");
-
-    let dimensions = surface.window().inner_size();
-    let composite_alpha = capabilities
-        .supported_composite_alpha
-        .iter()
-        .next()
-        .unwrap();
-    let image_format = Some(
-        physical_device
-            .surface_formats(&surface, Default::default())
-            .unwrap()[0]
-            .0,
-    );
-
-    let (swapchain, images) = Swapchain::new(
-        device.clone(),
-        surface.clone(),
-        SwapchainCreateInfo {
-            min_image_count: capabilities.min_image_count + 1, // How many buffers to use in the swapchain
-            image_format,
-            image_extent: dimensions.into(),
-            image_usage: ImageUsage::color_attachment(), // What the images are going to be used for
-            composite_alpha,
-            ..Default::default()
-        },
-    )
-    .unwrap();
-
-    (swapchain, images, dimensions)
-}
-
-pub fn get_render_pass(device: Arc<Device>, swapchain: Arc<Swapchain<Window>>) -> Arc<RenderPass> {
-    vulkano::ordered_passes_renderpass!(
+    final_format: Format,
+) -> Result<Arc<RenderPass>, RenderError> {
+    Ok(vulkano::ordered_passes_renderpass!(
         device.clone(),
         attachments: {
                 final_colour: {
                     load: Clear,
                     store: Store,
-                    format: swapchain.image_format(),  // set the format the same as the swapchain
+                    format: final_format,  // set the format the same as the swapchain or output image
                     samples: 1,
                 },
 
@@ -179,41 +172,43 @@ pub fn get_render_pass(device: Arc<Device>, swapchain: Arc<Swapchain<Window>>) -
             {
                 color: [final_colour],
                 depth_stencil: {},
-                input: [normals, colour]
+                input: [normals, colour, depth]
+            },
+            {
+                // Forward pass for wireframes and lines over the lit output
+                color: [final_colour],
+                depth_stencil: {depth},
+                input: []
             }
         ]
-    )
-    .unwrap()
+    )?)
 }
 
 pub fn new_attachment_image(
     device: Arc<Device>,
     dimensions: winit::dpi::PhysicalSize<u32>,
     format: Format,
-) -> Arc<ImageView<AttachmentImage>> {
-    ImageView::new_default(
+) -> Result<Arc<ImageView<AttachmentImage>>, RenderError> {
+    Ok(ImageView::new_default(
         AttachmentImage::transient_input_attachment(
             device.clone(),
             dimensions.clone().into(),
             format,
-        )
-        .unwrap(),
-    )
-    .unwrap()
+        )?,
+    )?)
 }
 
 pub fn get_framebuffers(
-    images: &[Arc<SwapchainImage<Window>>],
+    images: Vec<Arc<dyn ImageViewAbstract>>,
     render_pass: Arc<RenderPass>,
     colour_buffer: Arc<ImageView<AttachmentImage>>,
     normal_buffer: Arc<ImageView<AttachmentImage>>,
     depth_buffer: Arc<ImageView<AttachmentImage>>,
-) -> Vec<Arc<Framebuffer>> {
+) -> Result<Vec<Arc<Framebuffer>>, RenderError> {
     images
-        .iter()
-        .map(|image| {
-            let view = ImageView::new_default(image.clone()).unwrap();
-            Framebuffer::new(
+        .into_iter()
+        .map(|view| {
+            Ok(Framebuffer::new(
                 render_pass.clone(),
                 FramebufferCreateInfo {
                     attachments: vec![
@@ -224,58 +219,16 @@ pub fn get_framebuffers(
                     ],
                     ..Default::default()
                 },
-            )
-            .unwrap()
+            )?)
         })
-        .collect::<Vec<_>>()
+        .collect()
 }
 
-pub fn recreate_swapchain(
-    dimensions: winit::dpi::PhysicalSize<u32>,
-    device: Arc<Device>,
-    swapchain: Arc<Swapchain<Window>>,
-) -> Option<(
-    Arc<Swapchain<Window>>,
-    winit::dpi::PhysicalSize<u32>,
-    Vec<Arc<Framebuffer>>,
-    Arc<RenderPass>,
-    Arc<ImageView<AttachmentImage>>,
-    Arc<ImageView<AttachmentImage>>,
-)> {
-    // Recreate attachment image buffers
-    let depth_buffer = new_attachment_image(device.clone(), dimensions, Format::D16_UNORM);
-    let normal_buffer =
-        new_attachment_image(device.clone(), dimensions, Format::R16G16B16A16_SFLOAT);
-    let colour_buffer =
-        new_attachment_image(device.clone(), dimensions, Format::A2B10G10R10_UNORM_PACK32);
-
-    let (new_swapchain, images) = match swapchain.recreate(SwapchainCreateInfo {
-        image_extent: dimensions.into(),
-        ..swapchain.create_info()
-    }) {
-        Ok(r) => r,
-        Err(SwapchainCreationError::ImageExtentNotSupported { .. }) => return Option::None,
-        Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
-    };
-
-    let render_pass = get_render_pass(device, swapchain);
-
-    let framebuffers = get_framebuffers(
-        &images,
-        render_pass.clone(),
-        colour_buffer.clone(),
-        normal_buffer.clone(),
-        depth_buffer.clone(),
-    );
-
-    Option::from((
-        new_swapchain,
-        dimensions,
-        framebuffers,
-        render_pass,
-        normal_buffer,
-        colour_buffer,
-    ))
+/// The `main` function every shader is compiled with
+pub fn main_entry_point(module: &ShaderModule) -> Result<EntryPoint<'_>, RenderError> {
+    module
+        .entry_point("main")
+        .ok_or(RenderError::MissingEntryPoint)
 }
 
 pub fn get_pipeline(
@@ -283,18 +236,23 @@ pub fn get_pipeline(
     vs: Arc<ShaderModule>,
     fs: Arc<ShaderModule>,
     subpass: Subpass,
-    viewport: Viewport,
-) -> Arc<GraphicsPipeline> {
-    GraphicsPipeline::start()
-        .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
-        .vertex_shader(vs.entry_point("main").unwrap(), ())
+    pipeline_cache: Arc<PipelineCache>,
+) -> Result<Arc<GraphicsPipeline>, RenderError> {
+    Ok(GraphicsPipeline::start()
+        .vertex_input_state(
+            BuffersDefinition::new()
+                .vertex::<Vertex>()
+                .vertex::<SkinVertex>()
+                .vertex::<MorphVertex>(),
+        )
+        .vertex_shader(main_entry_point(&vs)?, ())
         .input_assembly_state(InputAssemblyState::new())
-        .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
-        .fragment_shader(fs.entry_point("main").unwrap(), ())
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(main_entry_point(&fs)?, ())
         .rasterization_state(RasterizationState::new().cull_mode(CullMode::Back))
         .render_pass(subpass)
-        .build(device.clone())
-        .unwrap()
+        .build_with_cache(pipeline_cache)
+        .build(device.clone())?)
 }
 
 pub fn get_pipeline_with_depth(
@@ -302,77 +260,252 @@ pub fn get_pipeline_with_depth(
     vs: Arc<ShaderModule>,
     fs: Arc<ShaderModule>,
     subpass: Subpass,
-    viewport: Viewport,
-) -> Arc<GraphicsPipeline> {
-    GraphicsPipeline::start()
-        .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
-        .vertex_shader(vs.entry_point("main").unwrap(), ())
+    pipeline_cache: Arc<PipelineCache>,
+) -> Result<Arc<GraphicsPipeline>, RenderError> {
+    Ok(GraphicsPipeline::start()
+        .vertex_input_state(
+            BuffersDefinition::new()
+                .vertex::<Vertex>()
+                .vertex::<SkinVertex>()
+                .vertex::<MorphVertex>(),
+        )
+        .vertex_shader(main_entry_point(&vs)?, ())
         .input_assembly_state(InputAssemblyState::new())
-        .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
-        .fragment_shader(fs.entry_point("main").unwrap(), ())
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(main_entry_point(&fs)?, ())
         .depth_stencil_state(DepthStencilState::simple_depth_test())
         .rasterization_state(RasterizationState::new().cull_mode(CullMode::Back))
         .render_pass(subpass)
-        .build(device.clone())
-        .unwrap()
+        .build_with_cache(pipeline_cache)
+        .build(device.clone())?)
+}
+
+/// Tests against the G-buffer depth without writing to it
+fn overlay_depth_state(depth_test: bool) -> DepthStencilState {
+    if !depth_test {
+        return DepthStencilState::disabled();
+    }
+
+    DepthStencilState {
+        depth: Some(DepthState {
+            enable_dynamic: false,
+            write_enable: StateMode::Fixed(false),
+            compare_op: StateMode::Fixed(CompareOp::LessOrEqual),
+        }),
+        ..DepthStencilState::disabled()
+    }
+}
+
+pub fn get_wireframe_pipeline(
+    device: Arc<Device>,
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    subpass: Subpass,
+    pipeline_cache: Arc<PipelineCache>,
+    polygon_mode: PolygonMode,
+    depth_test: bool,
+) -> Result<Arc<GraphicsPipeline>, RenderError> {
+    let mut rasterization_state = RasterizationState::new().polygon_mode(polygon_mode);
+
+    // Pull the edges towards the camera so they aren't hidden by the faces they belong to
+    rasterization_state.depth_bias = Some(DepthBiasState {
+        enable_dynamic: false,
+        bias: StateMode::Fixed(DepthBias {
+            constant_factor: -1.0,
+            clamp: 0.0,
+            slope_factor: -1.0,
+        }),
+    });
+
+    Ok(GraphicsPipeline::start()
+        .vertex_input_state(BuffersDefinition::new().vertex::<WireVertex>())
+        .vertex_shader(main_entry_point(&vs)?, ())
+        .input_assembly_state(InputAssemblyState::new())
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(main_entry_point(&fs)?, ())
+        .depth_stencil_state(overlay_depth_state(depth_test))
+        .rasterization_state(rasterization_state)
+        .render_pass(subpass)
+        .build_with_cache(pipeline_cache)
+        .build(device.clone())?)
+}
+
+pub fn get_line_pipeline(
+    device: Arc<Device>,
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    subpass: Subpass,
+    pipeline_cache: Arc<PipelineCache>,
+    depth_test: bool,
+) -> Result<Arc<GraphicsPipeline>, RenderError> {
+    Ok(GraphicsPipeline::start()
+        .vertex_input_state(BuffersDefinition::new().vertex::<LineVertex>())
+        .vertex_shader(main_entry_point(&vs)?, ())
+        .input_assembly_state(InputAssemblyState::new().topology(PrimitiveTopology::LineList))
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(main_entry_point(&fs)?, ())
+        .depth_stencil_state(overlay_depth_state(depth_test))
+        .rasterization_state(RasterizationState::new())
+        .render_pass(subpass)
+        .build_with_cache(pipeline_cache)
+        .build(device.clone())?)
+}
+
+/// Screen space quads blended over everything else, without depth testing
+pub fn get_text_pipeline(
+    device: Arc<Device>,
+    vs: Arc<ShaderModule>,
+    fs: Arc<ShaderModule>,
+    subpass: Subpass,
+    pipeline_cache: Arc<PipelineCache>,
+) -> Result<Arc<GraphicsPipeline>, RenderError> {
+    Ok(GraphicsPipeline::start()
+        .vertex_input_state(BuffersDefinition::new().vertex::<TextVertex>())
+        .vertex_shader(main_entry_point(&vs)?, ())
+        .input_assembly_state(InputAssemblyState::new())
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(main_entry_point(&fs)?, ())
+        .depth_stencil_state(DepthStencilState::disabled())
+        .color_blend_state(ColorBlendState::new(1).blend_alpha())
+        .rasterization_state(RasterizationState::new())
+        .render_pass(subpass)
+        .build_with_cache(pipeline_cache)
+        .build(device.clone())?)
+}
+
+/// A non-indexed draw in the forward subpass after lighting
+#[derive(Clone)]
+pub struct ForwardDraw {
+    pub pipeline: Arc<GraphicsPipeline>,
+    pub descriptor_set: Arc<PersistentDescriptorSet>,
+    pub vertex_buffer: Arc<dyn BufferAccess>,
+    pub vertex_count: u32,
 }
 
 const BG_COL: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
 
-pub fn get_command_buffers(
+pub fn get_command_buffer(
     device: Arc<Device>,
     queue: Arc<Queue>,
     deferred_pipeline: Arc<GraphicsPipeline>,
     deferred_set: Arc<PersistentDescriptorSet>,
     lighting_pipeline: Arc<GraphicsPipeline>,
     lighting_set: Arc<PersistentDescriptorSet>,
-    framebuffers: &Vec<Arc<Framebuffer>>,
-    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
-    index_buffer: Arc<CpuAccessibleBuffer<[Index]>>,
-) -> Vec<Arc<PrimaryAutoCommandBuffer>> {
-    framebuffers
-        .iter()
-        .map(|framebuffer| {
-            let mut builder = AutoCommandBufferBuilder::primary(
-                device.clone(),
-                queue.family(),
-                CommandBufferUsage::MultipleSubmit, // don't forget to write the correct buffer usage
+    debug_data: lighting_frag::ty::DebugData,
+    framebuffer: Arc<Framebuffer>,
+    viewport: Viewport,
+    mesh: Option<&Mesh>,
+    draw_lighting: bool,
+    forward_draws: &[ForwardDraw],
+    queries: Option<&FrameQueries>,
+    capture_buffer: Option<Arc<dyn BufferAccess>>,
+) -> Result<Arc<PrimaryAutoCommandBuffer>, RenderError> {
+    let mut builder = AutoCommandBufferBuilder::primary(
+        device.clone(),
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit, // Recorded fresh every frame since the uniforms change
+    )?;
+
+    // Each timestamp is written once all previous commands finish, so the differences between
+    // them are the time taken by each subpass
+    let write_timestamp = |builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+                           i: u32| {
+        if let Some(queries) = queries {
+            unsafe {
+                builder.write_timestamp(
+                    queries.pool.clone(),
+                    queries.first + i,
+                    PipelineStage::BottomOfPipe,
+                )?;
+            }
+        }
+
+        Ok::<_, RenderError>(())
+    };
+
+    if let Some(queries) = queries {
+        unsafe {
+            builder.reset_query_pool(queries.pool.clone(), queries.range())?;
+        }
+    }
+
+    write_timestamp(&mut builder, 0)?;
+
+    builder
+        .begin_render_pass(
+            framebuffer.clone(),
+            SubpassContents::Inline,
+            vec![BG_COL.into(), BG_COL.into(), BG_COL.into(), 1f32.into()], // Use 1f32 for depth clear to give unique colour
+        )?
+        .bind_pipeline_graphics(deferred_pipeline.clone())
+        .set_viewport(0, [viewport.clone()])
+        .bind_descriptor_sets(
+            PipelineBindPoint::Graphics,
+            deferred_pipeline.layout().clone(),
+            0,
+            deferred_set.clone(),
+        );
+
+    if let Some(mesh) = mesh {
+        builder
+            .bind_vertex_buffers(
+                0,
+                (
+                    mesh.vertex_buffer(),
+                    mesh.skin_buffer(),
+                    mesh.morph_buffer(),
+                ),
             )
-            .unwrap();
-
-            builder
-                .begin_render_pass(
-                    framebuffer.clone(),
-                    SubpassContents::Inline,
-                    vec![BG_COL.into(), BG_COL.into(), BG_COL.into(), 1f32.into()], // Use 1f32 for depth clear to give unique colour
-                )
-                .unwrap()
-                .bind_pipeline_graphics(deferred_pipeline.clone())
-                .bind_descriptor_sets(
-                    PipelineBindPoint::Graphics,
-                    deferred_pipeline.layout().clone(),
-                    0,
-                    deferred_set.clone(),
-                )
-                .bind_vertex_buffers(0, vertex_buffer.clone())
-                .bind_index_buffer(index_buffer.clone())
-                .draw_indexed(index_buffer.len() as u32, 1, 0, 0, 0)
-                .unwrap()
-                .next_subpass(SubpassContents::Inline)
-                .unwrap()
-                .bind_pipeline_graphics(lighting_pipeline.clone())
-                .bind_descriptor_sets(
-                    PipelineBindPoint::Graphics,
-                    lighting_pipeline.layout().clone(),
-                    0,
-                    lighting_set.clone(),
-                )
-                .draw_indexed(index_buffer.len() as u32, 1, 0, 0, 0)
-                .unwrap()
-                .end_render_pass()
-                .unwrap();
-
-            Arc::new(builder.build().unwrap())
-        })
-        .collect()
+            .bind_index_buffer(mesh.index_buffer())
+            .draw_indexed(mesh.index_count(), 1, 0, 0, 0)?;
+    }
+
+    write_timestamp(&mut builder, 1)?;
+
+    builder
+        .next_subpass(SubpassContents::Inline)?
+        .bind_pipeline_graphics(lighting_pipeline.clone())
+        .set_viewport(0, [viewport.clone()])
+        .bind_descriptor_sets(
+            PipelineBindPoint::Graphics,
+            lighting_pipeline.layout().clone(),
+            0,
+            lighting_set.clone(),
+        )
+        .push_constants(lighting_pipeline.layout().clone(), 0, debug_data);
+
+    if let (Some(mesh), true) = (mesh, draw_lighting) {
+        builder.draw_indexed(mesh.index_count(), 1, 0, 0, 0)?;
+    }
+
+    write_timestamp(&mut builder, 2)?;
+
+    builder.next_subpass(SubpassContents::Inline)?;
+
+    for draw in forward_draws {
+        builder
+            .bind_pipeline_graphics(draw.pipeline.clone())
+            .set_viewport(0, [viewport.clone()])
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                draw.pipeline.layout().clone(),
+                0,
+                draw.descriptor_set.clone(),
+            )
+            .bind_vertex_buffers(0, draw.vertex_buffer.clone())
+            .draw(draw.vertex_count, 1, 0, 0)?;
+    }
+
+    write_timestamp(&mut builder, 3)?;
+
+    builder.end_render_pass()?;
+
+    if let Some(capture_buffer) = capture_buffer {
+        // The first attachment is the output image
+        let output = framebuffer.attachments()[0].image();
+
+        builder.copy_image_to_buffer(output, capture_buffer)?;
+    }
+
+    Ok(Arc::new(builder.build()?))
 }
@@ -0,0 +1,524 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::error::SceneError;
+use crate::geometry::{reverse_triangle, vertex_normals, NormalWeighting, Winding};
+use crate::model::Model;
+use crate::vertex::{CompactVec3, Index, Vertex};
+
+/// How the elements after a PLY file's header are stored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlyFormat {
+    /// Numbers as text, one element per line
+    Ascii,
+    #[default]
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// Loads the vertices and faces of a PLY file in any of its three formats
+pub fn load_ply(path: impl AsRef<Path>) -> Result<Model, SceneError> {
+    parse_ply(&fs::read(path)?)
+}
+
+/// Parses PLY data. Vertices need `x`, `y` and `z`, and can have `nx`, `ny` and `nz` normals,
+/// `red`, `green` and `blue` colours and `s` and `t` or `u` and `v` texture coordinates, with v
+/// going up as in OBJ files. Faces are split into triangle fans, and smooth normals are made if the
+/// vertices have none. Other elements and properties are skipped. Fails if there are no faces
+pub fn parse_ply(data: &[u8]) -> Result<Model, SceneError> {
+    let (header, body) = split_header(data)?;
+    let mut body = match header.format {
+        PlyFormat::Ascii => {
+            let text = std::str::from_utf8(body)
+                .map_err(|_| SceneError::InvalidMesh("PLY body isn't text".to_string()))?;
+
+            Body::Ascii {
+                tokens: text
+                    .lines()
+                    .enumerate()
+                    .flat_map(|(i, line)| {
+                        line.split_whitespace()
+                            .map(move |token| (header.lines + i + 1, token))
+                    })
+                    .collect(),
+                next: 0,
+            }
+        }
+        format => Body::Binary {
+            data: body,
+            offset: 0,
+            big_endian: format == PlyFormat::BinaryBigEndian,
+        },
+    };
+
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut indices: Vec<Index> = Vec::new();
+    let mut has_normals = false;
+
+    for element in &header.elements {
+        let find = |names: &[&str]| {
+            element.properties.iter().position(|property| {
+                matches!(property, Property::Scalar { name, .. } if names.contains(&name.as_str()))
+            })
+        };
+
+        match element.name.as_str() {
+            "vertex" => {
+                let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+                let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                let colour = [find(&["red"]), find(&["green"]), find(&["blue"])];
+                let uv = [
+                    find(&["s", "u", "texture_u"]),
+                    find(&["t", "v", "texture_v"]),
+                ];
+
+                if position.contains(&None) {
+                    return Err(body.error("vertices need x, y and z"));
+                }
+                has_normals = !normal.contains(&None);
+
+                for _ in 0..element.count {
+                    let values = body.read_element(element)?;
+                    let get = |property: Option<usize>| property.map(|i| values[i][0] as f32);
+
+                    let mut vertex = Vertex::new(
+                        position.map(|i| get(i).unwrap()),
+                        normal.map(|i| get(i).unwrap_or(0.0)),
+                    );
+
+                    if !colour.contains(&None) {
+                        vertex.colour = colour.map(|i| {
+                            let i = i.unwrap();
+
+                            values[i][0] as f32 / element.properties[i].scalar_type().scale()
+                        });
+                    }
+
+                    if !uv.contains(&None) {
+                        vertex.uv = [get(uv[0]).unwrap(), 1.0 - get(uv[1]).unwrap()];
+                    }
+
+                    vertices.push(vertex);
+                }
+            }
+            "face" => {
+                let list = element.properties.iter().position(|property| {
+                    matches!(property, Property::List { name, .. }
+                        if name == "vertex_indices" || name == "vertex_index")
+                });
+                let list = list.ok_or_else(|| body.error("faces need vertex_indices"))?;
+
+                for _ in 0..element.count {
+                    let values = body.read_element(element)?;
+                    let corners = &values[list];
+
+                    if corners.len() < 3 {
+                        return Err(body.error("face has fewer than 3 corners"));
+                    }
+
+                    for j in 1..corners.len() - 1 {
+                        for corner in reverse_triangle([corners[0], corners[j], corners[j + 1]]) {
+                            if corner < 0.0 || corner >= vertices.len() as f64 {
+                                return Err(body.error(&format!("index {} out of range", corner)));
+                            }
+
+                            indices.push(corner as Index);
+                        }
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    body.read_element(element)?;
+                }
+            }
+        }
+    }
+
+    if indices.is_empty() {
+        return Err(SceneError::InvalidMesh("file has no faces".to_string()));
+    }
+
+    if !has_normals {
+        let positions: Vec<CompactVec3> = vertices.iter().map(|v| v.position).collect();
+        let normals = vertex_normals(
+            &positions,
+            &indices,
+            NormalWeighting::Area,
+            Winding::Clockwise,
+        );
+
+        for (vertex, normal) in vertices.iter_mut().zip(normals) {
+            vertex.normal = normal;
+        }
+    }
+
+    Ok(Model::new(vertices, indices))
+}
+
+/// Writes the model's vertices in its own space with their normals, colours and texture
+/// coordinates
+pub fn save_ply(
+    model: &Model,
+    path: impl AsRef<Path>,
+    format: PlyFormat,
+) -> Result<(), SceneError> {
+    Ok(fs::write(path, write_ply(model, format))?)
+}
+
+/// The PLY data for a model, which `parse_ply` reads back as the same vertices and triangles.
+/// Colours are stored as bytes
+pub fn write_ply(model: &Model, format: PlyFormat) -> Vec<u8> {
+    let vertices = model.vertices();
+    let indices = model.indices();
+
+    let format_name = match format {
+        PlyFormat::Ascii => "ascii",
+        PlyFormat::BinaryLittleEndian => "binary_little_endian",
+        PlyFormat::BinaryBigEndian => "binary_big_endian",
+    };
+
+    let mut data: Vec<u8> = Vec::new();
+    write!(
+        data,
+        "ply\n\
+         format {} 1.0\n\
+         element vertex {}\n\
+         property float x\n\
+         property float y\n\
+         property float z\n\
+         property float nx\n\
+         property float ny\n\
+         property float nz\n\
+         property float s\n\
+         property float t\n\
+         property uchar red\n\
+         property uchar green\n\
+         property uchar blue\n\
+         element face {}\n\
+         property list uchar uint vertex_indices\n\
+         end_header\n",
+        format_name,
+        vertices.len(),
+        indices.len() / 3
+    )
+    .unwrap();
+
+    let put_f32 = |data: &mut Vec<u8>, value: f32| match format {
+        PlyFormat::Ascii => write!(data, "{} ", value).unwrap(),
+        PlyFormat::BinaryLittleEndian => data.extend(value.to_le_bytes()),
+        PlyFormat::BinaryBigEndian => data.extend(value.to_be_bytes()),
+    };
+    let put_u32 = |data: &mut Vec<u8>, value: u32| match format {
+        PlyFormat::Ascii => write!(data, " {}", value).unwrap(),
+        PlyFormat::BinaryLittleEndian => data.extend(value.to_le_bytes()),
+        PlyFormat::BinaryBigEndian => data.extend(value.to_be_bytes()),
+    };
+    let put_u8 = |data: &mut Vec<u8>, value: u8| match format {
+        PlyFormat::Ascii => write!(data, "{}", value).unwrap(),
+        _ => data.push(value),
+    };
+    let end_line = |data: &mut Vec<u8>| {
+        if format == PlyFormat::Ascii {
+            data.push(b'\n');
+        }
+    };
+
+    for v in &vertices {
+        for value in v.position.iter().chain(&v.normal) {
+            put_f32(&mut data, *value);
+        }
+        put_f32(&mut data, v.uv[0]);
+        put_f32(&mut data, 1.0 - v.uv[1]);
+
+        for (i, channel) in v.colour.iter().enumerate() {
+            if i > 0 && format == PlyFormat::Ascii {
+                data.push(b' ');
+            }
+            put_u8(&mut data, (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+        end_line(&mut data);
+    }
+
+    for triangle in indices.chunks_exact(3) {
+        put_u8(&mut data, 3);
+        for index in reverse_triangle([triangle[0], triangle[1], triangle[2]]) {
+            put_u32(&mut data, index);
+        }
+        end_line(&mut data);
+    }
+
+    data
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self: Self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    /// What a colour channel of this type is divided by to get from 0 to 1
+    fn scale(self: Self) -> f32 {
+        match self {
+            ScalarType::U8 => u8::MAX as f32,
+            ScalarType::U16 => u16::MAX as f32,
+            _ => 1.0,
+        }
+    }
+}
+
+enum Property {
+    Scalar {
+        name: String,
+        scalar_type: ScalarType,
+    },
+    /// A count followed by that many items
+    List {
+        name: String,
+        count_type: ScalarType,
+        item_type: ScalarType,
+    },
+}
+
+impl Property {
+    fn scalar_type(self: &Self) -> ScalarType {
+        match self {
+            Property::Scalar { scalar_type, .. } => *scalar_type,
+            Property::List { item_type, .. } => *item_type,
+        }
+    }
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: PlyFormat,
+    elements: Vec<Element>,
+    /// How many lines the header takes up, so errors in an ASCII body give the right line
+    lines: usize,
+}
+
+/// Reads the header up to `end_header`, returning it and the data after it
+fn split_header(data: &[u8]) -> Result<(Header, &[u8]), SceneError> {
+    let mut header = Header {
+        format: PlyFormat::Ascii,
+        elements: Vec::new(),
+        lines: 0,
+    };
+    let mut format = None;
+    let mut offset = 0;
+
+    loop {
+        let line_number = header.lines + 1;
+        let error = |message: &str| SceneError::Parse {
+            line: line_number,
+            message: message.to_string(),
+        };
+
+        let end = data[offset..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| error("header has no end_header"))?;
+        let line = std::str::from_utf8(&data[offset..offset + end])
+            .map_err(|_| error("header isn't text"))?
+            .trim();
+
+        offset += end + 1;
+        header.lines += 1;
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        match parts.as_slice() {
+            ["ply"] if line_number == 1 => (),
+            _ if line_number == 1 => return Err(error("not a PLY file")),
+            ["format", name, _] => {
+                format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(error("unknown format")),
+                });
+            }
+            ["element", name, count] => header.elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| error("invalid element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_type, item_type, name] => {
+                let count_type = ScalarType::parse(count_type);
+                let item_type = ScalarType::parse(item_type);
+
+                header
+                    .elements
+                    .last_mut()
+                    .ok_or_else(|| error("property before any element"))?
+                    .properties
+                    .push(Property::List {
+                        name: name.to_string(),
+                        count_type: count_type.ok_or_else(|| error("unknown type"))?,
+                        item_type: item_type.ok_or_else(|| error("unknown type"))?,
+                    });
+            }
+            ["property", scalar_type, name] => {
+                let scalar_type = ScalarType::parse(scalar_type);
+
+                header
+                    .elements
+                    .last_mut()
+                    .ok_or_else(|| error("property before any element"))?
+                    .properties
+                    .push(Property::Scalar {
+                        name: name.to_string(),
+                        scalar_type: scalar_type.ok_or_else(|| error("unknown type"))?,
+                    });
+            }
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => (),
+            _ => return Err(error("invalid header line")),
+        }
+    }
+
+    header.format = format.ok_or_else(|| SceneError::Parse {
+        line: header.lines,
+        message: "header has no format".to_string(),
+    })?;
+
+    Ok((header, &data[offset..]))
+}
+
+/// The data after the header, read one value at a time
+enum Body<'a> {
+    /// Every number with the line it's on
+    Ascii {
+        tokens: Vec<(usize, &'a str)>,
+        next: usize,
+    },
+    Binary {
+        data: &'a [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl<'a> Body<'a> {
+    /// An error at the line or byte last read
+    fn error(self: &Self, message: &str) -> SceneError {
+        match self {
+            Body::Ascii { tokens, next } => SceneError::Parse {
+                line: tokens
+                    .get(next.saturating_sub(1))
+                    .map(|&(line, _)| line)
+                    .unwrap_or(0),
+                message: message.to_string(),
+            },
+            Body::Binary { offset, .. } => {
+                SceneError::InvalidMesh(format!("PLY byte {}: {}", offset, message))
+            }
+        }
+    }
+
+    fn read(self: &mut Self, scalar_type: ScalarType) -> Result<f64, SceneError> {
+        match self {
+            Body::Ascii { tokens, next } => {
+                let (line, token) = *tokens.get(*next).ok_or_else(|| SceneError::Parse {
+                    line: tokens.last().map(|&(line, _)| line).unwrap_or(0),
+                    message: "file ends early".to_string(),
+                })?;
+                *next += 1;
+
+                token.parse().map_err(|_| SceneError::Parse {
+                    line,
+                    message: format!("invalid number {}", token),
+                })
+            }
+            Body::Binary {
+                data,
+                offset,
+                big_endian,
+            } => {
+                let size = scalar_type.size();
+                let bytes = data
+                    .get(*offset..*offset + size)
+                    .ok_or_else(|| SceneError::InvalidMesh("PLY file ends early".to_string()))?;
+                *offset += size;
+
+                let mut buffer = [0u8; 8];
+                buffer[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+
+                let [b0, b1, b2, b3, ..] = buffer;
+
+                Ok(match scalar_type {
+                    ScalarType::I8 => b0 as i8 as f64,
+                    ScalarType::U8 => b0 as f64,
+                    ScalarType::I16 => i16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([b0, b1]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+
+    /// Each property's values, with one for a scalar
+    fn read_element(self: &mut Self, element: &Element) -> Result<Vec<Vec<f64>>, SceneError> {
+        element
+            .properties
+            .iter()
+            .map(|property| match property {
+                Property::Scalar { scalar_type, .. } => Ok(vec![self.read(*scalar_type)?]),
+                Property::List {
+                    count_type,
+                    item_type,
+                    ..
+                } => {
+                    let count = self.read(*count_type)?;
+                    if count < 0.0 {
+                        return Err(self.error("negative list length"));
+                    }
+
+                    (0..count as usize).map(|_| self.read(*item_type)).collect()
+                }
+            })
+            .collect()
+    }
+}
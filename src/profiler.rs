@@ -0,0 +1,238 @@
+use vulkano::device::Queue;
+use vulkano::query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::debug::set_name;
+use crate::error::RenderError;
+
+/// Written at the start of the frame and after the deferred, lighting and forward subpasses
+pub const TIMESTAMPS_PER_FRAME: u32 = 4;
+
+/// GPU time spent in each pass of a frame, in milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GpuTimings {
+    /// Counts the frames recorded by the renderer, these timings arrive a few frames later
+    pub frame: u64,
+    pub deferred: f64,
+    pub lighting: f64,
+    /// Wireframes, debug lines and text drawn after lighting
+    pub forward: f64,
+}
+
+impl GpuTimings {
+    pub const CSV_HEADER: &'static str = "frame,deferred_ms,lighting_ms,forward_ms,total_ms";
+
+    pub fn total(self: &Self) -> f64 {
+        self.deferred + self.lighting + self.forward
+    }
+
+    pub fn csv_row(self: &Self) -> String {
+        format!(
+            "{},{:.4},{:.4},{:.4},{:.4}",
+            self.frame,
+            self.deferred,
+            self.lighting,
+            self.forward,
+            self.total()
+        )
+    }
+}
+
+/// The queries a frame writes its timestamps to
+#[derive(Clone)]
+pub struct FrameQueries {
+    pub pool: Arc<QueryPool>,
+    pub first: u32,
+}
+
+impl FrameQueries {
+    pub fn range(self: &Self) -> Range<u32> {
+        self.first..self.first + TIMESTAMPS_PER_FRAME
+    }
+}
+
+/// Times each pass with timestamp queries. Every frame in flight writes to its own slot, and a
+/// slot is read back just before it's reused, by which point its frame has finished, so the CPU
+/// never waits on the GPU for results
+pub struct GpuProfiler {
+    query_pool: Arc<QueryPool>,
+    /// The frame each slot holds timestamps for, if it was submitted and hasn't been read yet
+    slots: Vec<Option<u64>>,
+    frame: u64,
+    /// Nanoseconds per timestamp tick
+    timestamp_period: f64,
+    timestamp_mask: u64,
+    latest: Option<GpuTimings>,
+}
+
+impl GpuProfiler {
+    /// Keeps `latency` frames of timestamps, which must be more than the frames in flight for
+    /// results to be ready when read. Returns `None` if the queue can't write timestamps
+    pub fn new(queue: &Arc<Queue>, latency: usize) -> Result<Option<Self>, RenderError> {
+        let valid_bits = match queue.family().timestamp_valid_bits() {
+            Some(valid_bits) => valid_bits,
+            None => return Ok(None),
+        };
+
+        let device = queue.device();
+        let latency = latency.max(1);
+
+        let query_pool = QueryPool::new(
+            device.clone(),
+            QueryPoolCreateInfo {
+                query_count: TIMESTAMPS_PER_FRAME * latency as u32,
+                ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+            },
+        )?;
+
+        set_name(&*query_pool, "Timestamp queries");
+
+        Ok(Some(Self {
+            query_pool,
+            slots: vec![None; latency],
+            frame: 0,
+            timestamp_period: device.physical_device().properties().timestamp_period as f64,
+            timestamp_mask: if valid_bits >= 64 {
+                u64::MAX
+            } else {
+                (1 << valid_bits) - 1
+            },
+            latest: None,
+        }))
+    }
+
+    /// Reads back the slot the next frame will use and returns its queries
+    pub fn begin_frame(self: &mut Self) -> Result<FrameQueries, RenderError> {
+        let slot = (self.frame % self.slots.len() as u64) as usize;
+        let queries = FrameQueries {
+            pool: self.query_pool.clone(),
+            first: slot as u32 * TIMESTAMPS_PER_FRAME,
+        };
+
+        if let Some(frame) = self.slots[slot].take() {
+            let mut timestamps = [0u64; TIMESTAMPS_PER_FRAME as usize];
+
+            // Results that still aren't available are dropped rather than waited for
+            let available = self
+                .query_pool
+                .queries_range(queries.range())
+                .unwrap()
+                .get_results(&mut timestamps, QueryResultFlags::default())?;
+
+            if available {
+                let ms = |i: usize| {
+                    let ticks = timestamps[i + 1].wrapping_sub(timestamps[i]) & self.timestamp_mask;
+
+                    ticks as f64 * self.timestamp_period / 1_000_000.0
+                };
+
+                self.latest = Some(GpuTimings {
+                    frame,
+                    deferred: ms(0),
+                    lighting: ms(1),
+                    forward: ms(2),
+                });
+            }
+        }
+
+        Ok(queries)
+    }
+
+    /// Marks the slot from `begin_frame` as submitted, so it's read back next time round
+    pub fn end_frame(self: &mut Self) {
+        let slot = (self.frame % self.slots.len() as u64) as usize;
+
+        self.slots[slot] = Some(self.frame);
+        self.frame += 1;
+    }
+
+    /// The most recent timings that have been read back
+    pub fn latest(self: &Self) -> Option<GpuTimings> {
+        self.latest
+    }
+}
+
+/// Averages timings over a period, counting each frame once
+#[derive(Clone, Default)]
+pub struct TimingsAverage {
+    sum: GpuTimings,
+    count: u32,
+    last_frame: Option<u64>,
+}
+
+impl TimingsAverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(self: &mut Self, timings: &GpuTimings) {
+        if self.last_frame == Some(timings.frame) {
+            return;
+        }
+
+        self.last_frame = Some(timings.frame);
+        self.sum.frame = timings.frame;
+        self.sum.deferred += timings.deferred;
+        self.sum.lighting += timings.lighting;
+        self.sum.forward += timings.forward;
+        self.count += 1;
+    }
+
+    /// The mean of the timings added since the last call, with the latest frame number
+    pub fn take(self: &mut Self) -> Option<GpuTimings> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let count = self.count as f64;
+        let mean = GpuTimings {
+            frame: self.sum.frame,
+            deferred: self.sum.deferred / count,
+            lighting: self.sum.lighting / count,
+            forward: self.sum.forward / count,
+        };
+
+        self.sum = GpuTimings::default();
+        self.count = 0;
+
+        Some(mean)
+    }
+}
+
+/// Writes a row of timings per frame, for comparing runs
+pub struct TimingsCsv {
+    writer: BufWriter<File>,
+    last_frame: Option<u64>,
+}
+
+impl TimingsCsv {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{}", GpuTimings::CSV_HEADER)?;
+
+        Ok(Self {
+            writer,
+            last_frame: None,
+        })
+    }
+
+    /// Writes the timings unless that frame has already been written
+    pub fn write(self: &mut Self, timings: &GpuTimings) -> io::Result<()> {
+        if self.last_frame == Some(timings.frame) {
+            return Ok(());
+        }
+
+        self.last_frame = Some(timings.frame);
+
+        writeln!(self.writer, "{}", timings.csv_row())
+    }
+
+    pub fn flush(self: &mut Self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,1422 @@
+use vulkano::buffer::{
+    BufferAccess, BufferContents, BufferUsage, CpuAccessibleBuffer, CpuBufferPool,
+    TypedBufferAccess,
+};
+use vulkano::command_buffer::{CommandBufferExecFuture, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{
+    view::ImageView, AttachmentImage, ImageAccess, ImageDimensions, ImageUsage, ImmutableImage,
+    MipmapsCount,
+};
+use vulkano::instance::Instance;
+use vulkano::pipeline::cache::PipelineCache;
+use vulkano::pipeline::graphics::rasterization::PolygonMode;
+use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::pipeline::{GraphicsPipeline, Pipeline};
+use vulkano::render_pass::{Framebuffer, RenderPass, Subpass};
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo};
+use vulkano::shader::{EntryPoint, ShaderModule};
+use vulkano::swapchain::{PresentFuture, PresentMode, Surface, SwapchainAcquireFuture};
+use vulkano::sync::{self, FenceSignalFuture, GpuFuture, JoinFuture};
+
+use winit::window::Window;
+
+use nalgebra_glm::{identity, TMat4};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::assets::TextureHandle;
+use crate::camera::Camera;
+use crate::capture::CapturedFrame;
+use crate::debug::set_name;
+use crate::debug_draw::DebugDraw;
+use crate::debug_view::{DebugView, Wireframe};
+use crate::device::{gpu_memory_usage, DeviceSelector, GpuMemory};
+use crate::error::RenderError;
+use crate::font;
+use crate::frame_limiter::FrameLimiter;
+use crate::model::ModelCollection;
+use crate::pipeline_commands::{
+    get_command_buffer, get_device_queue, get_line_pipeline, get_pipeline, get_pipeline_with_depth,
+    get_render_pass, get_text_pipeline, get_wireframe_pipeline, main_entry_point, ForwardDraw,
+};
+use crate::profiler::{GpuProfiler, GpuTimings};
+use crate::scene::Scene;
+use crate::shader::{
+    deferred_frag, deferred_vert, lighting_frag, lighting_vert, line_frag, line_vert, text_frag,
+    text_vert, wireframe_barycentric_frag, wireframe_frag, wireframe_vert,
+};
+use crate::swapchain::{Attachments, SwapchainResources};
+use crate::text::TextBatch;
+use crate::texture::Texture;
+use crate::vertex::{Index, MorphDelta, MorphVertex, SkinVertex, Vertex};
+use crate::vp;
+
+const PIPELINE_CACHE_PATH: &str = "pipeline_cache.bin";
+const HEADLESS_FORMAT: Format = Format::R8G8B8A8_UNORM;
+const NORMAL_LINE_LENGTH: f32 = 0.25;
+const NORMAL_LINE_COLOUR: [f32; 3] = [1.0, 1.0, 0.0];
+
+type FrameFence = FenceSignalFuture<
+    PresentFuture<
+        CommandBufferExecFuture<
+            JoinFuture<Box<dyn GpuFuture>, SwapchainAcquireFuture<Window>>,
+            Arc<PrimaryAutoCommandBuffer>,
+        >,
+        Window,
+    >,
+>;
+
+/// Joint matrices for skinning, as columns
+type JointBuffer = Arc<CpuAccessibleBuffer<[[[f32; 4]; 4]]>>;
+
+/// The buffers the deferred and lighting vertex shaders deform meshes with
+#[derive(Clone)]
+struct DeformBuffers {
+    joints: JointBuffer,
+    morph_deltas: Arc<CpuAccessibleBuffer<[MorphDelta]>>,
+    morph_weights: Arc<CpuAccessibleBuffer<[f32]>>,
+}
+
+/// How frames are paced and presented
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    /// Falls back to the closest supported mode, FIFO is always available
+    pub present_mode: PresentMode,
+    /// How many frames the CPU can record ahead of the GPU, independent of the swapchain size
+    pub frames_in_flight: usize,
+    pub target_fps: Option<f64>,
+    /// Chosen automatically, or from `GRAPHICS_DEVICE`, if `None`
+    pub device: Option<DeviceSelector>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            present_mode: PresentMode::Fifo,
+            frames_in_flight: 2,
+            target_fps: None,
+            device: None,
+        }
+    }
+}
+
+/// Counts from the most recently drawn frame
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub triangles: u64,
+}
+
+/// What the renderer draws into
+#[derive(Clone)]
+pub enum RenderTarget {
+    Window(Arc<Surface<Window>>),
+    Headless(winit::dpi::PhysicalSize<u32>),
+}
+
+enum Output {
+    Swapchain {
+        surface: Arc<Surface<Window>>,
+        resources: SwapchainResources<Window>,
+        fences: Vec<Option<Arc<FrameFence>>>,
+        previous_fence_i: usize,
+    },
+    Image {
+        image: Arc<AttachmentImage>,
+        attachments: Attachments,
+    },
+}
+
+/// Geometry which has been uploaded to the GPU
+#[derive(Clone)]
+pub struct Mesh {
+    vertex_buffer: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    skin_buffer: Arc<CpuAccessibleBuffer<[SkinVertex]>>,
+    morph_buffer: Arc<CpuAccessibleBuffer<[MorphVertex]>>,
+    index_buffer: Arc<CpuAccessibleBuffer<[Index]>>,
+    deform_buffers: DeformBuffers,
+}
+
+impl Mesh {
+    pub fn vertex_buffer(self: &Self) -> Arc<CpuAccessibleBuffer<[Vertex]>> {
+        self.vertex_buffer.clone()
+    }
+
+    pub fn skin_buffer(self: &Self) -> Arc<CpuAccessibleBuffer<[SkinVertex]>> {
+        self.skin_buffer.clone()
+    }
+
+    pub fn morph_buffer(self: &Self) -> Arc<CpuAccessibleBuffer<[MorphVertex]>> {
+        self.morph_buffer.clone()
+    }
+
+    pub fn index_buffer(self: &Self) -> Arc<CpuAccessibleBuffer<[Index]>> {
+        self.index_buffer.clone()
+    }
+
+    pub fn index_count(self: &Self) -> u32 {
+        self.index_buffer.len() as u32
+    }
+}
+
+/// Kept so the pipelines can be rebuilt for a new render pass
+#[derive(Clone)]
+struct Shaders {
+    deferred_vert: Arc<ShaderModule>,
+    deferred_frag: Arc<ShaderModule>,
+    lighting_vert: Arc<ShaderModule>,
+    lighting_frag: Arc<ShaderModule>,
+    wireframe_vert: Arc<ShaderModule>,
+    wireframe_frag: Arc<ShaderModule>,
+    line_vert: Arc<ShaderModule>,
+    line_frag: Arc<ShaderModule>,
+    text_vert: Arc<ShaderModule>,
+    text_frag: Arc<ShaderModule>,
+}
+
+impl Shaders {
+    fn load(device: Arc<Device>) -> Result<Self, RenderError> {
+        // Without line rasterization the wireframe is drawn by discarding the inside of triangles
+        let wireframe_frag = if device.enabled_features().fill_mode_non_solid {
+            wireframe_frag::load(device.clone())?
+        } else {
+            wireframe_barycentric_frag::load(device.clone())?
+        };
+
+        Ok(Self {
+            deferred_vert: deferred_vert::load(device.clone())?,
+            deferred_frag: deferred_frag::load(device.clone())?,
+            lighting_vert: lighting_vert::load(device.clone())?,
+            lighting_frag: lighting_frag::load(device.clone())?,
+            wireframe_vert: wireframe_vert::load(device.clone())?,
+            wireframe_frag,
+            line_vert: line_vert::load(device.clone())?,
+            line_frag: line_frag::load(device.clone())?,
+            text_vert: text_vert::load(device.clone())?,
+            text_frag: text_frag::load(device)?,
+        })
+    }
+
+    /// The module compiled from a file in `src/shaders`, or `None` if the file isn't used on this
+    /// device
+    fn module_mut(
+        self: &mut Self,
+        file_name: &str,
+        device: &Device,
+    ) -> Option<&mut Arc<ShaderModule>> {
+        let line_rasterization = device.enabled_features().fill_mode_non_solid;
+
+        match file_name {
+            "deferred.vert.glsl" => Some(&mut self.deferred_vert),
+            "deferred.frag.glsl" => Some(&mut self.deferred_frag),
+            "lighting.vert.glsl" => Some(&mut self.lighting_vert),
+            "lighting.frag.glsl" => Some(&mut self.lighting_frag),
+            "wireframe.vert.glsl" => Some(&mut self.wireframe_vert),
+            "wireframe.frag.glsl" if line_rasterization => Some(&mut self.wireframe_frag),
+            "wireframe_barycentric.frag.glsl" if !line_rasterization => {
+                Some(&mut self.wireframe_frag)
+            }
+            "line.vert.glsl" => Some(&mut self.line_vert),
+            "line.frag.glsl" => Some(&mut self.line_frag),
+            "text.vert.glsl" => Some(&mut self.text_vert),
+            "text.frag.glsl" => Some(&mut self.text_frag),
+            _ => None,
+        }
+    }
+}
+
+/// Checks a reloaded module needs the same descriptors and push constants as the one it replaces,
+/// since the renderer writes descriptor sets for the original bindings
+fn check_shader_layout(old: &ShaderModule, new: &ShaderModule) -> Result<(), RenderError> {
+    let old = main_entry_point(old)?;
+    let new = main_entry_point(new)?;
+
+    let descriptors = |entry_point: &EntryPoint| {
+        let mut descriptors: Vec<_> = entry_point
+            .descriptor_requirements()
+            .map(|(binding, requirements)| (binding, requirements.descriptor_types.clone()))
+            .collect();
+        descriptors.sort_by_key(|&(binding, _)| binding);
+
+        descriptors
+    };
+
+    let (old_descriptors, new_descriptors) = (descriptors(&old), descriptors(&new));
+    if old_descriptors != new_descriptors {
+        let bindings = |descriptors: &[((u32, u32), _)]| {
+            descriptors
+                .iter()
+                .map(|((set, binding), _)| format!("{}.{}", set, binding))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        return Err(RenderError::ShaderLayout(format!(
+            "descriptors are [{}], expected [{}]",
+            bindings(&new_descriptors),
+            bindings(&old_descriptors)
+        )));
+    }
+
+    let push_constants = |entry_point: &EntryPoint| {
+        entry_point
+            .push_constant_requirements()
+            .map(|range| (range.offset, range.size))
+    };
+
+    if push_constants(&old) != push_constants(&new) {
+        return Err(RenderError::ShaderLayout(
+            "push constants don't match".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Everything built against the render pass
+#[derive(Clone)]
+struct Pipelines {
+    deferred: Arc<GraphicsPipeline>,
+    lighting: Arc<GraphicsPipeline>,
+    wireframe: Arc<GraphicsPipeline>,
+    wireframe_overlay: Arc<GraphicsPipeline>,
+    lines: Arc<GraphicsPipeline>,
+    lines_overlay: Arc<GraphicsPipeline>,
+    text: Arc<GraphicsPipeline>,
+}
+
+pub struct Renderer {
+    instance: Arc<Instance>,
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+
+    settings: RenderSettings,
+    output: Output,
+    dimensions: winit::dpi::PhysicalSize<u32>,
+    recreate_output: bool,
+    frame_limiter: FrameLimiter,
+    debug_view: DebugView,
+    wireframe: Wireframe,
+    show_normals: bool,
+    debug_draw: DebugDraw,
+    text: TextBatch,
+    stats: RenderStats,
+    profiler: Option<GpuProfiler>,
+    capture: bool,
+    captured: Option<CapturedFrame>,
+
+    font_view: Arc<ImageView<ImmutableImage>>,
+    font_sampler: Arc<Sampler>,
+    /// Images from `upload_texture`, with their pixels to upload again if the device is recreated
+    textures: HashMap<TextureHandle, (Texture, Arc<ImageView<ImmutableImage>>)>,
+
+    shaders: Shaders,
+    /// SPIR-V swapped in by `reload_shader` by file name, applied again if the device is recreated
+    reloaded_shaders: BTreeMap<String, Vec<u32>>,
+    pipeline_cache: Arc<PipelineCache>,
+    pipeline_cache_path: PathBuf,
+
+    render_pass: Arc<RenderPass>,
+    pipelines: Pipelines,
+
+    /// The models drawn last frame and their buffers, reused while they don't change
+    uploaded: Option<(ModelCollection, Mesh)>,
+
+    vp_buffer: CpuBufferPool<deferred_vert::ty::VpData>,
+    lighting_buffer: CpuBufferPool<lighting_frag::ty::LightData>,
+    camera_buffer: CpuBufferPool<lighting_frag::ty::CameraData>,
+}
+
+impl Renderer {
+    pub fn new(instance: Arc<Instance>, target: RenderTarget) -> Result<Self, RenderError> {
+        Self::with_settings(instance, target, RenderSettings::default())
+    }
+
+    pub fn with_settings(
+        instance: Arc<Instance>,
+        target: RenderTarget,
+        settings: RenderSettings,
+    ) -> Result<Self, RenderError> {
+        let surface = match &target {
+            RenderTarget::Window(surface) => Some(surface),
+            RenderTarget::Headless(_) => None,
+        };
+
+        let (device, queue) = get_device_queue(&instance, surface, settings.device.as_ref())?;
+
+        let (output, render_pass, dimensions) = match target {
+            RenderTarget::Window(surface) => {
+                let resources = SwapchainResources::new(
+                    device.clone(),
+                    surface.clone(),
+                    surface.window().inner_size(),
+                    settings.present_mode,
+                )?
+                .ok_or(RenderError::ZeroExtent)?;
+
+                let render_pass = resources.render_pass();
+                let dimensions = resources.dimensions();
+                let output = Output::Swapchain {
+                    surface,
+                    fences: vec![None; settings.frames_in_flight.max(1)],
+                    resources,
+                    previous_fence_i: 0,
+                };
+
+                (output, render_pass, dimensions)
+            }
+            RenderTarget::Headless(dimensions) => {
+                let render_pass = get_render_pass(device.clone(), HEADLESS_FORMAT)?;
+                let (image, attachments) =
+                    new_output_image(device.clone(), render_pass.clone(), dimensions)?;
+
+                (
+                    Output::Image { image, attachments },
+                    render_pass,
+                    dimensions,
+                )
+            }
+        };
+
+        let shaders = Shaders::load(device.clone())?;
+
+        let pipeline_cache_path = PathBuf::from(PIPELINE_CACHE_PATH);
+        let pipeline_cache = load_pipeline_cache(device.clone(), &pipeline_cache_path)?;
+
+        let pipelines = build_pipelines(
+            device.clone(),
+            render_pass.clone(),
+            pipeline_cache.clone(),
+            &shaders,
+        )?;
+
+        let (font_view, font_sampler) = load_font(device.clone(), queue.clone())?;
+
+        // One more slot than frames in flight, so the oldest has always finished when it's read
+        let profiler = GpuProfiler::new(&queue, settings.frames_in_flight.max(1) + 1)?;
+        if profiler.is_none() {
+            log::warn!("Timestamps not supported, GPU timings are disabled");
+        }
+
+        let vp_buffer = CpuBufferPool::uniform_buffer(device.clone());
+        let lighting_buffer = CpuBufferPool::uniform_buffer(device.clone());
+        let camera_buffer = CpuBufferPool::uniform_buffer(device.clone());
+
+        Ok(Self {
+            instance,
+            device,
+            queue,
+            frame_limiter: FrameLimiter::new(settings.target_fps),
+            settings,
+            output,
+            dimensions,
+            recreate_output: false,
+            debug_view: DebugView::default(),
+            wireframe: Wireframe::default(),
+            show_normals: false,
+            debug_draw: DebugDraw::new(),
+            text: TextBatch::new(),
+            stats: RenderStats::default(),
+            profiler,
+            capture: false,
+            captured: None,
+            font_view,
+            font_sampler,
+            textures: HashMap::new(),
+            shaders,
+            pipeline_cache,
+            reloaded_shaders: BTreeMap::new(),
+            pipeline_cache_path,
+            render_pass,
+            pipelines,
+            uploaded: None,
+            vp_buffer,
+            lighting_buffer,
+            camera_buffer,
+        })
+    }
+
+    pub fn device(self: &Self) -> Arc<Device> {
+        self.device.clone()
+    }
+
+    pub fn dimensions(self: &Self) -> winit::dpi::PhysicalSize<u32> {
+        self.dimensions
+    }
+
+    pub fn settings(self: &Self) -> RenderSettings {
+        self.settings.clone()
+    }
+
+    /// The present mode actually in use, which may differ from the requested one
+    pub fn present_mode(self: &Self) -> Option<PresentMode> {
+        match &self.output {
+            Output::Swapchain { resources, .. } => Some(resources.present_mode()),
+            Output::Image { .. } => None,
+        }
+    }
+
+    pub fn debug_view(self: &Self) -> DebugView {
+        self.debug_view
+    }
+
+    pub fn set_debug_view(self: &mut Self, debug_view: DebugView) {
+        self.debug_view = debug_view;
+    }
+
+    pub fn wireframe(self: &Self) -> Wireframe {
+        self.wireframe
+    }
+
+    pub fn set_wireframe(self: &mut Self, wireframe: Wireframe) {
+        self.wireframe = wireframe;
+    }
+
+    pub fn show_normals(self: &Self) -> bool {
+        self.show_normals
+    }
+
+    /// Draws each vertex normal as a line
+    pub fn set_show_normals(self: &mut Self, show_normals: bool) {
+        self.show_normals = show_normals;
+    }
+
+    /// Lines added here are drawn in the next frame and then cleared
+    pub fn debug_draw_mut(self: &mut Self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// Text and rectangles added here are drawn over the next frame and then cleared
+    pub fn text_mut(self: &mut Self) -> &mut TextBatch {
+        &mut self.text
+    }
+
+    pub fn stats(self: &Self) -> RenderStats {
+        self.stats
+    }
+
+    /// Per pass GPU timings from a recent frame. These lag a few frames behind so reading them
+    /// never stalls, and are `None` if the device can't write timestamps
+    pub fn gpu_timings(self: &Self) -> Option<GpuTimings> {
+        self.profiler
+            .as_ref()
+            .and_then(|profiler| profiler.latest())
+    }
+
+    /// Whether frames can be copied back from the output, which depends on its format and usage
+    pub fn can_capture(self: &Self) -> bool {
+        let transfer_source = match &self.output {
+            Output::Swapchain { resources, .. } => {
+                resources
+                    .swapchain()
+                    .create_info()
+                    .image_usage
+                    .transfer_source
+            }
+            Output::Image { .. } => true,
+        };
+
+        transfer_source && CapturedFrame::supports_format(self.output_format())
+    }
+
+    /// Copies each frame back to the CPU once it's finished, to be collected with `take_capture`.
+    /// This waits for every frame, so it's meant for recording rather than interactive use
+    pub fn set_capture(self: &mut Self, capture: bool) {
+        self.capture = capture;
+        self.captured = None;
+    }
+
+    /// The last captured frame, if one has been drawn since the last call
+    pub fn take_capture(self: &mut Self) -> Option<CapturedFrame> {
+        self.captured.take()
+    }
+
+    /// Device local memory use, if the device can report it
+    pub fn gpu_memory(self: &Self) -> Option<GpuMemory> {
+        gpu_memory_usage(&self.device)
+    }
+
+    /// The G-buffer attachments currently being rendered into
+    pub fn attachments(self: &Self) -> &Attachments {
+        match &self.output {
+            Output::Swapchain { resources, .. } => resources.attachments(),
+            Output::Image { attachments, .. } => attachments,
+        }
+    }
+
+    /// Resizes the output, the swapchain and attachments are recreated before the next frame
+    pub fn resize(self: &mut Self, dimensions: winit::dpi::PhysicalSize<u32>) {
+        self.dimensions = dimensions;
+        self.recreate_output = true;
+    }
+
+    /// Uploads the combined geometry of a set of models to the GPU
+    pub fn upload(self: &Self, models: &ModelCollection) -> Result<Mesh, RenderError> {
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            models.vertices(),
+        )?;
+
+        let skin_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            models.skin_vertices(),
+        )?;
+
+        let index_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::index_buffer(),
+            false,
+            models.indices(),
+        )?;
+
+        let morph_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            models.morph_vertices(),
+        )?;
+
+        let deform_buffers = self.deform_buffers(
+            models.joint_matrices(),
+            models.morph_deltas(),
+            models.morph_weights(),
+        )?;
+
+        Ok(Mesh {
+            vertex_buffer,
+            skin_buffer,
+            morph_buffer,
+            index_buffer,
+            deform_buffers,
+        })
+    }
+
+    /// The scene's models on the GPU. Buffers from the previous frame are kept for whatever hasn't
+    /// changed, so a still scene isn't uploaded again and moving or posing models only replaces
+    /// the joint matrices
+    fn scene_mesh(self: &mut Self, models: &ModelCollection) -> Result<Mesh, RenderError> {
+        let mesh = match &self.uploaded {
+            Some((previous, mesh)) if previous.same_geometry(models) => {
+                if previous.same_deformation(models) {
+                    return Ok(mesh.clone());
+                }
+
+                Mesh {
+                    deform_buffers: self.deform_buffers(
+                        models.joint_matrices(),
+                        models.morph_deltas(),
+                        models.morph_weights(),
+                    )?,
+                    ..mesh.clone()
+                }
+            }
+            _ => self.upload(models)?,
+        };
+
+        self.uploaded = Some((models.clone(), mesh.clone()));
+
+        Ok(mesh)
+    }
+
+    fn deform_buffers(
+        self: &Self,
+        joint_matrices: Vec<TMat4<f32>>,
+        morph_deltas: Vec<MorphDelta>,
+        morph_weights: Vec<f32>,
+    ) -> Result<DeformBuffers, RenderError> {
+        let joint_matrices = joint_matrices.into_iter().map(|matrix| matrix.into());
+
+        Ok(DeformBuffers {
+            joints: self.storage_buffer(joint_matrices, identity::<f32, 4>().into())?,
+            morph_deltas: self.storage_buffer(morph_deltas, MorphDelta::default())?,
+            morph_weights: self.storage_buffer(morph_weights, 0.0)?,
+        })
+    }
+
+    /// Holds `fallback` when there's no data, since empty buffers can't be bound
+    fn storage_buffer<T>(
+        self: &Self,
+        data: impl IntoIterator<Item = T>,
+        fallback: T,
+    ) -> Result<Arc<CpuAccessibleBuffer<[T]>>, RenderError>
+    where
+        [T]: BufferContents,
+    {
+        let mut data = data.into_iter().collect::<Vec<_>>();
+        if data.is_empty() {
+            data.push(fallback);
+        }
+
+        Ok(CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::storage_buffer(),
+            false,
+            data,
+        )?)
+    }
+
+    /// Draws a frame, recovering from an out of date swapchain, a lost device or running out of
+    /// memory. Any other error is returned
+    pub fn render(self: &mut Self, scene: &Scene, camera: &Camera) -> Result<(), RenderError> {
+        let result = self.draw_frame(scene, camera);
+
+        // Lines and text are only meant for one frame, even if it was skipped
+        self.debug_draw.clear();
+        self.text.clear();
+
+        let error = match result {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+
+        if error.is_out_of_date() {
+            self.recreate_output = true;
+
+            Ok(())
+        } else if error.is_device_lost() {
+            log::warn!("Device lost, recreating renderer");
+
+            self.recreate_device()
+        } else if error.is_out_of_memory() {
+            log::warn!("Skipping frame: {}", error);
+
+            // Frames in flight hold on to uniform and vertex buffers, let them finish to free up memory
+            self.wait_for_frames();
+
+            Ok(())
+        } else {
+            Err(error)
+        }
+    }
+
+    /// Writes the pipeline cache to disk so the next run can skip shader compilation
+    pub fn save_pipeline_cache(self: &Self) -> Result<(), RenderError> {
+        let data = self.pipeline_cache.get_data()?;
+
+        // Write to a temporary file first so a crash can't leave a truncated cache behind
+        let tmp_path = self.pipeline_cache_path.with_extension("bin.tmp");
+
+        if let Err(e) = fs::write(&tmp_path, data)
+            .and_then(|_| fs::rename(&tmp_path, &self.pipeline_cache_path))
+        {
+            let _ = fs::remove_file(&tmp_path);
+
+            return Err(e.into());
+        }
+
+        Ok(())
+    }
+
+    fn draw_frame(self: &mut Self, scene: &Scene, camera: &Camera) -> Result<(), RenderError> {
+        if self.recreate_output {
+            self.recreate_output()?;
+
+            // Nothing can be drawn until the window is restored
+            if self.recreate_output {
+                return Ok(());
+            }
+        }
+
+        let models = scene.models();
+        let models = if models.is_empty() {
+            None
+        } else {
+            Some(ModelCollection::from_vec(models))
+        };
+        let mesh = match &models {
+            Some(models) => Some(self.scene_mesh(models)?),
+            None => None,
+        };
+
+        let vp_buffer_subbuffer = {
+            let vp = vp::get_vp(self.dimensions);
+            let vp_data = deferred_vert::ty::VpData {
+                view: vp.view.into(),
+                proj: vp.proj.into(),
+            };
+
+            self.vp_buffer.next(vp_data)?
+        };
+
+        let deform_buffers = match &mesh {
+            Some(mesh) => mesh.deform_buffers.clone(),
+            None => self.deform_buffers(Vec::new(), Vec::new(), Vec::new())?,
+        };
+
+        let lighting_buffer_subbuffer = {
+            // The lighting shader only handles a single light so far
+            let light_i = match self.debug_view {
+                DebugView::Light(light_i) => light_i,
+                _ => 0,
+            };
+            let light = scene.lights().into_iter().nth(light_i).unwrap_or_default();
+            let light_data = lighting_frag::ty::LightData {
+                _dummy0: [0; 4],
+                position: light.position(),
+                colour: light.colour(),
+                intensity: light.intensity(),
+            };
+
+            self.lighting_buffer.next(light_data)?
+        };
+
+        let camera_buffer_subbuffer = {
+            let camera_data = lighting_frag::ty::CameraData {
+                position: camera.position(),
+                dt: camera.dt(),
+            };
+
+            self.camera_buffer.next(camera_data)?
+        };
+
+        let deferred_layout = self
+            .pipelines
+            .deferred
+            .layout()
+            .set_layouts()
+            .get(0)
+            .ok_or(RenderError::MissingDescriptorSet(0))?
+            .clone();
+        let deferred_set = PersistentDescriptorSet::new(
+            deferred_layout,
+            [
+                WriteDescriptorSet::buffer(0, vp_buffer_subbuffer.clone()),
+                WriteDescriptorSet::buffer(1, deform_buffers.joints.clone()),
+                WriteDescriptorSet::buffer(2, deform_buffers.morph_deltas.clone()),
+                WriteDescriptorSet::buffer(3, deform_buffers.morph_weights.clone()),
+            ],
+        )?;
+
+        let lighting_layout = self
+            .pipelines
+            .lighting
+            .layout()
+            .set_layouts()
+            .get(0)
+            .ok_or(RenderError::MissingDescriptorSet(0))?
+            .clone();
+        let lighting_set = PersistentDescriptorSet::new(
+            lighting_layout,
+            [
+                WriteDescriptorSet::image_view(0, self.attachments().normal_buffer()),
+                WriteDescriptorSet::image_view(1, self.attachments().colour_buffer()),
+                WriteDescriptorSet::buffer(2, vp_buffer_subbuffer.clone()),
+                WriteDescriptorSet::buffer(3, lighting_buffer_subbuffer),
+                WriteDescriptorSet::buffer(4, camera_buffer_subbuffer),
+                WriteDescriptorSet::image_view(5, self.attachments().depth_buffer()),
+                WriteDescriptorSet::buffer(6, deform_buffers.joints),
+                WriteDescriptorSet::buffer(7, deform_buffers.morph_deltas),
+                WriteDescriptorSet::buffer(8, deform_buffers.morph_weights),
+            ],
+        )?;
+
+        let debug_data = lighting_frag::ty::DebugData {
+            mode: self.debug_view.mode(),
+            near: vp::NEAR,
+            far: vp::FAR,
+        };
+
+        let draw_lighting = self.wireframe != Wireframe::Only;
+        let mut stats = RenderStats::default();
+
+        if let Some(mesh) = &mesh {
+            let passes = if draw_lighting { 2 } else { 1 };
+
+            stats.draw_calls += passes;
+            stats.triangles += (mesh.index_count() / 3 * passes) as u64;
+        }
+
+        let mut forward_draws = Vec::new();
+
+        if let Some(models) = &models {
+            let wireframe_pipeline = match self.wireframe {
+                Wireframe::Off => None,
+                Wireframe::Only => Some(self.pipelines.wireframe.clone()),
+                Wireframe::Overlay => Some(self.pipelines.wireframe_overlay.clone()),
+            };
+
+            if let Some(pipeline) = wireframe_pipeline {
+                let vertices = models.wireframe_vertices();
+                stats.triangles += (vertices.len() / 3) as u64;
+
+                forward_draws.push(self.forward_draw(
+                    pipeline,
+                    [WriteDescriptorSet::buffer(0, vp_buffer_subbuffer.clone())],
+                    vertices,
+                )?);
+            }
+
+            if self.show_normals {
+                forward_draws.push(self.forward_draw(
+                    self.pipelines.lines.clone(),
+                    [WriteDescriptorSet::buffer(0, vp_buffer_subbuffer.clone())],
+                    models.normal_lines(NORMAL_LINE_LENGTH, NORMAL_LINE_COLOUR),
+                )?);
+            }
+        }
+
+        for (depth_tested, pipeline) in [
+            (true, self.pipelines.lines.clone()),
+            (false, self.pipelines.lines_overlay.clone()),
+        ] {
+            let vertices = self.debug_draw.vertices(depth_tested);
+
+            if !vertices.is_empty() {
+                forward_draws.push(self.forward_draw(
+                    pipeline,
+                    [WriteDescriptorSet::buffer(0, vp_buffer_subbuffer.clone())],
+                    vertices,
+                )?);
+            }
+        }
+
+        // Drawn last so it's over everything, including overlay lines
+        if !self.text.is_empty() {
+            let vertices = self.text.vertices(self.dimensions);
+            stats.triangles += (vertices.len() / 3) as u64;
+
+            forward_draws.push(self.forward_draw(
+                self.pipelines.text.clone(),
+                [WriteDescriptorSet::image_view_sampler(
+                    0,
+                    self.font_view.clone(),
+                    self.font_sampler.clone(),
+                )],
+                vertices,
+            )?);
+        }
+
+        stats.draw_calls += forward_draws.len() as u32;
+
+        let viewport = Viewport {
+            origin: [0.0, 0.0],
+            dimensions: self.dimensions.into(),
+            depth_range: 0.0..1.0,
+        };
+
+        let output_format = self.output_format();
+        let capture_buffer = if self.capture && self.can_capture() {
+            let len = self.dimensions.width as usize * self.dimensions.height as usize * 4;
+
+            Some(CpuAccessibleBuffer::from_iter(
+                self.device.clone(),
+                BufferUsage::transfer_destination(),
+                false,
+                (0..len).map(|_| 0u8),
+            )?)
+        } else {
+            None
+        };
+
+        let queries = match &mut self.profiler {
+            Some(profiler) => Some(profiler.begin_frame()?),
+            None => None,
+        };
+
+        let record = |framebuffer: Arc<Framebuffer>| {
+            get_command_buffer(
+                self.device.clone(),
+                self.queue.clone(),
+                self.pipelines.deferred.clone(),
+                deferred_set.clone(),
+                self.pipelines.lighting.clone(),
+                lighting_set.clone(),
+                debug_data,
+                framebuffer,
+                viewport.clone(),
+                mesh.as_ref(),
+                draw_lighting,
+                &forward_draws,
+                queries.as_ref(),
+                capture_buffer
+                    .clone()
+                    .map(|buffer| buffer as Arc<dyn BufferAccess>),
+            )
+        };
+
+        self.stats = stats;
+
+        match &mut self.output {
+            Output::Swapchain {
+                resources,
+                fences,
+                previous_fence_i,
+                ..
+            } => {
+                // Fences are per frame in flight rather than per image, so the CPU can't get more
+                // than `frames_in_flight` frames ahead however many images the swapchain has
+                let fence_i = (*previous_fence_i + 1) % fences.len();
+
+                if let Some(frame_fence) = &fences[fence_i] {
+                    frame_fence.wait(None)?;
+                }
+
+                // Wait before acquiring so the image is presented as soon as possible after
+                self.frame_limiter.wait();
+
+                let swapchain = resources.swapchain();
+                let (image_i, suboptimal, acquire_future) =
+                    vulkano::swapchain::acquire_next_image(swapchain.clone(), None)?;
+
+                if suboptimal {
+                    self.recreate_output = true;
+                }
+
+                let command_buffer = record(resources.attachments().framebuffer(image_i))?;
+
+                let previous_future = match fences[*previous_fence_i].clone() {
+                    // Create a NowFuture
+                    None => {
+                        let mut now = sync::now(self.device.clone());
+                        now.cleanup_finished();
+
+                        now.boxed()
+                    }
+                    // Use the existing FenceSignalFuture
+                    Some(fence) => fence.boxed(),
+                };
+
+                let future = previous_future
+                    .join(acquire_future)
+                    .then_execute(self.queue.clone(), command_buffer)?
+                    .then_swapchain_present(self.queue.clone(), swapchain, image_i)
+                    .then_signal_fence_and_flush();
+
+                *previous_fence_i = fence_i;
+
+                match future {
+                    Ok(value) => fences[fence_i] = Some(Arc::new(value)),
+                    Err(e) => {
+                        fences[fence_i] = None;
+
+                        return Err(e.into());
+                    }
+                }
+
+                // The copy has to finish before the buffer can be read
+                if let (Some(_), Some(frame_fence)) = (&capture_buffer, &fences[fence_i]) {
+                    frame_fence.wait(None)?;
+                }
+            }
+            Output::Image { attachments, .. } => {
+                self.frame_limiter.wait();
+
+                let command_buffer = record(attachments.framebuffer(0))?;
+
+                sync::now(self.device.clone())
+                    .then_execute(self.queue.clone(), command_buffer)?
+                    .then_signal_fence_and_flush()?
+                    .wait(None)?;
+            }
+        }
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_frame();
+        }
+
+        if let Some(capture_buffer) = capture_buffer {
+            self.captured = CapturedFrame::from_raw(
+                &capture_buffer.read()?,
+                self.dimensions.width,
+                self.dimensions.height,
+                output_format,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn output_format(self: &Self) -> Format {
+        match &self.output {
+            Output::Swapchain { resources, .. } => resources.swapchain().image_format(),
+            Output::Image { .. } => HEADLESS_FORMAT,
+        }
+    }
+
+    /// Uploads the vertices of a draw in the forward pass and binds its resources to set 0
+    fn forward_draw<V>(
+        self: &Self,
+        pipeline: Arc<GraphicsPipeline>,
+        descriptor_writes: impl IntoIterator<Item = WriteDescriptorSet>,
+        vertices: Vec<V>,
+    ) -> Result<ForwardDraw, RenderError>
+    where
+        [V]: BufferContents,
+    {
+        let vertex_count = vertices.len() as u32;
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            vertices,
+        )?;
+
+        let layout = pipeline
+            .layout()
+            .set_layouts()
+            .get(0)
+            .ok_or(RenderError::MissingDescriptorSet(0))?
+            .clone();
+        let descriptor_set = PersistentDescriptorSet::new(layout, descriptor_writes)?;
+
+        Ok(ForwardDraw {
+            pipeline,
+            descriptor_set,
+            vertex_buffer,
+            vertex_count,
+        })
+    }
+
+    fn wait_for_frames(self: &mut Self) {
+        if let Output::Swapchain { fences, .. } = &mut self.output {
+            for fence in fences.iter_mut() {
+                if let Some(fence) = fence.take() {
+                    // The frame is being dropped either way
+                    let _ = fence.wait(None);
+                }
+            }
+        }
+    }
+
+    /// Uploads a texture as an sRGB image for `handle`. Call again with the same handle after the
+    /// texture is reloaded, the previous image is freed once nothing refers to it
+    pub fn upload_texture(
+        self: &mut Self,
+        handle: TextureHandle,
+        texture: &Texture,
+    ) -> Result<(), RenderError> {
+        let view = upload_image(
+            self.queue.clone(),
+            texture.pixels().to_vec(),
+            [texture.width(), texture.height()],
+            Format::R8G8B8A8_SRGB,
+            &format!("{:?}", handle),
+        )?;
+
+        self.textures.insert(handle, (texture.clone(), view));
+
+        Ok(())
+    }
+
+    /// The image last uploaded for `handle`
+    pub fn texture(self: &Self, handle: TextureHandle) -> Option<Arc<ImageView<ImmutableImage>>> {
+        self.textures.get(&handle).map(|(_, view)| view.clone())
+    }
+
+    /// Swaps in a shader compiled at runtime from `file_name` in `src/shaders`, rebuilding the
+    /// pipelines. Everything is left as it was on an error. Returns false if the file isn't used on
+    /// this device. Uniform blocks must keep their layout, as the structs filling them are generated
+    /// at build time
+    pub fn reload_shader(
+        self: &mut Self,
+        file_name: &str,
+        spirv: &[u32],
+    ) -> Result<bool, RenderError> {
+        let mut shaders = self.shaders.clone();
+        let module = match shaders.module_mut(file_name, &self.device) {
+            Some(module) => module,
+            None => return Ok(false),
+        };
+
+        // Reflection only reads the code, so a module that doesn't match the renderer is caught
+        // by the layout check or pipeline creation
+        let new_module = unsafe { ShaderModule::from_words(self.device.clone(), spirv)? };
+        check_shader_layout(module, &new_module)?;
+        *module = new_module;
+
+        self.pipelines = build_pipelines(
+            self.device.clone(),
+            self.render_pass.clone(),
+            self.pipeline_cache.clone(),
+            &shaders,
+        )?;
+        self.shaders = shaders;
+        self.reloaded_shaders
+            .insert(file_name.to_string(), spirv.to_vec());
+
+        Ok(true)
+    }
+
+    /// Rebuilds everything created from the device, keeping the same output and any shaders
+    /// reloaded since the renderer was built
+    fn recreate_device(self: &mut Self) -> Result<(), RenderError> {
+        let target = match &self.output {
+            Output::Swapchain { surface, .. } => RenderTarget::Window(surface.clone()),
+            Output::Image { .. } => RenderTarget::Headless(self.dimensions),
+        };
+        let reloaded_shaders = std::mem::take(&mut self.reloaded_shaders);
+        let textures = std::mem::take(&mut self.textures);
+
+        *self = Renderer::with_settings(self.instance.clone(), target, self.settings.clone())?;
+
+        for (handle, (texture, _)) in textures {
+            self.upload_texture(handle, &texture)?;
+        }
+
+        for (file_name, spirv) in reloaded_shaders {
+            if let Err(e) = self.reload_shader(&file_name, &spirv) {
+                log::warn!("Failed to reload {} on the new device: {}", file_name, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds the output and everything sized to it. Leaves `recreate_output` set if the window
+    /// is minimised so it's tried again next frame
+    fn recreate_output(self: &mut Self) -> Result<(), RenderError> {
+        let render_pass = match &mut self.output {
+            Output::Swapchain { resources, .. } => {
+                let new_resources = match resources.recreate(self.dimensions)? {
+                    Some(new_resources) => new_resources,
+                    None => return Ok(()),
+                };
+
+                *resources = new_resources;
+
+                self.dimensions = resources.dimensions();
+
+                resources.render_pass()
+            }
+            Output::Image { image, attachments } => {
+                let (new_image, new_attachments) = new_output_image(
+                    self.device.clone(),
+                    self.render_pass.clone(),
+                    self.dimensions,
+                )?;
+
+                *image = new_image;
+                *attachments = new_attachments;
+
+                self.render_pass.clone()
+            }
+        };
+
+        self.set_render_pass(render_pass)?;
+        self.recreate_output = false;
+
+        Ok(())
+    }
+
+    /// Rebuilds the pipelines if the render pass has changed, the viewport is dynamic so a resize
+    /// alone doesn't require new pipelines
+    fn set_render_pass(self: &mut Self, render_pass: Arc<RenderPass>) -> Result<(), RenderError> {
+        if Arc::ptr_eq(&self.render_pass, &render_pass) {
+            return Ok(());
+        }
+
+        self.pipelines = build_pipelines(
+            self.device.clone(),
+            render_pass.clone(),
+            self.pipeline_cache.clone(),
+            &self.shaders,
+        )?;
+        self.render_pass = render_pass;
+
+        Ok(())
+    }
+}
+
+fn new_output_image(
+    device: Arc<Device>,
+    render_pass: Arc<RenderPass>,
+    dimensions: winit::dpi::PhysicalSize<u32>,
+) -> Result<(Arc<AttachmentImage>, Attachments), RenderError> {
+    let image = AttachmentImage::with_usage(
+        device.clone(),
+        dimensions.into(),
+        HEADLESS_FORMAT,
+        ImageUsage {
+            transfer_source: true,
+            ..ImageUsage::none()
+        },
+    )?;
+
+    let attachments = Attachments::new(
+        device,
+        render_pass,
+        vec![ImageView::new_default(image.clone())? as _],
+        dimensions,
+    )?;
+
+    Ok((image, attachments))
+}
+
+/// Uploads the font atlas, sampled without filtering so glyphs stay sharp at whole number scales
+fn load_font(
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+) -> Result<(Arc<ImageView<ImmutableImage>>, Arc<Sampler>), RenderError> {
+    let (pixels, [width, height]) = font::atlas();
+
+    let view = upload_image(
+        queue,
+        pixels,
+        [width, height],
+        Format::R8_UNORM,
+        "Font atlas",
+    )?;
+
+    let sampler = Sampler::new(
+        device,
+        SamplerCreateInfo {
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..Default::default()
+        },
+    )?;
+
+    Ok((view, sampler))
+}
+
+/// Copies pixels into a new image and waits for the upload to finish
+fn upload_image(
+    queue: Arc<Queue>,
+    pixels: Vec<u8>,
+    [width, height]: [u32; 2],
+    format: Format,
+    name: &str,
+) -> Result<Arc<ImageView<ImmutableImage>>, RenderError> {
+    let (image, future) = ImmutableImage::from_iter(
+        pixels,
+        ImageDimensions::Dim2d {
+            width,
+            height,
+            array_layers: 1,
+        },
+        MipmapsCount::One,
+        format,
+        queue,
+    )?;
+
+    future.then_signal_fence_and_flush()?.wait(None)?;
+
+    set_name(&*image.inner().image, name);
+
+    Ok(ImageView::new_default(image)?)
+}
+
+fn load_pipeline_cache(
+    device: Arc<Device>,
+    path: &PathBuf,
+) -> Result<Arc<PipelineCache>, RenderError> {
+    if let Ok(data) = fs::read(path) {
+        // The driver checks the cache header and ignores data from a different device or driver
+        if let Ok(cache) = unsafe { PipelineCache::with_data(device.clone(), &data) } {
+            return Ok(cache);
+        }
+    }
+
+    Ok(PipelineCache::empty(device)?)
+}
+
+fn build_pipelines(
+    device: Arc<Device>,
+    render_pass: Arc<RenderPass>,
+    pipeline_cache: Arc<PipelineCache>,
+    shaders: &Shaders,
+) -> Result<Pipelines, RenderError> {
+    let subpass =
+        |i: u32| Subpass::from(render_pass.clone(), i).ok_or(RenderError::MissingSubpass(i));
+    let deferred_pass = subpass(0)?;
+    let lighting_pass = subpass(1)?;
+    let forward_pass = subpass(2)?;
+
+    let deferred = get_pipeline_with_depth(
+        device.clone(),
+        shaders.deferred_vert.clone(),
+        shaders.deferred_frag.clone(),
+        deferred_pass,
+        pipeline_cache.clone(),
+    )?;
+
+    let lighting = get_pipeline(
+        device.clone(),
+        shaders.lighting_vert.clone(),
+        shaders.lighting_frag.clone(),
+        lighting_pass,
+        pipeline_cache.clone(),
+    )?;
+
+    // Matches the choice of fragment shader in `Shaders::load`
+    let polygon_mode = if device.enabled_features().fill_mode_non_solid {
+        PolygonMode::Line
+    } else {
+        PolygonMode::Fill
+    };
+
+    let wireframe = get_wireframe_pipeline(
+        device.clone(),
+        shaders.wireframe_vert.clone(),
+        shaders.wireframe_frag.clone(),
+        forward_pass.clone(),
+        pipeline_cache.clone(),
+        polygon_mode,
+        false,
+    )?;
+
+    let wireframe_overlay = get_wireframe_pipeline(
+        device.clone(),
+        shaders.wireframe_vert.clone(),
+        shaders.wireframe_frag.clone(),
+        forward_pass.clone(),
+        pipeline_cache.clone(),
+        polygon_mode,
+        true,
+    )?;
+
+    let lines = get_line_pipeline(
+        device.clone(),
+        shaders.line_vert.clone(),
+        shaders.line_frag.clone(),
+        forward_pass.clone(),
+        pipeline_cache.clone(),
+        true,
+    )?;
+
+    let lines_overlay = get_line_pipeline(
+        device.clone(),
+        shaders.line_vert.clone(),
+        shaders.line_frag.clone(),
+        forward_pass.clone(),
+        pipeline_cache.clone(),
+        false,
+    )?;
+
+    let text = get_text_pipeline(
+        device,
+        shaders.text_vert.clone(),
+        shaders.text_frag.clone(),
+        forward_pass,
+        pipeline_cache,
+    )?;
+
+    set_name(&*deferred, "Deferred pipeline");
+    set_name(&*lighting, "Lighting pipeline");
+    set_name(&*wireframe, "Wireframe pipeline");
+    set_name(&*wireframe_overlay, "Wireframe overlay pipeline");
+    set_name(&*lines, "Line pipeline");
+    set_name(&*lines_overlay, "Line overlay pipeline");
+    set_name(&*text, "Text pipeline");
+
+    Ok(Pipelines {
+        deferred,
+        lighting,
+        wireframe,
+        wireframe_overlay,
+        lines,
+        lines_overlay,
+        text,
+    })
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use crate::light::Light;
+use crate::lod::LodGroup;
+use crate::model::Model;
+use crate::vp::VP;
+
+#[derive(Default, Clone)]
+pub struct Scene {
+    models: Vec<Model>,
+    lights: Vec<Light>,
+    /// Levels of detail for models, by index
+    lods: BTreeMap<usize, LodGroup>,
+}
+
+#[allow(dead_code)]
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a model to the scene, returning its index
+    pub fn add_model(self: &mut Self, model: Model) -> usize {
+        self.models.push(model);
+
+        self.models.len() - 1
+    }
+
+    /// Adds a light to the scene, returning its index
+    pub fn add_light(self: &mut Self, light: Light) -> usize {
+        self.lights.push(light);
+
+        self.lights.len() - 1
+    }
+
+    pub fn models(self: &Self) -> Vec<Model> {
+        self.models.clone()
+    }
+
+    pub fn model_mut(self: &mut Self, index: usize) -> &mut Model {
+        &mut self.models[index]
+    }
+
+    pub fn lights(self: &Self) -> Vec<Light> {
+        self.lights.clone()
+    }
+
+    pub fn light_mut(self: &mut Self, index: usize) -> &mut Light {
+        &mut self.lights[index]
+    }
+
+    /// Draws the model at `index` with the group's levels of detail from now on
+    pub fn set_lod(self: &mut Self, index: usize, lod: LodGroup) {
+        self.lods.insert(index, lod);
+    }
+
+    pub fn lod(self: &Self, index: usize) -> Option<&LodGroup> {
+        self.lods.get(&index)
+    }
+
+    /// Chooses each model's level of detail for how large it is on screen, keeping its matrix,
+    /// material and pose
+    pub fn select_lods(self: &mut Self, vp: &VP) {
+        for (&index, lod) in &mut self.lods {
+            let model = &mut self.models[index];
+
+            if lod.select(&model.matrix(), vp) {
+                let previous = std::mem::replace(model, lod.model().clone());
+
+                model.set_matrix(previous.matrix());
+                model.set_material(previous.material());
+
+                // Levels are posed as they were when generated, so the joints and weights the
+                // model was animated to are carried over
+                if let (Some(skin), Some(previous)) = (model.skin_mut(), previous.skin()) {
+                    *skin.skeleton_mut() = previous.skeleton().clone();
+                }
+                if let (Some(morph), Some(previous)) = (model.morph_mut(), previous.morph()) {
+                    morph.set_weights(&previous.weights());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,563 @@
+use nalgebra_glm::{identity, quat_to_mat4, Qua, TMat4, TVec3, TVec4};
+
+use serde::{Deserialize, Serialize};
+
+use std::f32::consts::FRAC_PI_2;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::animation::{Clip, Pose};
+use crate::assets::{AssetRegistry, MeshHandle, TextureHandle};
+use crate::camera::Camera;
+use crate::clock::Clock;
+use crate::error::SceneError;
+use crate::light::Light;
+use crate::lod::LodGroup;
+use crate::model::{Material, Model};
+use crate::scene::Scene;
+use crate::shapes;
+use crate::vertex::{CompactVec3, CUBE_VERTICES};
+
+/// A serialisable description of a scene, stored as JSON
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub models: Vec<ModelDesc>,
+    pub lights: Vec<LightDesc>,
+    pub camera: CameraDesc,
+    /// Keyframe animations, played from the start of the scene
+    pub clips: Vec<Clip>,
+}
+
+/// Where a model's geometry comes from
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MeshDesc {
+    /// A unit cube centred on the origin
+    Cube,
+    /// A mesh file, relative to the scene file
+    File {
+        path: PathBuf,
+    },
+    UvSphere {
+        radius: f32,
+        segments: u32,
+        rings: u32,
+    },
+    Icosphere {
+        radius: f32,
+        subdivisions: u32,
+    },
+    Plane {
+        width: f32,
+        depth: f32,
+        subdivisions: [u32; 2],
+    },
+    Cylinder {
+        radius: f32,
+        height: f32,
+        segments: u32,
+    },
+    Cone {
+        radius: f32,
+        height: f32,
+        segments: u32,
+    },
+    Torus {
+        major_radius: f32,
+        minor_radius: f32,
+        segments: u32,
+        sides: u32,
+    },
+    Capsule {
+        radius: f32,
+        height: f32,
+        segments: u32,
+        rings: u32,
+    },
+}
+
+impl MeshDesc {
+    /// Why the shape can't be built, as the generators panic on these
+    fn check(self: &Self) -> Result<(), String> {
+        let problem = match *self {
+            MeshDesc::UvSphere {
+                segments, rings, ..
+            } if segments < 3 || rings < 2 => {
+                "a uv sphere needs at least 3 segments and 2 rings".to_string()
+            }
+            MeshDesc::Icosphere { subdivisions, .. }
+                if subdivisions > shapes::MAX_ICOSPHERE_SUBDIVISIONS =>
+            {
+                format!(
+                    "an icosphere can't have more than {} subdivisions",
+                    shapes::MAX_ICOSPHERE_SUBDIVISIONS
+                )
+            }
+            MeshDesc::Plane {
+                subdivisions: [columns, rows],
+                ..
+            } if columns < 1 || rows < 1 => {
+                "a plane needs at least 1 subdivision each way".to_string()
+            }
+            MeshDesc::Cylinder { segments, .. } | MeshDesc::Cone { segments, .. }
+                if segments < 3 =>
+            {
+                "cylinders and cones need at least 3 segments".to_string()
+            }
+            MeshDesc::Torus {
+                segments, sides, ..
+            } if segments < 3 || sides < 3 => {
+                "a torus needs at least 3 segments and 3 sides".to_string()
+            }
+            MeshDesc::Capsule {
+                segments, rings, ..
+            } if segments < 3 || rings < 1 => {
+                "a capsule needs at least 3 segments and 1 ring".to_string()
+            }
+            _ => return Ok(()),
+        };
+
+        Err(problem)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModelDesc {
+    /// How clips refer to the model
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub mesh: MeshDesc,
+    #[serde(default)]
+    pub transform: Transform,
+    #[serde(default)]
+    pub material: Material,
+    /// An image file for the surface, relative to the scene file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texture: Option<PathBuf>,
+    #[serde(default)]
+    pub animations: Vec<Animation>,
+    /// Simpler versions drawn when the model is small on screen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lod: Option<LodDesc>,
+}
+
+/// Levels of detail generated from a model's mesh
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LodDesc {
+    /// How many levels, including the full mesh
+    pub levels: usize,
+    /// The fraction of the triangles each level keeps from the one before
+    #[serde(default = "LodDesc::default_ratio")]
+    pub ratio: f32,
+}
+
+/// Scale, then rotation, then translation
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: CompactVec3,
+    /// Euler angles in degrees, applied around x, then y, then z
+    pub rotation: CompactVec3,
+    pub scale: CompactVec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scale: [1.0; 3],
+        }
+    }
+}
+
+/// Movement layered over a model's transform
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Animation {
+    /// Rotates around `axis` at `speed` radians per second
+    Spin { axis: CompactVec3, speed: f32 },
+    /// Moves by `offset` times the sine of `frequency` radians per second plus `phase`
+    Oscillate {
+        offset: CompactVec3,
+        frequency: f32,
+        #[serde(default)]
+        phase: f32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightDesc {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub position: CompactVec3,
+    pub colour: CompactVec3,
+    pub intensity: f32,
+    #[serde(default)]
+    pub pulse: Option<Pulse>,
+}
+
+/// Blends a light's colour towards another and back
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pulse {
+    pub colour: CompactVec3,
+    /// Radians per second
+    pub speed: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraDesc {
+    pub position: CompactVec3,
+}
+
+impl SceneFile {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(self: &Self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Two spinning cubes lit by a pulsing light
+    pub fn demo() -> Self {
+        Self {
+            models: vec![
+                ModelDesc {
+                    name: String::new(),
+                    mesh: MeshDesc::Cube,
+                    transform: Transform {
+                        translation: [0.0, -5.0, 20.0],
+                        ..Default::default()
+                    },
+                    material: Material::default(),
+                    texture: None,
+                    animations: vec![
+                        Animation::Spin {
+                            axis: [0.5, -0.5, 0.5],
+                            speed: 2.0,
+                        },
+                        Animation::Oscillate {
+                            offset: [0.0, 0.0, 15.0],
+                            frequency: 2.0,
+                            phase: 0.0,
+                        },
+                    ],
+                    lod: None,
+                },
+                ModelDesc {
+                    name: String::new(),
+                    mesh: MeshDesc::Cube,
+                    transform: Transform {
+                        translation: [0.0, 2.0, 15.0],
+                        ..Default::default()
+                    },
+                    material: Material::default(),
+                    texture: None,
+                    animations: vec![
+                        Animation::Spin {
+                            axis: [0.2, 0.0, 0.5],
+                            speed: 10.0,
+                        },
+                        Animation::Oscillate {
+                            offset: [3.0, 0.0, 0.0],
+                            frequency: 1.0,
+                            phase: 0.0,
+                        },
+                        Animation::Oscillate {
+                            offset: [0.0, 3.0, 0.0],
+                            frequency: 1.0,
+                            phase: FRAC_PI_2,
+                        },
+                    ],
+                    lod: None,
+                },
+            ],
+            lights: vec![LightDesc {
+                name: String::new(),
+                position: [0.0, 0.0, -1.0],
+                colour: [0.0, 0.0, 1.0],
+                intensity: 1.0,
+                pulse: Some(Pulse {
+                    colour: [1.0, 0.0, 1.0],
+                    speed: 3.0,
+                }),
+            }],
+            camera: CameraDesc::default(),
+            clips: Vec::new(),
+        }
+    }
+
+    /// Loads every mesh and creates the scene at time 0. Mesh paths are relative to `directory`
+    pub fn build(self: &Self, directory: &Path) -> Result<Scene, SceneError> {
+        self.build_with_assets(directory, &mut AssetRegistry::new())
+    }
+
+    /// As `build`, taking meshes from the registry so files it has already loaded aren't read
+    /// again
+    pub fn build_with_assets(
+        self: &Self,
+        directory: &Path,
+        assets: &mut AssetRegistry,
+    ) -> Result<Scene, SceneError> {
+        let mut scene = Scene::new();
+
+        for desc in &self.models {
+            let model = desc.model(directory, assets)?;
+            let index = scene.add_model(model.clone());
+
+            if let Some(lod) = desc.lod {
+                scene.set_lod(index, lod.generate(&model)?);
+            }
+        }
+
+        for _ in &self.lights {
+            scene.add_light(Light::default());
+        }
+
+        self.animate(&mut scene, &Clock::default());
+
+        Ok(scene)
+    }
+
+    /// Loads every model's texture from the registry, each handle once. Paths are relative to
+    /// `directory`
+    pub fn load_textures(
+        self: &Self,
+        directory: &Path,
+        assets: &mut AssetRegistry,
+    ) -> Result<Vec<TextureHandle>, SceneError> {
+        let mut handles = Vec::new();
+
+        for path in self.models.iter().filter_map(|desc| desc.texture.as_ref()) {
+            let handle = assets.load_texture(directory.join(path))?;
+
+            if !handles.contains(&handle) {
+                handles.push(handle);
+            }
+        }
+
+        Ok(handles)
+    }
+
+    /// Swaps a re-imported mesh into the models made from it, regenerating only their levels of
+    /// detail and leaving the rest of the scene as it is
+    pub fn reload_mesh(
+        self: &Self,
+        scene: &mut Scene,
+        directory: &Path,
+        assets: &mut AssetRegistry,
+        handle: MeshHandle,
+    ) -> Result<(), SceneError> {
+        // Matched by path, as the registry does, so no other mesh is loaded
+        let mesh_path = assets.mesh_path(handle);
+
+        for (i, desc) in self.models.iter().enumerate() {
+            match &desc.mesh {
+                MeshDesc::File { path } if directory.join(path) == mesh_path => (),
+                _ => continue,
+            }
+
+            let model = desc.model(directory, assets)?;
+
+            if let Some(lod) = desc.lod {
+                scene.set_lod(i, lod.generate(&model)?);
+            }
+            *scene.model_mut(i) = model;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the models and lights of a scene from `build` to where they are at the clock's time
+    pub fn animate(self: &Self, scene: &mut Scene, clock: &Clock) {
+        let time = clock.time().as_secs_f32();
+
+        for (i, desc) in self.models.iter().enumerate() {
+            let pose = self.pose(&desc.name, time);
+            let model = scene.model_mut(i);
+            model.set_matrix(desc.matrix(time, &pose));
+
+            if let Some(morph) = model.morph_mut() {
+                for &(target, weight) in &pose.morph_weights {
+                    if target < morph.targets().len() {
+                        morph.set_weight(target, weight);
+                    }
+                }
+            }
+
+            // Joints are animated by name, like models and lights
+            if let Some(skin) = model.skin_mut() {
+                for clip in &self.clips {
+                    skin.skeleton_mut().apply_clip(clip, time);
+                }
+            }
+        }
+
+        for (i, desc) in self.lights.iter().enumerate() {
+            let pose = self.pose(&desc.name, time);
+            *scene.light_mut(i) = desc.light(time, &pose);
+        }
+    }
+
+    /// What the clips set on a named model or light at `time` seconds. Later clips take priority
+    pub fn pose(self: &Self, name: &str, time: f32) -> Pose {
+        let mut pose = Pose::default();
+
+        if !name.is_empty() {
+            for clip in &self.clips {
+                clip.pose(name, time, &mut pose);
+            }
+        }
+
+        pose
+    }
+
+    pub fn camera(self: &Self, clock: &Clock) -> Camera {
+        Camera::new(self.camera.position, clock)
+    }
+}
+
+impl ModelDesc {
+    /// The mesh with its material, taking files from the registry
+    fn model(
+        self: &Self,
+        directory: &Path,
+        assets: &mut AssetRegistry,
+    ) -> Result<Model, SceneError> {
+        self.mesh.check().map_err(SceneError::InvalidScene)?;
+
+        let mut model = match &self.mesh {
+            MeshDesc::Cube => Model::new_cube(CUBE_VERTICES.to_vec()),
+            MeshDesc::File { path } => {
+                let handle = assets.load_mesh(directory.join(path))?;
+
+                assets.mesh(handle)
+            }
+            &MeshDesc::UvSphere {
+                radius,
+                segments,
+                rings,
+            } => shapes::uv_sphere(radius, segments, rings),
+            &MeshDesc::Icosphere {
+                radius,
+                subdivisions,
+            } => shapes::icosphere(radius, subdivisions),
+            &MeshDesc::Plane {
+                width,
+                depth,
+                subdivisions,
+            } => shapes::plane(width, depth, subdivisions),
+            &MeshDesc::Cylinder {
+                radius,
+                height,
+                segments,
+            } => shapes::cylinder(radius, height, segments),
+            &MeshDesc::Cone {
+                radius,
+                height,
+                segments,
+            } => shapes::cone(radius, height, segments),
+            &MeshDesc::Torus {
+                major_radius,
+                minor_radius,
+                segments,
+                sides,
+            } => shapes::torus(major_radius, minor_radius, segments, sides),
+            &MeshDesc::Capsule {
+                radius,
+                height,
+                segments,
+                rings,
+            } => shapes::capsule(radius, height, segments, rings),
+        };
+
+        model.set_material(self.material);
+
+        Ok(model)
+    }
+
+    /// The world matrix at `time` seconds, with anything the pose sets replacing the transform
+    pub fn matrix(self: &Self, time: f32, pose: &Pose) -> TMat4<f32> {
+        let mut translation = TVec3::from(pose.translation.unwrap_or(self.transform.translation));
+        let mut rotation: TMat4<f32> = identity();
+
+        for animation in &self.animations {
+            match *animation {
+                Animation::Spin { axis, speed } => {
+                    rotation = rotation * nalgebra_glm::rotation(time * speed, &TVec3::from(axis));
+                }
+                Animation::Oscillate {
+                    offset,
+                    frequency,
+                    phase,
+                } => translation += TVec3::from(offset) * (time * frequency + phase).sin(),
+            }
+        }
+
+        let base_rotation = match pose.rotation {
+            Some(rotation) => quat_to_mat4(&Qua::from_vector(TVec4::from(rotation))),
+            None => {
+                let [x, y, z] = self.transform.rotation.map(f32::to_radians);
+
+                nalgebra_glm::rotation(z, &TVec3::z())
+                    * nalgebra_glm::rotation(y, &TVec3::y())
+                    * nalgebra_glm::rotation(x, &TVec3::x())
+            }
+        };
+
+        nalgebra_glm::translation(&translation)
+            * rotation
+            * base_rotation
+            * nalgebra_glm::scaling(&TVec3::from(pose.scale.unwrap_or(self.transform.scale)))
+    }
+}
+
+impl LodDesc {
+    fn default_ratio() -> f32 {
+        0.5
+    }
+
+    /// The levels generated from a model, if `levels` and `ratio` are in range
+    fn generate(self: &Self, model: &Model) -> Result<LodGroup, SceneError> {
+        if self.levels < 1 {
+            return Err(SceneError::InvalidScene(
+                "levels of detail need at least 1 level".to_string(),
+            ));
+        }
+        if !(self.ratio > 0.0 && self.ratio < 1.0) {
+            return Err(SceneError::InvalidScene(format!(
+                "level of detail ratio {} isn't between 0 and 1",
+                self.ratio
+            )));
+        }
+
+        Ok(LodGroup::generate(model, self.levels, self.ratio))
+    }
+}
+
+impl LightDesc {
+    /// The light at `time` seconds, with anything the pose sets replacing the description
+    pub fn light(self: &Self, time: f32, pose: &Pose) -> Light {
+        let base_colour = pose.light_colour.unwrap_or(self.colour);
+
+        let colour = match self.pulse {
+            Some(pulse) => {
+                let t = ((time * pulse.speed).sin() + 1.0) * 0.5;
+
+                [0, 1, 2].map(|i| base_colour[i] + (pulse.colour[i] - base_colour[i]) * t)
+            }
+            None => base_colour,
+        };
+
+        Light::new(
+            pose.light_position.unwrap_or(self.position),
+            colour,
+            pose.light_intensity.unwrap_or(self.intensity),
+        )
+    }
+}
@@ -0,0 +1,347 @@
+use nalgebra_glm::{identity, quat_identity, quat_to_mat4, Qua, TMat4, TVec3, TVec4};
+
+use crate::animation::{Clip, Pose};
+use crate::camera::Camera;
+use crate::clock::Clock;
+use crate::light::Light;
+use crate::model::Model;
+use crate::scene::Scene;
+
+/// A handle to a node in a `SceneGraph`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// A node's transform relative to its parent. Applied as scale, then rotation, then translation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trs {
+    pub translation: TVec3<f32>,
+    pub rotation: Qua<f32>,
+    pub scale: TVec3<f32>,
+}
+
+impl Default for Trs {
+    fn default() -> Self {
+        Self {
+            translation: TVec3::zeros(),
+            rotation: quat_identity(),
+            scale: TVec3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Trs {
+    pub fn from_translation(translation: TVec3<f32>) -> Self {
+        Self {
+            translation,
+            ..Default::default()
+        }
+    }
+
+    pub fn matrix(self: &Self) -> TMat4<f32> {
+        nalgebra_glm::translation(&self.translation)
+            * quat_to_mat4(&self.rotation)
+            * nalgebra_glm::scaling(&self.scale)
+    }
+}
+
+#[derive(Clone)]
+struct Node {
+    name: String,
+    local: Trs,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: TMat4<f32>,
+    dirty: bool,
+    mesh: Option<Model>,
+    light: Option<Light>,
+    camera: bool,
+}
+
+/// A hierarchy of transforms with meshes, lights and cameras attached to the nodes. World matrices
+/// are cached and only recomputed for nodes whose transform, or an ancestor's, has changed
+#[derive(Default, Clone)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+}
+
+#[allow(dead_code)]
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node under `parent`, or at the root
+    pub fn add_node(
+        self: &mut Self,
+        name: impl Into<String>,
+        local: Trs,
+        parent: Option<NodeId>,
+    ) -> NodeId {
+        let id = NodeId(self.nodes.len());
+
+        self.nodes.push(Node {
+            name: name.into(),
+            local,
+            parent,
+            children: Vec::new(),
+            world: identity(),
+            dirty: true,
+            mesh: None,
+            light: None,
+            camera: false,
+        });
+
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+
+        id
+    }
+
+    pub fn len(self: &Self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn ids(self: &Self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    /// The first node with this name
+    pub fn find(self: &Self, name: &str) -> Option<NodeId> {
+        self.nodes
+            .iter()
+            .position(|node| node.name == name)
+            .map(NodeId)
+    }
+
+    pub fn name(self: &Self, id: NodeId) -> String {
+        self.nodes[id.0].name.clone()
+    }
+
+    pub fn parent(self: &Self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id.0].parent
+    }
+
+    pub fn children(self: &Self, id: NodeId) -> Vec<NodeId> {
+        self.nodes[id.0].children.clone()
+    }
+
+    /// Moves a node and its descendants under a new parent, keeping its local transform. Panics
+    /// if `parent` is the node itself or one of its descendants
+    pub fn set_parent(self: &mut Self, id: NodeId, parent: Option<NodeId>) {
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            assert!(a != id, "a node can't be its own ancestor");
+            ancestor = self.nodes[a.0].parent;
+        }
+
+        if let Some(old) = self.nodes[id.0].parent {
+            self.nodes[old.0].children.retain(|&child| child != id);
+        }
+        if let Some(parent) = parent {
+            self.nodes[parent.0].children.push(id);
+        }
+
+        self.nodes[id.0].parent = parent;
+        self.mark_dirty(id);
+    }
+
+    pub fn local(self: &Self, id: NodeId) -> Trs {
+        self.nodes[id.0].local
+    }
+
+    pub fn set_local(self: &mut Self, id: NodeId, local: Trs) {
+        self.nodes[id.0].local = local;
+        self.mark_dirty(id);
+    }
+
+    pub fn set_translation(self: &mut Self, id: NodeId, translation: TVec3<f32>) {
+        self.nodes[id.0].local.translation = translation;
+        self.mark_dirty(id);
+    }
+
+    pub fn set_rotation(self: &mut Self, id: NodeId, rotation: Qua<f32>) {
+        self.nodes[id.0].local.rotation = rotation;
+        self.mark_dirty(id);
+    }
+
+    pub fn set_scale(self: &mut Self, id: NodeId, scale: TVec3<f32>) {
+        self.nodes[id.0].local.scale = scale;
+        self.mark_dirty(id);
+    }
+
+    /// Sets whatever the pose animates on the node and its attached mesh and light
+    pub fn apply_pose(self: &mut Self, id: NodeId, pose: &Pose) {
+        let node = &mut self.nodes[id.0];
+
+        if let Some(translation) = pose.translation {
+            node.local.translation = TVec3::from(translation);
+        }
+        if let Some(rotation) = pose.rotation {
+            node.local.rotation = Qua::from_vector(TVec4::from(rotation));
+        }
+        if let Some(scale) = pose.scale {
+            node.local.scale = TVec3::from(scale);
+        }
+
+        if let Some(morph) = node.mesh.as_mut().and_then(Model::morph_mut) {
+            for &(target, weight) in &pose.morph_weights {
+                if target < morph.targets().len() {
+                    morph.set_weight(target, weight);
+                }
+            }
+        }
+
+        if let Some(light) = &mut node.light {
+            *light = Light::new(
+                pose.light_position.unwrap_or(light.position()),
+                pose.light_colour.unwrap_or(light.colour()),
+                pose.light_intensity.unwrap_or(light.intensity()),
+            );
+        }
+
+        if pose.translation.is_some() || pose.rotation.is_some() || pose.scale.is_some() {
+            self.mark_dirty(id);
+        }
+    }
+
+    /// Poses every node the clip animates, at `time` seconds of playback, along with the joints
+    /// of any skinned meshes
+    pub fn apply_clip(self: &mut Self, clip: &Clip, time: f32) {
+        for id in self.ids().collect::<Vec<_>>() {
+            let mut pose = Pose::default();
+            clip.pose(&self.nodes[id.0].name, time, &mut pose);
+
+            self.apply_pose(id, &pose);
+
+            if let Some(skin) = self.nodes[id.0].mesh.as_mut().and_then(Model::skin_mut) {
+                skin.skeleton_mut().apply_clip(clip, time);
+            }
+        }
+    }
+
+    /// Marks a node and everything below it as needing a new world matrix. Stops at nodes which
+    /// are already dirty, since their descendants must be too
+    fn mark_dirty(self: &mut Self, id: NodeId) {
+        let mut stack = vec![id];
+
+        while let Some(id) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            if node.dirty {
+                continue;
+            }
+
+            node.dirty = true;
+            stack.extend(node.children.iter().copied());
+        }
+    }
+
+    /// Recomputes the world matrix of every dirty node
+    pub fn update(self: &mut Self) {
+        let roots = self
+            .ids()
+            .filter(|&id| self.nodes[id.0].parent.is_none())
+            .collect::<Vec<_>>();
+
+        let mut stack = roots
+            .into_iter()
+            .map(|id| (id, identity(), false))
+            .collect::<Vec<(NodeId, TMat4<f32>, bool)>>();
+
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = &mut self.nodes[id.0];
+            let changed = parent_changed || node.dirty;
+
+            if changed {
+                node.world = parent_world * node.local.matrix();
+                node.dirty = false;
+            }
+
+            let world = node.world;
+            stack.extend(node.children.iter().map(|&child| (child, world, changed)));
+        }
+    }
+
+    /// The world matrix as of the last `update`
+    pub fn world_matrix(self: &Self, id: NodeId) -> TMat4<f32> {
+        self.nodes[id.0].world
+    }
+
+    /// The node's origin in world space as of the last `update`
+    pub fn world_position(self: &Self, id: NodeId) -> TVec3<f32> {
+        let world = self.nodes[id.0].world;
+
+        TVec3::new(world[(0, 3)], world[(1, 3)], world[(2, 3)])
+    }
+
+    /// Attaches a mesh, whose own matrix is applied before the node's
+    pub fn attach_mesh(self: &mut Self, id: NodeId, model: Model) {
+        self.nodes[id.0].mesh = Some(model);
+    }
+
+    /// Attaches a light, whose position is relative to the node
+    pub fn attach_light(self: &mut Self, id: NodeId, light: Light) {
+        self.nodes[id.0].light = Some(light);
+    }
+
+    /// Makes the node a viewpoint for `camera`
+    pub fn attach_camera(self: &mut Self, id: NodeId) {
+        self.nodes[id.0].camera = true;
+    }
+
+    pub fn detach(self: &mut Self, id: NodeId) {
+        let node = &mut self.nodes[id.0];
+        node.mesh = None;
+        node.light = None;
+        node.camera = false;
+    }
+
+    pub fn mesh_mut(self: &mut Self, id: NodeId) -> Option<&mut Model> {
+        self.nodes[id.0].mesh.as_mut()
+    }
+
+    pub fn light_mut(self: &mut Self, id: NodeId) -> Option<&mut Light> {
+        self.nodes[id.0].light.as_mut()
+    }
+
+    /// Updates the world matrices and flattens the attached meshes and lights into a scene
+    pub fn scene(self: &mut Self) -> Scene {
+        self.update();
+
+        let mut scene = Scene::new();
+
+        for node in &self.nodes {
+            if let Some(mesh) = &node.mesh {
+                let mut model = mesh.clone();
+                model.set_matrix(node.world * mesh.matrix());
+                scene.add_model(model);
+            }
+
+            if let Some(light) = &node.light {
+                let [x, y, z] = light.position();
+                let position = node.world * TVec4::new(x, y, z, 1.0);
+
+                scene.add_light(Light::new(
+                    [position.x, position.y, position.z],
+                    light.colour(),
+                    light.intensity(),
+                ));
+            }
+        }
+
+        scene
+    }
+
+    /// A camera at the first node with one attached, as of the last `update`
+    pub fn camera(self: &Self, clock: &Clock) -> Option<Camera> {
+        self.ids().find(|&id| self.nodes[id.0].camera).map(|id| {
+            let position = self.world_position(id);
+
+            Camera::new([position.x, position.y, position.z], clock)
+        })
+    }
+}
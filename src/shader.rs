@@ -40,3 +40,52 @@ pub mod lighting_frag {
         },
     }
 }
+
+pub mod wireframe_vert {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/shaders/wireframe.vert.glsl",
+    }
+}
+
+pub mod wireframe_frag {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/shaders/wireframe.frag.glsl",
+    }
+}
+
+pub mod wireframe_barycentric_frag {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/shaders/wireframe_barycentric.frag.glsl",
+    }
+}
+
+pub mod line_vert {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/shaders/line.vert.glsl",
+    }
+}
+
+pub mod line_frag {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/shaders/line.frag.glsl",
+    }
+}
+
+pub mod text_vert {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        path: "src/shaders/text.vert.glsl",
+    }
+}
+
+pub mod text_frag {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        path: "src/shaders/text.frag.glsl",
+    }
+}
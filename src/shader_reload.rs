@@ -0,0 +1,187 @@
+use shaderc::{Compiler, ShaderKind};
+
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use crate::assets::POLL_INTERVAL;
+use crate::font::GLYPH_SIZE;
+use crate::renderer::Renderer;
+use crate::text::TextBatch;
+
+/// The GLSL the renderer was built with
+pub const SHADER_DIRECTORY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shaders");
+
+const ERROR_SCALE: f32 = 2.0;
+const ERROR_MARGIN: f32 = 8.0;
+const ERROR_COLOUR: [f32; 4] = [1.0, 0.4, 0.4, 1.0];
+const ERROR_BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+
+/// Watches a directory of GLSL shaders for development, recompiling changed files with shaderc and
+/// swapping them into the renderer. A shader that fails to compile or load keeps the previous
+/// version running, and its error is kept until the file is fixed
+pub struct ShaderWatcher {
+    directory: PathBuf,
+    compiler: Compiler,
+    modified: HashMap<PathBuf, SystemTime>,
+    errors: BTreeMap<String, String>,
+    last_poll: Instant,
+}
+
+impl ShaderWatcher {
+    /// None if shaderc couldn't be initialised. The files as they are now are assumed to match
+    /// the shaders the renderer was built with
+    pub fn new(directory: impl Into<PathBuf>) -> Option<Self> {
+        let directory = directory.into();
+
+        Some(Self {
+            modified: modified_times(&directory),
+            directory,
+            compiler: Compiler::new()?,
+            errors: BTreeMap::new(),
+            last_poll: Instant::now(),
+        })
+    }
+
+    pub fn directory(self: &Self) -> &Path {
+        &self.directory
+    }
+
+    /// Recompiles and reloads every shader changed since the last poll
+    pub fn poll(self: &mut Self, renderer: &mut Renderer) {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = modified_times(&self.directory);
+
+        let mut changed: Vec<&PathBuf> = modified
+            .iter()
+            .filter(|(path, time)| self.modified.get(*path) != Some(time))
+            .map(|(path, _)| path)
+            .collect();
+        changed.sort();
+
+        for path in changed {
+            let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+
+            match self.reload(path, &file_name, renderer) {
+                Ok(true) => {
+                    log::info!("Reloaded {}", file_name);
+
+                    self.errors.remove(&file_name);
+                }
+                Ok(false) => (),
+                Err(e) => {
+                    log::warn!("Failed to reload {}:\n{}", file_name, e);
+
+                    self.errors.insert(file_name, e);
+                }
+            }
+        }
+
+        // Deleted files can't be reloaded, so their errors would never clear
+        self.errors.retain(|file_name, _| {
+            modified
+                .keys()
+                .any(|path| path.file_name() == Some(OsStr::new(file_name)))
+        });
+
+        self.modified = modified;
+    }
+
+    fn reload(
+        self: &mut Self,
+        path: &Path,
+        file_name: &str,
+        renderer: &mut Renderer,
+    ) -> Result<bool, String> {
+        let kind = if file_name.contains(".vert") {
+            ShaderKind::Vertex
+        } else if file_name.contains(".frag") {
+            ShaderKind::Fragment
+        } else {
+            return Ok(false);
+        };
+
+        let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+
+        let artifact = self
+            .compiler
+            .compile_into_spirv(&source, kind, file_name, "main", None)
+            .map_err(|e| e.to_string())?;
+
+        if artifact.get_num_warnings() > 0 {
+            log::warn!("{}", artifact.get_warning_messages());
+        }
+
+        renderer
+            .reload_shader(file_name, artifact.as_binary())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Each file that failed to reload and why
+    pub fn errors(self: &Self) -> Vec<(String, String)> {
+        self.errors
+            .iter()
+            .map(|(file_name, error)| (file_name.clone(), error.clone()))
+            .collect()
+    }
+
+    /// Draws the current errors along the bottom of an output of `dimensions`
+    pub fn draw_errors(
+        self: &Self,
+        text: &mut TextBatch,
+        dimensions: winit::dpi::PhysicalSize<u32>,
+    ) {
+        if self.errors.is_empty() {
+            return;
+        }
+
+        let lines: Vec<String> = self
+            .errors
+            .iter()
+            .flat_map(|(file_name, error)| {
+                std::iter::once(format!("{}:", file_name))
+                    .chain(error.lines().map(|line| format!("  {}", line)))
+            })
+            .collect();
+
+        let line_height = GLYPH_SIZE as f32 * ERROR_SCALE;
+        let top = dimensions.height as f32 - lines.len() as f32 * line_height - ERROR_MARGIN * 2.0;
+
+        text.rect(
+            [0.0, top],
+            [dimensions.width as f32, dimensions.height as f32],
+            ERROR_BACKGROUND,
+        );
+
+        let mut position = [ERROR_MARGIN, top + ERROR_MARGIN];
+        for line in &lines {
+            position = text.text(position, ERROR_SCALE, ERROR_COLOUR, line);
+        }
+    }
+}
+
+/// Every GLSL file in the directory and when it was last written. Files that can't be read are
+/// left out and picked up once they can be
+fn modified_times(directory: &Path) -> HashMap<PathBuf, SystemTime> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return HashMap::new(),
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension() == Some(OsStr::new("glsl")))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|metadata| metadata.modified());
+
+            modified.ok().map(|time| (path, time))
+        })
+        .collect()
+}
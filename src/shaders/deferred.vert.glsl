@@ -2,6 +2,12 @@
         
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec3 normal;
+layout(location = 2) in vec3 colour;
+layout(location = 3) in uvec4 joints;
+layout(location = 4) in vec4 weights;
+layout(location = 5) in uint first_delta;
+layout(location = 6) in uint first_weight;
+layout(location = 7) in uint target_count;
 
 layout(location = 0) out vec3 out_normal;
 layout(location = 1) out vec3 out_colour;
@@ -11,9 +17,53 @@ layout(set = 0, binding = 0) uniform VpData {
     mat4 proj;
 } vp;
 
+layout(set = 0, binding = 1) readonly buffer JointData {
+    mat4 matrices[];
+} joint_data;
+
+struct MorphDelta {
+    vec4 position;
+    vec4 normal;
+};
+
+layout(set = 0, binding = 2) readonly buffer MorphDeltas {
+    MorphDelta deltas[];
+} morph_deltas;
+
+layout(set = 0, binding = 3) readonly buffer MorphWeights {
+    float weights[];
+} morph_weights;
+
+// Models without a skin are weighted to one joint holding their matrix, so this places every
+// vertex in world space. A vertex with no weight isn't moved
+mat4 skin_matrix() {
+    if (weights == vec4(0.0)) {
+        return mat4(1.0);
+    }
+
+    return weights.x * joint_data.matrices[joints.x]
+        + weights.y * joint_data.matrices[joints.y]
+        + weights.z * joint_data.matrices[joints.z]
+        + weights.w * joint_data.matrices[joints.w];
+}
+
 void main() {
-    out_colour = vec3(1.0);
-    out_normal = normal;
+    // Morph targets are blended in the bind pose, before skinning
+    vec3 morphed_position = position;
+    vec3 morphed_normal = normal;
+
+    for (uint i = 0; i < target_count; i++) {
+        float weight = morph_weights.weights[first_weight + i];
+        MorphDelta delta = morph_deltas.deltas[first_delta + i];
+
+        morphed_position += weight * delta.position.xyz;
+        morphed_normal += weight * delta.normal.xyz;
+    }
+
+    mat4 skin = skin_matrix();
+
+    out_colour = colour;
+    out_normal = mat3(skin) * morphed_normal;
 
-    gl_Position = vp.proj * vp.view * vec4(position, 1.0);
+    gl_Position = vp.proj * vp.view * skin * vec4(morphed_position, 1.0);
 }
@@ -4,6 +4,7 @@ layout(location = 0) in vec3 frag_pos;
 
 layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput u_normals;
 layout(input_attachment_index = 1, set = 0, binding = 1) uniform subpassInput u_colour;
+layout(input_attachment_index = 2, set = 0, binding = 5) uniform subpassInput u_depth;
 
 layout(location = 0) out vec4 f_colour;
 
@@ -25,6 +26,18 @@ layout(set = 0, binding = 4) uniform CameraData {
     uint dt;
 } camera;
 
+// 0 lit, 1 normals, 2 albedo, 3 linear depth, 4 world position, 5 light contribution
+layout(push_constant) uniform DebugData {
+    uint mode;
+    float near;
+    float far;
+} debug;
+
+// The projection is OpenGL style, so undo its depth mapping rather than the Vulkan one
+float linearise_depth(float depth) {
+    return 2.0 * debug.near * debug.far / (debug.far + debug.near - depth * (debug.far - debug.near));
+}
+
 void main() {
     vec3 colour = subpassLoad(u_colour).xyz;
     vec3 normals = subpassLoad(u_normals).xyz;
@@ -37,5 +50,23 @@ void main() {
     vec3 diffuse = max(dot(normals, lightDir), 0.0) * light.colour * light.intensity; // * abs(vec3(sin(camera.dt * 0.05), cos(camera.dt * 0.1), sin(camera.dt * 0.2)));
     vec3 specular = pow(max(dot(viewDir, reflectDir), 0.0), 16) * 0.9 * light.colour * light.intensity;
     
-    f_colour = vec4((ambient + diffuse + specular) * colour, 1.0);
+    switch (debug.mode) {
+        case 1:
+            f_colour = vec4(normals * 0.5 + 0.5, 1.0);
+            break;
+        case 2:
+            f_colour = vec4(colour, 1.0);
+            break;
+        case 3:
+            f_colour = vec4(vec3(linearise_depth(subpassLoad(u_depth).x) / debug.far), 1.0);
+            break;
+        case 4:
+            f_colour = vec4(fract(frag_pos), 1.0);
+            break;
+        case 5:
+            f_colour = vec4(diffuse + specular, 1.0);
+            break;
+        default:
+            f_colour = vec4((ambient + diffuse + specular) * colour, 1.0);
+    }
 }
@@ -1,15 +1,59 @@
 #version 450
 
 layout(location = 0) in vec3 position;
+layout(location = 3) in uvec4 joints;
+layout(location = 4) in vec4 weights;
+layout(location = 5) in uint first_delta;
+layout(location = 6) in uint first_weight;
+layout(location = 7) in uint target_count;
 
 layout(set = 0, binding = 2) uniform VpData {
     mat4 view;
     mat4 proj;
 } vp;
 
+// The same joints and morph targets as the deferred pass, so deformed meshes cover the same
+// pixels
+layout(set = 0, binding = 6) readonly buffer JointData {
+    mat4 matrices[];
+} joint_data;
+
+struct MorphDelta {
+    vec4 position;
+    vec4 normal;
+};
+
+layout(set = 0, binding = 7) readonly buffer MorphDeltas {
+    MorphDelta deltas[];
+} morph_deltas;
+
+layout(set = 0, binding = 8) readonly buffer MorphWeights {
+    float weights[];
+} morph_weights;
+
 layout(location = 0) out vec3 frag_pos;
 
+mat4 skin_matrix() {
+    if (weights == vec4(0.0)) {
+        return mat4(1.0);
+    }
+
+    return weights.x * joint_data.matrices[joints.x]
+        + weights.y * joint_data.matrices[joints.y]
+        + weights.z * joint_data.matrices[joints.z]
+        + weights.w * joint_data.matrices[joints.w];
+}
+
 void main() {
-    frag_pos = position;
-    gl_Position = vp.proj * vp.view * vec4(position, 1.0);
-}
\ No newline at end of file
+    vec3 morphed_position = position;
+
+    for (uint i = 0; i < target_count; i++) {
+        morphed_position += morph_weights.weights[first_weight + i]
+            * morph_deltas.deltas[first_delta + i].position.xyz;
+    }
+
+    vec4 world_position = skin_matrix() * vec4(morphed_position, 1.0);
+
+    frag_pos = world_position.xyz;
+    gl_Position = vp.proj * vp.view * world_position;
+}
@@ -0,0 +1,18 @@
+#version 450
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 1) in vec4 in_colour;
+
+layout(location = 0) out vec4 f_colour;
+
+layout(set = 0, binding = 0) uniform sampler2D u_font;
+
+void main() {
+    float coverage = texture(u_font, in_uv).r;
+
+    if (coverage == 0.0) {
+        discard;
+    }
+
+    f_colour = vec4(in_colour.rgb, in_colour.a * coverage);
+}
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 uv;
+layout(location = 2) in vec4 colour;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec4 out_colour;
+
+void main() {
+    out_uv = uv;
+    out_colour = colour;
+
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,9 @@
+#version 450
+
+layout(location = 0) in vec3 in_barycentric;
+
+layout(location = 0) out vec4 f_colour;
+
+void main() {
+    f_colour = vec4(0.0, 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 barycentric;
+
+layout(location = 0) out vec3 out_barycentric;
+
+layout(set = 0, binding = 0) uniform VpData {
+    mat4 view;
+    mat4 proj;
+} vp;
+
+void main() {
+    out_barycentric = barycentric;
+
+    gl_Position = vp.proj * vp.view * vec4(position, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 450
+
+// Used when the device can't rasterize polygons as lines, triangles are filled and everything
+// but their edges is discarded
+
+layout(location = 0) in vec3 in_barycentric;
+
+layout(location = 0) out vec4 f_colour;
+
+void main() {
+    // Distance to each edge in pixels
+    vec3 edge_distance = in_barycentric / fwidth(in_barycentric);
+
+    if (min(edge_distance.x, min(edge_distance.y, edge_distance.z)) > 1.0) {
+        discard;
+    }
+
+    f_colour = vec4(0.0, 1.0, 0.0, 1.0);
+}
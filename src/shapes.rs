@@ -0,0 +1,365 @@
+use nalgebra_glm::TVec3;
+
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::model::Model;
+use crate::vertex::{Index, Vertex};
+
+// Shapes are centred on the origin with -y as up, the same as the cube. Texture coordinates run
+// from 0 to 1, with v increasing downwards
+
+/// Each subdivision of an icosphere quadruples its triangles, so this is over a million
+pub const MAX_ICOSPHERE_SUBDIVISIONS: u32 = 8;
+
+/// A sphere with `segments` lines of longitude and `rings` bands of latitude
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Model {
+    assert!(segments >= 3 && rings >= 2);
+
+    let mut builder = MeshBuilder::default();
+
+    builder.surface(segments, rings, |column, row| {
+        let u = column as f32 / segments as f32;
+        let v = row as f32 / rings as f32;
+        let normal = sphere_normal(TAU * u, PI * v);
+
+        vertex(normal * radius, normal, [u, v])
+    });
+
+    builder.build()
+}
+
+/// A sphere made by splitting each triangle of an icosahedron into four `subdivisions` times,
+/// which spreads the triangles more evenly than `uv_sphere`. Texture coordinates wrap past 1 on
+/// triangles crossing the seam
+pub fn icosphere(radius: f32, subdivisions: u32) -> Model {
+    assert!(subdivisions <= MAX_ICOSPHERE_SUBDIVISIONS);
+
+    let t = (1.0 + 5.0f32.sqrt()) / 2.0;
+
+    let mut positions: Vec<TVec3<f32>> = [
+        [-1.0, t, 0.0],
+        [1.0, t, 0.0],
+        [-1.0, -t, 0.0],
+        [1.0, -t, 0.0],
+        [0.0, -1.0, t],
+        [0.0, 1.0, t],
+        [0.0, -1.0, -t],
+        [0.0, 1.0, -t],
+        [t, 0.0, -1.0],
+        [t, 0.0, 1.0],
+        [-t, 0.0, -1.0],
+        [-t, 0.0, 1.0],
+    ]
+    .iter()
+    .map(|&position| TVec3::from(position).normalize())
+    .collect();
+
+    let mut triangles: Vec<[usize; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Neighbouring triangles share the vertex in the middle of their edge
+        let mut midpoints: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut midpoint = |a: usize, b: usize| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push(((positions[a] + positions[b]) * 0.5).normalize());
+
+                positions.len() - 1
+            })
+        };
+
+        triangles = triangles
+            .iter()
+            .flat_map(|&[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut builder = MeshBuilder::default();
+
+    for &normal in &positions {
+        let u = 0.5 + normal.z.atan2(normal.x) / TAU;
+        let v = (-normal.y).clamp(-1.0, 1.0).acos() / PI;
+
+        builder
+            .vertices
+            .push(vertex(normal * radius, normal, [u, v]));
+    }
+
+    // Triangles crossing the seam where u wraps from 1 back to 0 get copies of their low corners
+    // moved past 1, so the texture isn't squashed back across the whole sphere
+    let mut wrapped: HashMap<usize, Index> = HashMap::new();
+
+    for corners in triangles {
+        let us = corners.map(|i| builder.vertices[i].uv[0]);
+        let crosses_seam = us.iter().cloned().fold(f32::MIN, f32::max)
+            - us.iter().cloned().fold(f32::MAX, f32::min)
+            > 0.5;
+
+        let [a, b, c] = corners.map(|i| {
+            if crosses_seam && builder.vertices[i].uv[0] < 0.5 {
+                *wrapped.entry(i).or_insert_with(|| {
+                    let mut v = builder.vertices[i];
+                    v.uv[0] += 1.0;
+                    builder.vertices.push(v);
+
+                    (builder.vertices.len() - 1) as Index
+                })
+            } else {
+                i as Index
+            }
+        });
+
+        builder.triangle(a, b, c);
+    }
+
+    builder.build()
+}
+
+/// A flat rectangle in the xz plane facing up, split into `subdivisions` quads along x and z
+pub fn plane(width: f32, depth: f32, subdivisions: [u32; 2]) -> Model {
+    let [columns, rows] = subdivisions;
+    assert!(columns >= 1 && rows >= 1);
+
+    let mut builder = MeshBuilder::default();
+
+    builder.surface(columns, rows, |column, row| {
+        let u = column as f32 / columns as f32;
+        let v = row as f32 / rows as f32;
+
+        vertex(
+            TVec3::new((u - 0.5) * width, 0.0, (v - 0.5) * depth),
+            TVec3::new(0.0, -1.0, 0.0),
+            [u, v],
+        )
+    });
+
+    builder.build()
+}
+
+/// A capped cylinder standing along y
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Model {
+    assert!(segments >= 3);
+
+    let mut builder = MeshBuilder::default();
+
+    builder.surface(segments, 1, |column, row| {
+        let u = column as f32 / segments as f32;
+        let normal = ring_direction(TAU * u);
+        let y = (row as f32 - 0.5) * height;
+
+        vertex(
+            normal * radius + TVec3::new(0.0, y, 0.0),
+            normal,
+            [u, row as f32],
+        )
+    });
+
+    builder.disc(-height * 0.5, radius, -1.0, segments);
+    builder.disc(height * 0.5, radius, 1.0, segments);
+
+    builder.build()
+}
+
+/// A capped cone standing along y with its point at the top
+pub fn cone(radius: f32, height: f32, segments: u32) -> Model {
+    assert!(segments >= 3);
+
+    let mut builder = MeshBuilder::default();
+
+    // The point has a copy for each segment, so each side keeps its own normal
+    builder.surface(segments, 1, |column, row| {
+        let u = column as f32 / segments as f32;
+        let direction = ring_direction(TAU * u);
+        let normal = (direction * height + TVec3::new(0.0, -radius, 0.0)).normalize();
+        let y = (row as f32 - 0.5) * height;
+
+        vertex(
+            direction * radius * row as f32 + TVec3::new(0.0, y, 0.0),
+            normal,
+            [u, row as f32],
+        )
+    });
+
+    builder.disc(height * 0.5, radius, 1.0, segments);
+
+    builder.build()
+}
+
+/// A ring lying in the xz plane. `major_radius` is from the centre to the middle of the tube, which
+/// is `minor_radius` thick and split into `sides` around its length
+pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32) -> Model {
+    assert!(segments >= 3 && sides >= 3);
+
+    let mut builder = MeshBuilder::default();
+
+    builder.surface(segments, sides, |column, row| {
+        let u = column as f32 / segments as f32;
+        let v = row as f32 / sides as f32;
+        let direction = ring_direction(TAU * u);
+        let (sin, cos) = (TAU * v).sin_cos();
+        let normal = direction * cos + TVec3::new(0.0, -sin, 0.0);
+
+        vertex(
+            direction * major_radius + normal * minor_radius,
+            normal,
+            [u, v],
+        )
+    });
+
+    builder.build()
+}
+
+/// A cylinder with hemispheres on each end, standing along y. `height` includes the hemispheres,
+/// and is at least the diameter. Each hemisphere has `rings` bands of latitude
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Model {
+    assert!(segments >= 3 && rings >= 1);
+
+    let middle = (height - radius * 2.0).max(0.0);
+    let length = PI * radius + middle;
+
+    let mut builder = MeshBuilder::default();
+
+    // The top hemisphere's last row and the bottom's first are the two ends of the cylinder
+    builder.surface(segments, rings * 2 + 1, |column, row| {
+        let u = column as f32 / segments as f32;
+        let (latitude, centre, distance) = if row <= rings {
+            let latitude = FRAC_PI_2 * row as f32 / rings as f32;
+
+            (latitude, -middle * 0.5, latitude * radius)
+        } else {
+            let latitude = FRAC_PI_2 * (1.0 + (row - rings - 1) as f32 / rings as f32);
+
+            (latitude, middle * 0.5, latitude * radius + middle)
+        };
+        let normal = sphere_normal(TAU * u, latitude);
+
+        vertex(
+            normal * radius + TVec3::new(0.0, centre, 0.0),
+            normal,
+            [u, distance / length],
+        )
+    });
+
+    builder.build()
+}
+
+fn vertex(position: TVec3<f32>, normal: TVec3<f32>, uv: [f32; 2]) -> Vertex {
+    Vertex::new(position.into(), normal.into()).with_uv(uv)
+}
+
+/// A point on the horizontal unit circle
+fn ring_direction(angle: f32) -> TVec3<f32> {
+    let (sin, cos) = angle.sin_cos();
+
+    TVec3::new(cos, 0.0, sin)
+}
+
+/// A point on the unit sphere, with `latitude` from 0 at the top to pi at the bottom
+fn sphere_normal(longitude: f32, latitude: f32) -> TVec3<f32> {
+    let (sin, cos) = latitude.sin_cos();
+
+    ring_direction(longitude) * sin + TVec3::new(0.0, -cos, 0.0)
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    vertices: Vec<Vertex>,
+    indices: Vec<Index>,
+}
+
+impl MeshBuilder {
+    /// Adds a triangle facing the way its vertex normals point. The corners are ordered to match
+    /// the cube, which the pipelines treat as front facing when seen from outside. Triangles
+    /// without area, such as those touching the poles of a sphere, are left out
+    fn triangle(self: &mut Self, a: Index, b: Index, c: Index) {
+        let corners = [a, b, c].map(|i| self.vertices[i as usize]);
+        let [pa, pb, pc] = corners.map(|v| TVec3::from(v.position));
+
+        let edges = [(pb - pa).norm(), (pc - pb).norm(), (pa - pc).norm()];
+        let longest = edges.iter().cloned().fold(0.0, f32::max);
+        if edges.iter().any(|&edge| edge <= longest * 1e-4) {
+            return;
+        }
+
+        let normal: TVec3<f32> = corners.iter().map(|v| TVec3::from(v.normal)).sum();
+
+        if (pb - pa).cross(&(pc - pa)).dot(&normal) < 0.0 {
+            self.indices.extend([a, b, c]);
+        } else {
+            self.indices.extend([a, c, b]);
+        }
+    }
+
+    /// A grid of `columns` by `rows` quads, with the vertex at each corner from `corner`
+    fn surface(self: &mut Self, columns: u32, rows: u32, corner: impl Fn(u32, u32) -> Vertex) {
+        let first = self.vertices.len() as Index;
+
+        for row in 0..=rows {
+            for column in 0..=columns {
+                self.vertices.push(corner(column, row));
+            }
+        }
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let top_left = first + row * (columns + 1) + column;
+                let bottom_left = top_left + columns + 1;
+
+                self.triangle(top_left, top_left + 1, bottom_left + 1);
+                self.triangle(top_left, bottom_left + 1, bottom_left);
+            }
+        }
+    }
+
+    /// A horizontal disc at height `y` facing along y in the direction of `facing`
+    fn disc(self: &mut Self, y: f32, radius: f32, facing: f32, segments: u32) {
+        let normal = TVec3::new(0.0, facing, 0.0);
+        let centre = self.vertices.len() as Index;
+
+        self.vertices
+            .push(vertex(TVec3::new(0.0, y, 0.0), normal, [0.5, 0.5]));
+
+        for i in 0..=segments {
+            let direction = ring_direction(TAU * i as f32 / segments as f32);
+
+            self.vertices.push(vertex(
+                direction * radius + TVec3::new(0.0, y, 0.0),
+                normal,
+                [0.5 + direction.x * 0.5, 0.5 + direction.z * 0.5],
+            ));
+        }
+
+        for i in 0..segments {
+            self.triangle(centre, centre + 1 + i, centre + 2 + i);
+        }
+    }
+
+    fn build(self: Self) -> Model {
+        Model::new(self.vertices, self.indices)
+    }
+}
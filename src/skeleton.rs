@@ -0,0 +1,201 @@
+use nalgebra_glm::{identity, Qua, TMat4, TVec3, TVec4};
+
+use std::collections::HashSet;
+
+use crate::animation::{Clip, Pose};
+use crate::error::SceneError;
+use crate::scene_graph::Trs;
+use crate::vertex::SkinVertex;
+
+#[derive(Clone, Debug)]
+pub struct Joint {
+    pub name: String,
+    /// Index of the parent joint in the same skeleton
+    pub parent: Option<usize>,
+    pub local: Trs,
+    /// Takes a vertex from model space into the joint's space in the bind pose
+    pub inverse_bind: TMat4<f32>,
+}
+
+/// A hierarchy of joints which deform a skinned mesh
+#[derive(Clone, Debug)]
+pub struct Skeleton {
+    joints: Vec<Joint>,
+    root: TMat4<f32>,
+}
+
+#[allow(dead_code)]
+impl Skeleton {
+    /// `root` places the joints without a parent, for skeletons under a transformed node. Fails
+    /// if a parent is out of range or the parents form a cycle
+    pub fn new(joints: Vec<Joint>, root: TMat4<f32>) -> Result<Self, SceneError> {
+        // Joints known to lead up to a root without a cycle
+        let mut rooted = vec![false; joints.len()];
+
+        for i in 0..joints.len() {
+            let mut visited = HashSet::new();
+            let mut joint = i;
+
+            while !rooted[joint] {
+                if !visited.insert(joint) {
+                    return Err(SceneError::InvalidSkeleton(format!(
+                        "joint {} is its own ancestor",
+                        joints[joint].name
+                    )));
+                }
+
+                match joints[joint].parent {
+                    Some(parent) if parent >= joints.len() => {
+                        return Err(SceneError::InvalidSkeleton(format!(
+                            "joint {} has parent {} out of range",
+                            joints[joint].name, parent
+                        )));
+                    }
+                    Some(parent) => joint = parent,
+                    None => break,
+                }
+            }
+
+            for joint in visited {
+                rooted[joint] = true;
+            }
+        }
+
+        Ok(Self { joints, root })
+    }
+
+    pub fn len(self: &Self) -> usize {
+        self.joints.len()
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        self.joints.is_empty()
+    }
+
+    pub fn joints(self: &Self) -> &[Joint] {
+        &self.joints
+    }
+
+    pub fn find(self: &Self, name: &str) -> Option<usize> {
+        self.joints.iter().position(|joint| joint.name == name)
+    }
+
+    pub fn local(self: &Self, joint: usize) -> Trs {
+        self.joints[joint].local
+    }
+
+    pub fn set_local(self: &mut Self, joint: usize, local: Trs) {
+        self.joints[joint].local = local;
+    }
+
+    /// Poses every joint the clip animates, at `time` seconds of playback
+    pub fn apply_clip(self: &mut Self, clip: &Clip, time: f32) {
+        for joint in &mut self.joints {
+            let mut pose = Pose::default();
+            clip.pose(&joint.name, time, &mut pose);
+
+            if let Some(translation) = pose.translation {
+                joint.local.translation = TVec3::from(translation);
+            }
+            if let Some(rotation) = pose.rotation {
+                joint.local.rotation = Qua::from_vector(TVec4::from(rotation));
+            }
+            if let Some(scale) = pose.scale {
+                joint.local.scale = TVec3::from(scale);
+            }
+        }
+    }
+
+    /// Each joint's transform in model space for the current pose
+    pub fn world_matrices(self: &Self) -> Vec<TMat4<f32>> {
+        let mut worlds: Vec<Option<TMat4<f32>>> = vec![None; self.joints.len()];
+
+        for i in 0..self.joints.len() {
+            if worlds[i].is_some() {
+                continue;
+            }
+
+            // The joints up to the nearest resolved ancestor, resolved from the top down. `new`
+            // rules out cycles, so this ends
+            let mut chain = vec![i];
+            while let Some(parent) = self.joints[*chain.last().unwrap()].parent {
+                if worlds[parent].is_some() {
+                    break;
+                }
+                chain.push(parent);
+            }
+
+            for &joint in chain.iter().rev() {
+                let parent = match self.joints[joint].parent {
+                    Some(parent) => worlds[parent].unwrap(),
+                    None => self.root,
+                };
+                worlds[joint] = Some(parent * self.joints[joint].local.matrix());
+            }
+        }
+
+        worlds.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// The matrices the vertex shader blends, taking bind pose vertices to the current pose
+    pub fn joint_matrices(self: &Self) -> Vec<TMat4<f32>> {
+        self.world_matrices()
+            .into_iter()
+            .zip(&self.joints)
+            .map(|(world, joint)| world * joint.inverse_bind)
+            .collect()
+    }
+}
+
+/// A skeleton and the per vertex weights binding a mesh to it
+#[derive(Clone, Debug)]
+pub struct Skin {
+    skeleton: Skeleton,
+    vertices: Vec<SkinVertex>,
+}
+
+impl Skin {
+    /// Panics if a vertex refers to a joint the skeleton doesn't have
+    pub fn new(skeleton: Skeleton, vertices: Vec<SkinVertex>) -> Self {
+        for v in &vertices {
+            for (&joint, &weight) in v.joints.iter().zip(&v.weights) {
+                assert!(
+                    weight == 0.0 || (joint as usize) < skeleton.len(),
+                    "joint {} is out of range",
+                    joint
+                );
+            }
+        }
+
+        Self { skeleton, vertices }
+    }
+
+    pub fn skeleton(self: &Self) -> &Skeleton {
+        &self.skeleton
+    }
+
+    pub fn skeleton_mut(self: &mut Self) -> &mut Skeleton {
+        &mut self.skeleton
+    }
+
+    pub fn vertices(self: &Self) -> Vec<SkinVertex> {
+        self.vertices.clone()
+    }
+}
+
+/// The weighted blend of joint matrices for one vertex, or the identity if it has no weight
+pub fn skin_matrix(vertex: &SkinVertex, joint_matrices: &[TMat4<f32>]) -> TMat4<f32> {
+    let total: f32 = vertex.weights.iter().sum();
+    if total == 0.0 {
+        return identity();
+    }
+
+    vertex
+        .joints
+        .iter()
+        .zip(&vertex.weights)
+        .filter(|(_, &weight)| weight != 0.0)
+        .fold(TMat4::zeros(), |sum, (&joint, &weight)| {
+            sum + joint_matrices[joint as usize] * weight
+        })
+}
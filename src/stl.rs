@@ -0,0 +1,170 @@
+use nalgebra_glm::TVec3;
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::SceneError;
+use crate::geometry::{reverse_triangle, Winding};
+use crate::model::Model;
+use crate::obj::parse_vec3;
+use crate::vertex::{CompactVec3, Index, Vertex};
+
+const HEADER_SIZE: usize = 80;
+const TRIANGLE_SIZE: usize = 50;
+
+/// Loads a binary or ASCII STL file
+pub fn load_stl(path: impl AsRef<Path>) -> Result<Model, SceneError> {
+    parse_stl(&fs::read(path)?)
+}
+
+/// Parses STL data, telling binary and ASCII apart by whether the size matches the triangle count
+/// of a binary file. Each triangle gets its own corners with the facet's normal, so use
+/// `geometry::weld` to share them. Fails if there are no facets
+pub fn parse_stl(data: &[u8]) -> Result<Model, SceneError> {
+    let binary_count = data
+        .get(HEADER_SIZE..HEADER_SIZE + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize);
+
+    let model = match binary_count {
+        Some(count) if data.len() == HEADER_SIZE + 4 + count * TRIANGLE_SIZE => {
+            parse_binary(&data[HEADER_SIZE + 4..])
+        }
+        _ if data.starts_with(b"solid") => {
+            let source = std::str::from_utf8(data)
+                .map_err(|_| SceneError::InvalidMesh("ASCII STL isn't text".to_string()))?;
+
+            parse_ascii(source)?
+        }
+        _ => {
+            return Err(SceneError::InvalidMesh(
+                "STL file's size doesn't match its triangle count".to_string(),
+            ))
+        }
+    };
+
+    if model.indices().is_empty() {
+        return Err(SceneError::InvalidMesh("file has no faces".to_string()));
+    }
+
+    Ok(model)
+}
+
+/// Writes a binary STL file of the model's triangles in its own space
+pub fn save_stl(model: &Model, path: impl AsRef<Path>) -> Result<(), SceneError> {
+    Ok(fs::write(path, write_stl(model))?)
+}
+
+/// The binary STL data for a model. Each facet's normal is the average of its corners' normals,
+/// as STL has no vertex normals
+pub fn write_stl(model: &Model) -> Vec<u8> {
+    let vertices = model.vertices();
+    let indices = model.indices();
+    let triangles = indices.chunks_exact(3);
+
+    // ASCII files start with "solid", so the header mustn't
+    let mut data = b"binary STL".to_vec();
+    data.resize(HEADER_SIZE, 0);
+    data.extend((triangles.len() as u32).to_le_bytes());
+
+    for triangle in triangles {
+        let corners =
+            reverse_triangle([triangle[0], triangle[1], triangle[2]]).map(|i| vertices[i as usize]);
+        let normal = facet_normal(
+            corners.iter().map(|v| TVec3::from(v.normal)).sum(),
+            corners.map(|v| v.position),
+        );
+
+        for value in normal
+            .iter()
+            .chain(corners.iter().flat_map(|v| &v.position))
+        {
+            data.extend(value.to_le_bytes());
+        }
+        data.extend(0u16.to_le_bytes());
+    }
+
+    data
+}
+
+fn parse_binary(data: &[u8]) -> Model {
+    let mut vertices: Vec<Vertex> = Vec::new();
+
+    for triangle in data.chunks_exact(TRIANGLE_SIZE) {
+        let value = |i: usize| f32::from_le_bytes(triangle[i * 4..i * 4 + 4].try_into().unwrap());
+        let vec3 = |i: usize| [value(i), value(i + 1), value(i + 2)];
+
+        add_facet(&mut vertices, vec3(0), [vec3(3), vec3(6), vec3(9)]);
+    }
+
+    let indices = (0..vertices.len() as Index).collect();
+
+    Model::new(vertices, indices)
+}
+
+/// Reads `facet normal`, `vertex` and `endfacet` lines, splitting facets with more than three
+/// vertices into triangle fans
+fn parse_ascii(source: &str) -> Result<Model, SceneError> {
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut normal: CompactVec3 = [0.0; 3];
+    let mut corners: Vec<CompactVec3> = Vec::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |message: String| SceneError::Parse {
+            line: line_number,
+            message,
+        };
+
+        let mut parts = line.split_whitespace();
+
+        match parts.next() {
+            Some("facet") => {
+                if parts.next() != Some("normal") {
+                    return Err(error("expected facet normal".to_string()));
+                }
+
+                normal = parse_vec3(parts).map_err(error)?;
+                corners.clear();
+            }
+            Some("vertex") => corners.push(parse_vec3(parts).map_err(error)?),
+            Some("endfacet") => {
+                if corners.len() < 3 {
+                    return Err(error("facet has fewer than 3 vertices".to_string()));
+                }
+
+                for j in 1..corners.len() - 1 {
+                    add_facet(
+                        &mut vertices,
+                        normal,
+                        [corners[0], corners[j], corners[j + 1]],
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let indices = (0..vertices.len() as Index).collect();
+
+    Ok(Model::new(vertices, indices))
+}
+
+/// Adds a facet's corners, given in the file's winding
+fn add_facet(vertices: &mut Vec<Vertex>, normal: CompactVec3, corners: [CompactVec3; 3]) {
+    let normal = facet_normal(TVec3::from(normal), corners);
+
+    vertices.extend(reverse_triangle(corners).map(|position| Vertex::new(position, normal)));
+}
+
+/// `normal` made unit length, or worked out from the file's corners if it's zero
+fn facet_normal(normal: TVec3<f32>, corners: [CompactVec3; 3]) -> CompactVec3 {
+    normal
+        .try_normalize(f32::EPSILON)
+        .or_else(|| {
+            Winding::CounterClockwise
+                .face_normal(corners.map(TVec3::from))
+                .try_normalize(f32::EPSILON)
+        })
+        .unwrap_or_else(TVec3::zeros)
+        .into()
+}
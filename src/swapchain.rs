@@ -0,0 +1,332 @@
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::device::{Device, DeviceOwned};
+use vulkano::format::Format;
+use vulkano::image::view::{ImageView, ImageViewAbstract};
+use vulkano::image::{AttachmentImage, ImageAccess, ImageUsage};
+use vulkano::instance::Instance;
+use vulkano::render_pass::{Framebuffer, RenderPass};
+use vulkano::swapchain::{
+    PresentMode, Surface, SurfaceApi, SurfaceCreationError, Swapchain, SwapchainCreateInfo,
+};
+use vulkano::{OomError, VulkanObject};
+
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::Arc;
+
+use crate::debug::set_name;
+use crate::error::RenderError;
+use crate::pipeline_commands::{get_framebuffers, get_render_pass, new_attachment_image};
+
+/// The G-buffer attachments and the framebuffers which bind them to the output images
+pub struct Attachments {
+    depth_buffer: Arc<ImageView<AttachmentImage>>,
+    normal_buffer: Arc<ImageView<AttachmentImage>>,
+    colour_buffer: Arc<ImageView<AttachmentImage>>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+}
+
+impl Attachments {
+    pub fn new(
+        device: Arc<Device>,
+        render_pass: Arc<RenderPass>,
+        images: Vec<Arc<dyn ImageViewAbstract>>,
+        dimensions: winit::dpi::PhysicalSize<u32>,
+    ) -> Result<Self, RenderError> {
+        let depth_buffer = new_attachment_image(device.clone(), dimensions, Format::D16_UNORM)?;
+        let normal_buffer =
+            new_attachment_image(device.clone(), dimensions, Format::R16G16B16A16_SFLOAT)?;
+        let colour_buffer =
+            new_attachment_image(device, dimensions, Format::A2B10G10R10_UNORM_PACK32)?;
+
+        let framebuffers = get_framebuffers(
+            images,
+            render_pass,
+            colour_buffer.clone(),
+            normal_buffer.clone(),
+            depth_buffer.clone(),
+        )?;
+
+        set_name(depth_buffer.image().inner().image, "G-buffer depth");
+        set_name(normal_buffer.image().inner().image, "G-buffer normals");
+        set_name(colour_buffer.image().inner().image, "G-buffer colour");
+        for (i, framebuffer) in framebuffers.iter().enumerate() {
+            set_name(&**framebuffer, &format!("Framebuffer {}", i));
+        }
+
+        Ok(Self {
+            depth_buffer,
+            normal_buffer,
+            colour_buffer,
+            framebuffers,
+        })
+    }
+
+    pub fn depth_buffer(self: &Self) -> Arc<ImageView<AttachmentImage>> {
+        self.depth_buffer.clone()
+    }
+
+    pub fn normal_buffer(self: &Self) -> Arc<ImageView<AttachmentImage>> {
+        self.normal_buffer.clone()
+    }
+
+    pub fn colour_buffer(self: &Self) -> Arc<ImageView<AttachmentImage>> {
+        self.colour_buffer.clone()
+    }
+
+    pub fn framebuffer(self: &Self, image_i: usize) -> Arc<Framebuffer> {
+        self.framebuffers[image_i].clone()
+    }
+
+    pub fn framebuffer_count(self: &Self) -> usize {
+        self.framebuffers.len()
+    }
+}
+
+/// Everything which depends on the swapchain, always rebuilt together so the attachments can never
+/// be a different size or format to the swapchain images
+pub struct SwapchainResources<W> {
+    swapchain: Arc<Swapchain<W>>,
+    render_pass: Arc<RenderPass>,
+    attachments: Attachments,
+}
+
+impl<W> SwapchainResources<W>
+where
+    W: Send + Sync + 'static,
+{
+    /// Returns `None` while the surface has no area, such as when the window is minimised. Falls
+    /// back to a supported present mode if the requested one isn't available
+    pub fn new(
+        device: Arc<Device>,
+        surface: Arc<Surface<W>>,
+        dimensions: winit::dpi::PhysicalSize<u32>,
+        present_mode: PresentMode,
+    ) -> Result<Option<Self>, RenderError> {
+        Self::build(device, surface, dimensions, Some(present_mode), None)
+    }
+
+    /// Builds a replacement from the current state of the surface, reusing the render pass if the
+    /// surface format hasn't changed. Returns `None` while the surface has no area
+    pub fn recreate(
+        self: &Self,
+        dimensions: winit::dpi::PhysicalSize<u32>,
+    ) -> Result<Option<Self>, RenderError> {
+        Self::build(
+            self.swapchain.device().clone(),
+            self.swapchain.surface().clone(),
+            dimensions,
+            None,
+            Some(self),
+        )
+    }
+
+    fn build(
+        device: Arc<Device>,
+        surface: Arc<Surface<W>>,
+        dimensions: winit::dpi::PhysicalSize<u32>,
+        present_mode: Option<PresentMode>,
+        old: Option<&Self>,
+    ) -> Result<Option<Self>, RenderError> {
+        let physical_device = device.physical_device();
+
+        let capabilities = physical_device.surface_capabilities(&surface, Default::default())?;
+
+        // Window surfaces dictate their extent, others use whatever size was asked for
+        let image_extent = match capabilities.current_extent {
+            Some(extent) => extent,
+            None if dimensions.width == 0 || dimensions.height == 0 => return Ok(None),
+            None => [
+                dimensions.width.clamp(
+                    capabilities.min_image_extent[0],
+                    capabilities.max_image_extent[0],
+                ),
+                dimensions.height.clamp(
+                    capabilities.min_image_extent[1],
+                    capabilities.max_image_extent[1],
+                ),
+            ],
+        };
+
+        if image_extent.contains(&0) {
+            return Ok(None);
+        }
+
+        let image_format = physical_device.surface_formats(&surface, Default::default())?[0].0;
+
+        let (swapchain, images) = match old {
+            Some(old) => old.swapchain.recreate(SwapchainCreateInfo {
+                image_extent,
+                image_format: Some(image_format),
+                ..old.swapchain.create_info()
+            })?,
+            None => {
+                let composite_alpha = capabilities
+                    .supported_composite_alpha
+                    .iter()
+                    .next()
+                    .unwrap();
+
+                let present_mode = choose_present_mode(
+                    physical_device,
+                    &surface,
+                    present_mode.unwrap_or(PresentMode::Fifo),
+                )?;
+
+                // One more than the minimum so there's always an image free to render into
+                let mut min_image_count = capabilities.min_image_count + 1;
+                if let Some(max_image_count) = capabilities.max_image_count {
+                    min_image_count = min_image_count.min(max_image_count);
+                }
+
+                // Lets frames be copied out for capture where the surface allows it
+                let image_usage = ImageUsage {
+                    transfer_source: capabilities.supported_usage_flags.transfer_source,
+                    ..ImageUsage::color_attachment()
+                };
+
+                Swapchain::new(
+                    device.clone(),
+                    surface,
+                    SwapchainCreateInfo {
+                        min_image_count,
+                        image_format: Some(image_format),
+                        image_extent,
+                        image_usage,
+                        composite_alpha,
+                        present_mode,
+                        ..Default::default()
+                    },
+                )?
+            }
+        };
+
+        // Pipelines are built against the render pass, so only replace it if the format changed
+        let render_pass = match old {
+            Some(old) if old.swapchain.image_format() == swapchain.image_format() => {
+                old.render_pass.clone()
+            }
+            _ => get_render_pass(device.clone(), swapchain.image_format())?,
+        };
+
+        let images = images
+            .into_iter()
+            .map(|image| Ok(ImageView::new_default(image)? as _))
+            .collect::<Result<Vec<_>, RenderError>>()?;
+
+        let attachments = Attachments::new(
+            device,
+            render_pass.clone(),
+            images,
+            winit::dpi::PhysicalSize::new(image_extent[0], image_extent[1]),
+        )?;
+
+        Ok(Some(Self {
+            swapchain,
+            render_pass,
+            attachments,
+        }))
+    }
+
+    pub fn swapchain(self: &Self) -> Arc<Swapchain<W>> {
+        self.swapchain.clone()
+    }
+
+    pub fn render_pass(self: &Self) -> Arc<RenderPass> {
+        self.render_pass.clone()
+    }
+
+    pub fn attachments(self: &Self) -> &Attachments {
+        &self.attachments
+    }
+
+    pub fn dimensions(self: &Self) -> winit::dpi::PhysicalSize<u32> {
+        let [width, height] = self.swapchain.image_extent();
+
+        winit::dpi::PhysicalSize::new(width, height)
+    }
+
+    pub fn image_count(self: &Self) -> usize {
+        self.attachments.framebuffer_count()
+    }
+
+    pub fn present_mode(self: &Self) -> PresentMode {
+        self.swapchain.present_mode()
+    }
+}
+
+/// Picks the requested present mode, or the closest supported one. FIFO is always supported
+fn choose_present_mode<W>(
+    physical_device: PhysicalDevice,
+    surface: &Surface<W>,
+    requested: PresentMode,
+) -> Result<PresentMode, RenderError> {
+    let supported = physical_device
+        .surface_present_modes(surface)?
+        .collect::<Vec<_>>();
+
+    let fallbacks: &[PresentMode] = match requested {
+        PresentMode::Immediate => &[PresentMode::Immediate, PresentMode::Mailbox],
+        PresentMode::FifoRelaxed => &[PresentMode::FifoRelaxed],
+        PresentMode::Mailbox => &[PresentMode::Mailbox],
+        _ => &[],
+    };
+
+    let present_mode = fallbacks
+        .iter()
+        .copied()
+        .find(|mode| supported.contains(mode))
+        .unwrap_or(PresentMode::Fifo);
+
+    if present_mode != requested {
+        log::warn!(
+            "Present mode {:?} not supported, using {:?}",
+            requested,
+            present_mode
+        );
+    }
+
+    Ok(present_mode)
+}
+
+/// Creates a surface which isn't tied to a window, so swapchains can be tested without a display.
+/// The instance must have `ext_headless_surface` enabled
+pub fn create_headless_surface(instance: Arc<Instance>) -> Result<Arc<Surface<()>>, RenderError> {
+    if !instance.enabled_extensions().ext_headless_surface {
+        return Err(SurfaceCreationError::MissingExtension {
+            name: "VK_EXT_headless_surface",
+        }
+        .into());
+    }
+
+    let create_info = ash::vk::HeadlessSurfaceCreateInfoEXT::default();
+
+    let handle = unsafe {
+        let mut output = MaybeUninit::uninit();
+        let result = instance
+            .fns()
+            .ext_headless_surface
+            .create_headless_surface_ext(
+                instance.internal_object(),
+                &create_info,
+                ptr::null(),
+                output.as_mut_ptr(),
+            );
+
+        // These are the only errors the spec allows
+        match result {
+            ash::vk::Result::SUCCESS => (),
+            ash::vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+                return Err(SurfaceCreationError::OomError(OomError::OutOfDeviceMemory).into())
+            }
+            _ => return Err(SurfaceCreationError::OomError(OomError::OutOfHostMemory).into()),
+        }
+
+        output.assume_init()
+    };
+
+    // Vulkano has no headless surface api, the api is only checked for Win32 full-screen exclusivity
+    Ok(Arc::new(unsafe {
+        Surface::from_raw_surface(instance, handle, SurfaceApi::DisplayPlane, ())
+    }))
+}
@@ -0,0 +1,125 @@
+use crate::font::{self, GLYPH_SIZE, SOLID};
+use crate::vertex::TextVertex;
+
+/// A screen space quad in pixels from the top left of the output
+#[derive(Clone, Copy, Debug)]
+struct Quad {
+    min: [f32; 2],
+    max: [f32; 2],
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+    colour: [f32; 4],
+}
+
+/// Collects text and rectangles during a frame, which the renderer draws over everything else and
+/// then clears
+#[derive(Clone, Default)]
+pub struct TextBatch {
+    quads: Vec<Quad>,
+}
+
+impl TextBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws text with its top left corner at `position`, with each glyph `scale` times its
+    /// 8 pixel size. Returns the position below the last line
+    pub fn text(
+        self: &mut Self,
+        position: [f32; 2],
+        scale: f32,
+        colour: [f32; 4],
+        text: &str,
+    ) -> [f32; 2] {
+        let size = GLYPH_SIZE as f32 * scale;
+        let mut cursor = position;
+
+        for c in text.chars() {
+            if c == '\n' {
+                cursor = [position[0], cursor[1] + size];
+
+                continue;
+            }
+
+            if c != ' ' {
+                let (uv_min, uv_max) = font::glyph_uv(c);
+
+                self.quads.push(Quad {
+                    min: cursor,
+                    max: [cursor[0] + size, cursor[1] + size],
+                    uv_min,
+                    uv_max,
+                    colour,
+                });
+            }
+
+            cursor[0] += size;
+        }
+
+        [position[0], cursor[1] + size]
+    }
+
+    /// A filled rectangle between two corners in pixels
+    pub fn rect(self: &mut Self, min: [f32; 2], max: [f32; 2], colour: [f32; 4]) {
+        let (uv_min, uv_max) = font::glyph_uv(SOLID);
+
+        // Sample the middle of the solid glyph so filtering can't pick up its neighbours
+        let centre = [(uv_min[0] + uv_max[0]) * 0.5, (uv_min[1] + uv_max[1]) * 0.5];
+
+        self.quads.push(Quad {
+            min,
+            max,
+            uv_min: centre,
+            uv_max: centre,
+            colour,
+        });
+    }
+
+    /// A triangle list of every quad, converted to normalised device coordinates for an output of
+    /// `dimensions`
+    pub fn vertices(self: &Self, dimensions: winit::dpi::PhysicalSize<u32>) -> Vec<TextVertex> {
+        let to_ndc = |[x, y]: [f32; 2]| {
+            [
+                x / dimensions.width as f32 * 2.0 - 1.0,
+                y / dimensions.height as f32 * 2.0 - 1.0,
+            ]
+        };
+
+        self.quads
+            .iter()
+            .flat_map(|quad| {
+                let corner = |x: bool, y: bool| {
+                    TextVertex::new(
+                        to_ndc([
+                            if x { quad.max[0] } else { quad.min[0] },
+                            if y { quad.max[1] } else { quad.min[1] },
+                        ]),
+                        [
+                            if x { quad.uv_max[0] } else { quad.uv_min[0] },
+                            if y { quad.uv_max[1] } else { quad.uv_min[1] },
+                        ],
+                        quad.colour,
+                    )
+                };
+
+                [
+                    corner(false, false),
+                    corner(true, false),
+                    corner(true, true),
+                    corner(false, false),
+                    corner(true, true),
+                    corner(false, true),
+                ]
+            })
+            .collect()
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        self.quads.is_empty()
+    }
+
+    pub fn clear(self: &mut Self) {
+        self.quads.clear();
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use crate::error::SceneError;
+
+/// A decoded image as 8 bit RGBA rows from the top, ready to upload
+#[derive(Clone, Debug, PartialEq)]
+pub struct Texture {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+#[allow(dead_code)]
+impl Texture {
+    /// Panics if there aren't four bytes for every pixel
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), (width * height * 4) as usize);
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(self: &Self) -> u32 {
+        self.width
+    }
+
+    pub fn height(self: &Self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(self: &Self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+/// Loads any image format the image crate can decode, converted to RGBA
+pub fn load_texture(path: impl AsRef<Path>) -> Result<Texture, SceneError> {
+    let image = image::open(path)?.to_rgba8();
+
+    Ok(Texture::new(
+        image.width(),
+        image.height(),
+        image.into_raw(),
+    ))
+}
@@ -4,19 +4,148 @@ pub type CompactVec3 = [f32; 3];
 pub type Index = u32;
 
 #[repr(C)]
-#[derive(Debug, Default, Copy, Clone, Zeroable, Pod)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Zeroable, Pod)]
 pub struct Vertex {
     pub position: CompactVec3,
     pub normal: CompactVec3,
+    pub colour: CompactVec3,
+    /// Texture coordinates, zero if the mesh doesn't have any
+    pub uv: [f32; 2],
 }
 
 impl Vertex {
+    /// A white vertex
+    pub const fn new(position: CompactVec3, normal: CompactVec3) -> Self {
+        Vertex {
+            position,
+            normal,
+            colour: [1.0, 1.0, 1.0],
+            uv: [0.0, 0.0],
+        }
+    }
+
+    pub const fn with_colour(self: Self, colour: CompactVec3) -> Self {
+        Vertex { colour, ..self }
+    }
+
+    pub const fn with_uv(self: Self, uv: [f32; 2]) -> Self {
+        Vertex { uv, ..self }
+    }
+}
+
+vulkano::impl_vertex!(Vertex, position, normal, colour, uv);
+
+/// The joints which move a vertex and how much each one counts, bound as a second vertex buffer
+/// alongside `Vertex`. A vertex with no weight isn't skinned
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Zeroable, Pod)]
+pub struct SkinVertex {
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+impl SkinVertex {
+    pub const fn new(joints: [u32; 4], weights: [f32; 4]) -> Self {
+        SkinVertex { joints, weights }
+    }
+}
+
+vulkano::impl_vertex!(SkinVertex, joints, weights);
+
+/// Where a vertex's morph target deltas and their weights start, bound as a third vertex buffer.
+/// Each vertex has one delta per target, stored together
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Zeroable, Pod)]
+pub struct MorphVertex {
+    pub first_delta: u32,
+    pub first_weight: u32,
+    pub target_count: u32,
+}
+
+impl MorphVertex {
+    pub const fn new(first_delta: u32, first_weight: u32, target_count: u32) -> Self {
+        MorphVertex {
+            first_delta,
+            first_weight,
+            target_count,
+        }
+    }
+}
+
+vulkano::impl_vertex!(MorphVertex, first_delta, first_weight, target_count);
+
+/// How far one morph target moves a vertex, padded to match the shader's storage buffer layout
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Zeroable, Pod)]
+pub struct MorphDelta {
+    pub position: [f32; 4],
+    pub normal: [f32; 4],
+}
+
+impl MorphDelta {
     pub const fn new(position: CompactVec3, normal: CompactVec3) -> Self {
-        Vertex { position, normal }
+        MorphDelta {
+            position: [position[0], position[1], position[2], 0.0],
+            normal: [normal[0], normal[1], normal[2], 0.0],
+        }
+    }
+}
+
+/// A corner of a triangle drawn on its own, so the wireframe shader knows where the edges are
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Zeroable, Pod)]
+pub struct WireVertex {
+    pub position: CompactVec3,
+    pub barycentric: CompactVec3,
+}
+
+impl WireVertex {
+    pub const fn new(position: CompactVec3, barycentric: CompactVec3) -> Self {
+        WireVertex {
+            position,
+            barycentric,
+        }
+    }
+}
+
+vulkano::impl_vertex!(WireVertex, position, barycentric);
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Zeroable, Pod)]
+pub struct LineVertex {
+    pub position: CompactVec3,
+    pub colour: CompactVec3,
+}
+
+impl LineVertex {
+    pub const fn new(position: CompactVec3, colour: CompactVec3) -> Self {
+        LineVertex { position, colour }
+    }
+}
+
+vulkano::impl_vertex!(LineVertex, position, colour);
+
+/// A corner of a textured screen space quad, with the position already in normalised device
+/// coordinates
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, Zeroable, Pod)]
+pub struct TextVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub colour: [f32; 4],
+}
+
+impl TextVertex {
+    pub const fn new(position: [f32; 2], uv: [f32; 2], colour: [f32; 4]) -> Self {
+        TextVertex {
+            position,
+            uv,
+            colour,
+        }
     }
 }
 
-vulkano::impl_vertex!(Vertex, position, normal);
+vulkano::impl_vertex!(TextVertex, position, uv, colour);
 
 pub const CUBE_VERTICES: [Vertex; 4 * 6] = [
     // Front Face
@@ -0,0 +1,157 @@
+use graphics::animation::{Channel, Clip, Interpolation, Keyframe, LoopMode, Pose, Track};
+
+const EPSILON: f32 = 1e-5;
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() <= EPSILON
+}
+
+fn track(interpolation: Interpolation) -> Track<f32> {
+    Track::new(
+        interpolation,
+        vec![
+            Keyframe::new(0.0, 0.0),
+            Keyframe::new(1.0, 2.0),
+            Keyframe::new(2.0, 0.0),
+        ],
+    )
+}
+
+#[test]
+fn step_holds_each_keyframe() {
+    let track = track(Interpolation::Step);
+
+    assert_eq!(track.sample(0.0), Some(0.0));
+    assert_eq!(track.sample(0.99), Some(0.0));
+    assert_eq!(track.sample(1.0), Some(2.0));
+    assert_eq!(track.sample(1.5), Some(2.0));
+}
+
+#[test]
+fn linear_interpolates_between_keyframes() {
+    let track = track(Interpolation::Linear);
+
+    assert_eq!(track.sample(0.25), Some(0.5));
+    assert_eq!(track.sample(1.5), Some(1.0));
+
+    // Outside the keyframes the ends are held
+    assert_eq!(track.sample(-1.0), Some(0.0));
+    assert_eq!(track.sample(3.0), Some(0.0));
+    assert_eq!(
+        Track::<f32>::new(Interpolation::Linear, vec![]).sample(0.0),
+        None
+    );
+}
+
+#[test]
+fn cubic_splines_pass_through_keyframes_with_their_tangents() {
+    let track = track(Interpolation::CubicSpline);
+
+    for (time, value) in [(0.0, 0.0), (1.0, 2.0), (2.0, 0.0)] {
+        assert!(close(track.sample(time).unwrap(), value));
+    }
+
+    // The peak has a flat Catmull-Rom tangent, so the curve overshoots linear on the way up
+    let value = track.sample(0.5).unwrap();
+    assert!(value > 1.0 && value < 2.0, "{}", value);
+    assert!(close(value, track.sample(1.5).unwrap()));
+
+    // Explicit tangents: a straight line with slope 3 stays on it
+    let line = Track::new(
+        Interpolation::CubicSpline,
+        vec![
+            Keyframe {
+                out_tangent: Some(3.0),
+                ..Keyframe::new(0.0, 0.0)
+            },
+            Keyframe {
+                in_tangent: Some(3.0),
+                ..Keyframe::new(2.0, 6.0)
+            },
+        ],
+    );
+    assert!(close(line.sample(0.5).unwrap(), 1.5));
+}
+
+#[test]
+fn rotations_interpolate_along_the_shortest_arc() {
+    let s = std::f32::consts::FRAC_1_SQRT_2;
+    let track = Track::new(
+        Interpolation::Linear,
+        vec![
+            Keyframe::new(0.0, [0.0, 0.0, 0.0, 1.0]),
+            // The same quarter turn about z as (0, 0, s, s), written with the opposite sign
+            Keyframe::new(1.0, [0.0, 0.0, -s, -s]),
+        ],
+    );
+
+    let [x, y, z, w] = track.sample(0.5).unwrap();
+    let eighth = std::f32::consts::FRAC_PI_8;
+
+    assert!(close(x, 0.0) && close(y, 0.0));
+    assert!(close(z, eighth.sin()) && close(w, eighth.cos()));
+}
+
+#[test]
+fn loop_modes_wrap_time() {
+    assert_eq!(LoopMode::Once.wrap(-1.0, 2.0), 0.0);
+    assert_eq!(LoopMode::Once.wrap(3.0, 2.0), 2.0);
+
+    assert_eq!(LoopMode::Loop.wrap(2.5, 2.0), 0.5);
+    assert_eq!(LoopMode::Loop.wrap(-0.5, 2.0), 1.5);
+
+    assert_eq!(LoopMode::PingPong.wrap(1.5, 2.0), 1.5);
+    assert_eq!(LoopMode::PingPong.wrap(2.5, 2.0), 1.5);
+    assert_eq!(LoopMode::PingPong.wrap(4.5, 2.0), 0.5);
+
+    for mode in [LoopMode::Once, LoopMode::Loop, LoopMode::PingPong] {
+        assert_eq!(mode.wrap(1.0, 0.0), 0.0);
+    }
+}
+
+#[test]
+fn clips_apply_speed_and_looping() {
+    let mut clip = Clip::new(
+        "bounce",
+        LoopMode::PingPong,
+        vec![Channel::LightIntensity {
+            target: "lamp".to_string(),
+            track: track(Interpolation::Linear),
+        }],
+    );
+    clip.speed = 2.0;
+
+    let mut pose = Pose::default();
+    clip.pose("lamp", 1.25, &mut pose);
+
+    // 2.5s into a 2s clip playing back and forth is 1.5s in
+    assert_eq!(pose.light_intensity, Some(1.0));
+
+    let mut pose = Pose::default();
+    clip.pose("other", 1.25, &mut pose);
+    assert_eq!(pose, Pose::default());
+}
+
+#[test]
+fn loaded_keyframes_are_sorted() {
+    let track: Track<f32> = serde_json::from_str(
+        r#"{"keyframes": [
+            {"time": 2.0, "value": 0.0},
+            {"time": 0.0, "value": 0.0},
+            {"time": 1.0, "value": 2.0}
+        ]}"#,
+    )
+    .unwrap();
+
+    assert_eq!(track, self::track(Interpolation::Linear));
+    assert_eq!(track.sample(0.5), Some(1.0));
+}
+
+#[test]
+fn loaded_keyframes_need_finite_times() {
+    // Too large for an f32
+    let result =
+        serde_json::from_str::<Track<f32>>(r#"{"keyframes": [{"time": 1e300, "value": 0.0}]}"#);
+
+    assert!(result.is_err());
+}
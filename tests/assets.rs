@@ -0,0 +1,89 @@
+use std::env;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use graphics::assets::{AssetChange, AssetRegistry};
+use graphics::scene_file::SceneFile;
+
+#[test]
+fn scene_files_load_each_texture_once() {
+    let directory = env::temp_dir().join(format!("graphics-assets-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let pixels = [255, 0, 0, 255, 0, 255, 0, 255];
+    image::save_buffer(
+        directory.join("stripes.png"),
+        &pixels,
+        2,
+        1,
+        image::ColorType::Rgba8,
+    )
+    .unwrap();
+
+    let scene_file: SceneFile = serde_json::from_str(
+        r#"{"models": [
+            {"mesh": {"type": "cube"}, "texture": "stripes.png"},
+            {"mesh": {"type": "cube"}},
+            {"mesh": {"type": "cube"}, "texture": "stripes.png"}
+        ]}"#,
+    )
+    .unwrap();
+
+    let mut assets = AssetRegistry::new();
+    let handles = scene_file.load_textures(&directory, &mut assets).unwrap();
+
+    assert_eq!(handles.len(), 1);
+
+    let texture = assets.texture(handles[0]);
+    assert_eq!([texture.width(), texture.height()], [2, 1]);
+    assert_eq!(texture.pixels(), pixels);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn reloading_a_mesh_leaves_other_files_alone() {
+    let directory = env::temp_dir().join(format!("graphics-reload-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(
+        directory.join("a.obj"),
+        "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+    )
+    .unwrap();
+
+    let mut assets = AssetRegistry::new();
+    let scene_file: SceneFile =
+        serde_json::from_str(r#"{"models": [{"mesh": {"type": "file", "path": "a.obj"}}]}"#)
+            .unwrap();
+    let mut scene = scene_file
+        .build_with_assets(&directory, &mut assets)
+        .unwrap();
+    let handle = assets.load_mesh(directory.join("a.obj")).unwrap();
+
+    // The scene file gained a model whose file doesn't exist, so it couldn't be rebuilt
+    let edited: SceneFile = serde_json::from_str(
+        r#"{"models": [
+            {"mesh": {"type": "file", "path": "a.obj"}},
+            {"mesh": {"type": "file", "path": "missing.obj"}}
+        ]}"#,
+    )
+    .unwrap();
+
+    // Make sure the modification time changes, and wait out the poll interval
+    thread::sleep(Duration::from_millis(300));
+    fs::write(
+        directory.join("a.obj"),
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n",
+    )
+    .unwrap();
+
+    assert_eq!(assets.poll(), vec![AssetChange::Mesh(handle)]);
+
+    edited
+        .reload_mesh(&mut scene, &directory, &mut assets, handle)
+        .unwrap();
+    assert_eq!(scene.models()[0].indices().len(), 6);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,275 @@
+use nalgebra_glm::TVec3;
+
+use graphics::geometry::{
+    flip_winding, recompute_normals, tangents, validate, weld, MeshIssue, NormalWeighting, Winding,
+};
+use graphics::model::Model;
+use graphics::vertex::{CompactVec3, Vertex, CUBE_VERTICES};
+
+const EPSILON: f32 = 1e-5;
+
+fn cube() -> Model {
+    Model::new_cube(CUBE_VERTICES.to_vec())
+}
+
+/// The cube with its normals cleared, so only positions tell corners apart
+fn cube_without_normals() -> Model {
+    let vertices = CUBE_VERTICES
+        .iter()
+        .map(|v| Vertex::new(v.position, [0.0; 3]))
+        .collect();
+
+    Model::new_cube(vertices)
+}
+
+/// Each face's texture coordinates from the two axes it doesn't face along
+fn cube_with_uvs() -> Model {
+    let vertices = CUBE_VERTICES
+        .iter()
+        .map(|v| {
+            let [x, y, z] = v.position;
+            let uv = if v.normal[0] != 0.0 {
+                [z, y]
+            } else if v.normal[1] != 0.0 {
+                [x, z]
+            } else {
+                [x, y]
+            };
+
+            v.with_uv([uv[0] + 0.5, uv[1] + 0.5])
+        })
+        .collect();
+
+    Model::new_cube(vertices)
+}
+
+fn assert_close(a: CompactVec3, b: CompactVec3) {
+    assert!(
+        a.iter().zip(&b).all(|(a, b)| (a - b).abs() < EPSILON),
+        "{:?} != {:?}",
+        a,
+        b
+    );
+}
+
+#[test]
+fn welding_keeps_vertices_with_different_normals() {
+    let welded = weld(&cube(), EPSILON);
+
+    assert_eq!(welded.vertices().len(), CUBE_VERTICES.len());
+    assert_eq!(welded.indices(), cube().indices());
+}
+
+#[test]
+fn welding_merges_shared_corners() {
+    let model = cube_without_normals();
+    let welded = weld(&model, EPSILON);
+
+    assert_eq!(welded.vertices().len(), 8);
+    assert_eq!(welded.indices().len(), model.indices().len());
+
+    // Every triangle still has the same corners
+    for (&before, &after) in model.indices().iter().zip(&welded.indices()) {
+        assert_eq!(
+            model.vertices()[before as usize].position,
+            welded.vertices()[after as usize].position
+        );
+    }
+}
+
+#[test]
+fn welding_respects_epsilon() {
+    let mut vertices = CUBE_VERTICES.to_vec();
+    vertices[1].position[0] += 0.01;
+
+    let nudged = Model::new_cube(vertices);
+
+    assert_eq!(weld(&nudged, 0.001).vertices().len(), CUBE_VERTICES.len());
+    assert_eq!(weld(&cube_without_normals(), 0.1).vertices().len(), 8);
+}
+
+#[test]
+fn welding_without_a_tolerance_merges_identical_corners() {
+    assert_eq!(weld(&cube(), 0.0).vertices().len(), CUBE_VERTICES.len());
+    assert_eq!(weld(&cube_without_normals(), 0.0).vertices().len(), 8);
+
+    // -0 and +0 are the same position
+    let mut vertices = CUBE_VERTICES
+        .map(|v| Vertex::new(v.position, [0.0; 3]))
+        .to_vec();
+    vertices.push(Vertex::new([0.0, 0.0, 0.0], [0.0; 3]));
+    vertices.push(Vertex::new([-0.0, 0.0, -0.0], [0.0; 3]));
+    let indices = (0..vertices.len() as u32).collect();
+
+    assert_eq!(
+        weld(&Model::new(vertices, indices), 0.0).vertices().len(),
+        9
+    );
+}
+
+#[test]
+fn tiny_tolerances_far_from_the_origin_dont_overflow() {
+    let vertices = [[1e30, -1e30, 0.0], [1e30, -1e30, 0.0], [-1e30, 1e30, 1.0]]
+        .map(|position| Vertex::new(position, [0.0; 3]))
+        .to_vec();
+
+    for epsilon in [f32::MIN_POSITIVE, 1e-40] {
+        let welded = weld(&Model::new(vertices.clone(), vec![0, 1, 2]), epsilon);
+
+        assert_eq!(welded.vertices().len(), 2);
+    }
+}
+
+#[test]
+fn recomputed_normals_match_the_cube() {
+    for weighting in [NormalWeighting::Area, NormalWeighting::Angle] {
+        let recomputed = recompute_normals(&cube_without_normals(), weighting, Winding::Clockwise);
+
+        for (v, original) in recomputed.vertices().iter().zip(CUBE_VERTICES) {
+            assert_close(v.normal, original.normal);
+        }
+    }
+}
+
+#[test]
+fn angle_weighted_normals_point_out_of_corners() {
+    let welded = weld(&cube_without_normals(), EPSILON);
+    let smoothed = recompute_normals(&welded, NormalWeighting::Angle, Winding::Clockwise);
+
+    // Each corner touches three faces at right angles, however they're split into triangles
+    for v in smoothed.vertices() {
+        let expected = TVec3::from(v.position).normalize();
+
+        assert_close(v.normal, [expected.x, expected.y, expected.z]);
+    }
+}
+
+#[test]
+fn area_weighted_normals_point_outwards() {
+    let welded = weld(&cube_without_normals(), EPSILON);
+    let smoothed = recompute_normals(&welded, NormalWeighting::Area, Winding::Clockwise);
+
+    for v in smoothed.vertices() {
+        let normal = TVec3::from(v.normal);
+
+        assert!((normal.norm() - 1.0).abs() < EPSILON);
+        assert!(normal.dot(&TVec3::from(v.position)) > 0.0);
+    }
+}
+
+#[test]
+fn flipping_winding_turns_faces_inside_out() {
+    let flipped = flip_winding(&cube());
+    let recomputed = recompute_normals(&flipped, NormalWeighting::Area, Winding::Clockwise);
+
+    for (v, original) in recomputed.vertices().iter().zip(CUBE_VERTICES) {
+        assert_close(v.normal, original.normal.map(|x| -x));
+    }
+
+    // Counter-clockwise normals of the flipped cube are the cube's own
+    let recomputed = recompute_normals(&flipped, NormalWeighting::Area, Winding::CounterClockwise);
+
+    for (v, original) in recomputed.vertices().iter().zip(CUBE_VERTICES) {
+        assert_close(v.normal, original.normal);
+    }
+
+    assert_eq!(flip_winding(&flipped).indices(), cube().indices());
+}
+
+#[test]
+fn tangents_follow_u_at_right_angles_to_normals() {
+    let model = cube_with_uvs();
+
+    for (v, tangent) in model.vertices().iter().zip(tangents(&model)) {
+        let normal = TVec3::from(v.normal);
+        let direction = TVec3::new(tangent[0], tangent[1], tangent[2]);
+
+        assert!((direction.norm() - 1.0).abs() < EPSILON);
+        assert!(direction.dot(&normal).abs() < EPSILON);
+        assert!(tangent[3] == 1.0 || tangent[3] == -1.0);
+
+        // u is taken from the first axis the face doesn't point along
+        let axis = if v.normal[0] != 0.0 {
+            TVec3::z()
+        } else {
+            TVec3::x()
+        };
+        assert_close(tangent[..3].try_into().unwrap(), axis.into());
+    }
+}
+
+#[test]
+fn bitangent_sign_follows_v() {
+    let model = cube_with_uvs();
+
+    for (v, tangent) in model.vertices().iter().zip(tangents(&model)) {
+        let normal = TVec3::from(v.normal);
+        let direction = TVec3::new(tangent[0], tangent[1], tangent[2]);
+        let bitangent = normal.cross(&direction) * tangent[3];
+
+        // v is taken from y on the sides, and z on the top and bottom
+        let axis = if v.normal[1] != 0.0 {
+            TVec3::z()
+        } else {
+            TVec3::y()
+        };
+        assert_close(bitangent.into(), axis.into());
+    }
+}
+
+#[test]
+fn tangents_without_uvs_are_still_perpendicular() {
+    let model = cube();
+
+    for (v, tangent) in model.vertices().iter().zip(tangents(&model)) {
+        let direction = TVec3::new(tangent[0], tangent[1], tangent[2]);
+
+        assert!((direction.norm() - 1.0).abs() < EPSILON);
+        assert!(direction.dot(&TVec3::from(v.normal)).abs() < EPSILON);
+    }
+}
+
+#[test]
+fn the_cube_is_valid() {
+    assert!(validate(&cube()).is_empty());
+}
+
+#[test]
+fn degenerate_triangles_are_found() {
+    let mut indices = cube().indices();
+    // Two corners the same
+    indices.extend([0, 0, 1]);
+    // Three corners along the front face's diagonal, which passes through its centre
+    let mut vertices = CUBE_VERTICES.to_vec();
+    vertices.push(Vertex::new([0.0, 0.0, -0.5], [0.0, 0.0, -1.0]));
+    indices.extend([0, 24, 1]);
+
+    let issues = validate(&Model::new(vertices, indices));
+
+    assert_eq!(
+        issues,
+        vec![
+            MeshIssue::DegenerateTriangle { triangle: 12 },
+            MeshIssue::DegenerateTriangle { triangle: 13 },
+        ]
+    );
+}
+
+#[test]
+fn bad_indices_are_found() {
+    let mut indices = cube().indices();
+    indices.extend([0, 1, 99, 2]);
+
+    let issues = validate(&Model::new(CUBE_VERTICES.to_vec(), indices));
+
+    assert_eq!(
+        issues,
+        vec![
+            MeshIssue::IndexOutOfRange {
+                triangle: 12,
+                index: 99
+            },
+            MeshIssue::IncompleteTriangle,
+        ]
+    );
+}
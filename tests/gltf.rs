@@ -0,0 +1,255 @@
+use nalgebra_glm::TVec3;
+
+use serde_json::json;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use graphics::clock::Clock;
+use graphics::error::SceneError;
+use graphics::geometry::Winding;
+use graphics::gltf::{parse_gltf, GltfScene};
+use graphics::scene_file::SceneFile;
+
+const EPSILON: f32 = 1e-5;
+
+/// A triangle counter-clockwise seen from +z, with each corner weighted fully to `joint`
+fn triangle_buffer(joint: u8) -> Vec<u8> {
+    let positions = [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    let mut data: Vec<u8> = positions
+        .iter()
+        .flatten()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+
+    for _ in 0..3 {
+        data.extend([joint, 0, 0, 0]);
+    }
+    for _ in 0..3 {
+        data.extend([1.0f32, 0.0, 0.0, 0.0].iter().flat_map(|w| w.to_le_bytes()));
+    }
+
+    data
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    data.chunks(3)
+        .flat_map(|chunk| {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+                bits | ((byte as u32) << (16 - 8 * i))
+            });
+
+            (0..4).map(move |i| {
+                if i <= chunk.len() {
+                    ALPHABET[((bits >> (18 - 6 * i)) & 63) as usize] as char
+                } else {
+                    '='
+                }
+            })
+        })
+        .collect()
+}
+
+/// glTF JSON for a single triangle on node 0. If `nodes` has more than one, the triangle is
+/// skinned to node 1 and has an empty morph target. `nodes` are the JSON of each node
+fn document(nodes: &[&str], skin_joints: &str, joint: u8) -> String {
+    let data = triangle_buffer(joint);
+    let skinned = nodes.len() > 1;
+    let primitive = if skinned {
+        r#""attributes": {"POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2}, "targets": [{"POSITION": 3}]"#
+    } else {
+        r#""attributes": {"POSITION": 0}"#
+    };
+
+    format!(
+        r#"{{
+            "scenes": [{{"nodes": [0]}}],
+            "nodes": [{}],
+            "meshes": [{{"primitives": [{{{}}}]}}],
+            "skins": [{{"joints": {}}}],
+            "accessors": [
+                {{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}},
+                {{"bufferView": 1, "componentType": 5121, "count": 3, "type": "VEC4"}},
+                {{"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC4"}},
+                {{"componentType": 5126, "count": 3, "type": "VEC3"}}
+            ],
+            "bufferViews": [
+                {{"buffer": 0, "byteLength": 36}},
+                {{"buffer": 0, "byteOffset": 36, "byteLength": 12}},
+                {{"buffer": 0, "byteOffset": 48, "byteLength": 48}}
+            ],
+            "buffers": [{{"uri": "data:application/octet-stream;base64,{}", "byteLength": {}}}]
+        }}"#,
+        nodes.join(", "),
+        primitive,
+        skin_joints,
+        base64(&data),
+        data.len()
+    )
+}
+
+fn parse(nodes: &[&str], skin_joints: &str, joint: u8) -> Result<GltfScene, SceneError> {
+    parse_gltf(
+        document(nodes, skin_joints, joint).as_bytes(),
+        Path::new(""),
+    )
+}
+
+/// The unskinned triangle with node 0 moved by an animation keyed at `times`
+fn animated(times: &[f32]) -> Result<GltfScene, SceneError> {
+    let mut document: serde_json::Value =
+        serde_json::from_str(&document(&[r#"{"mesh": 0}"#], "[]", 0)).unwrap();
+    let data: Vec<u8> = times
+        .iter()
+        .chain(&vec![0.0; times.len() * 3])
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+
+    document["buffers"].as_array_mut().unwrap().push(json!({
+        "uri": format!("data:application/octet-stream;base64,{}", base64(&data)),
+        "byteLength": data.len()
+    }));
+    document["bufferViews"].as_array_mut().unwrap().extend([
+        json!({"buffer": 1, "byteLength": times.len() * 4}),
+        json!({"buffer": 1, "byteOffset": times.len() * 4, "byteLength": times.len() * 12}),
+    ]);
+    document["accessors"].as_array_mut().unwrap().extend([
+        json!({"bufferView": 3, "componentType": 5126, "count": times.len(), "type": "SCALAR"}),
+        json!({"bufferView": 4, "componentType": 5126, "count": times.len(), "type": "VEC3"}),
+    ]);
+    document["animations"] = json!([{
+        "channels": [{"sampler": 0, "target": {"node": 0, "path": "translation"}}],
+        "samplers": [{"input": 4, "output": 5}]
+    }]);
+
+    parse_gltf(document.to_string().as_bytes(), Path::new(""))
+}
+
+#[test]
+fn animation_times_must_be_finite_and_increasing() {
+    assert!(animated(&[0.0, 0.5, 1.0]).is_ok());
+
+    for times in [
+        [0.0, f32::NAN, 1.0],
+        [0.0, 0.5, f32::INFINITY],
+        [0.0, 1.0, 0.5],
+        [0.0, 0.5, 0.5],
+    ] {
+        assert!(
+            matches!(animated(&times), Err(SceneError::Gltf(_))),
+            "{:?}",
+            times
+        );
+    }
+}
+
+#[test]
+fn triangles_keep_their_front() {
+    let scene = parse(&[r#"{"mesh": 0}"#], "[]", 0).unwrap().graph.scene();
+    let model = &scene.models()[0];
+    let vertices = model.vertices();
+
+    for triangle in model.indices().chunks_exact(3) {
+        let corners = [0, 1, 2].map(|i| TVec3::from(vertices[triangle[i] as usize].position));
+        let facing = Winding::Clockwise.face_normal(corners).normalize();
+
+        assert!((facing - TVec3::z()).norm() <= EPSILON, "{:?}", facing);
+    }
+
+    // Missing normals are generated facing the same way
+    for v in &vertices {
+        assert!((TVec3::from(v.normal) - TVec3::z()).norm() <= EPSILON);
+    }
+}
+
+#[test]
+fn skinned_meshes_are_loaded() {
+    let scene = parse(
+        &[r#"{"mesh": 0, "skin": 0}"#, r#"{"name": "bone"}"#],
+        "[1]",
+        0,
+    )
+    .unwrap()
+    .graph
+    .scene();
+
+    assert!(scene.models()[0].skin().is_some());
+}
+
+#[test]
+fn weights_on_missing_joints_are_rejected() {
+    let result = parse(
+        &[r#"{"mesh": 0, "skin": 0}"#, r#"{"name": "bone"}"#],
+        "[1]",
+        5,
+    );
+
+    assert!(matches!(result, Err(SceneError::Gltf(_))));
+}
+
+#[test]
+fn joints_under_a_node_cycle_are_rejected() {
+    // The joint's parent and grandparent are each other's parent, and aren't reached from the
+    // scene
+    let result = parse(
+        &[
+            r#"{"mesh": 0, "skin": 0}"#,
+            r#"{"name": "bone"}"#,
+            r#"{"children": [1, 3]}"#,
+            r#"{"children": [2]}"#,
+        ],
+        "[1]",
+        0,
+    );
+
+    assert!(matches!(result, Err(SceneError::Gltf(_))));
+}
+
+#[test]
+fn scene_files_animate_gltf_joints_and_morph_weights() {
+    let directory = env::temp_dir().join(format!("graphics-gltf-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(
+        directory.join("figure.gltf"),
+        document(
+            &[r#"{"mesh": 0, "skin": 0}"#, r#"{"name": "bone"}"#],
+            "[1]",
+            0,
+        ),
+    )
+    .unwrap();
+
+    let scene_file: SceneFile = serde_json::from_str(
+        r#"{
+            "models": [{"name": "figure", "mesh": {"type": "file", "path": "figure.gltf"}}],
+            "clips": [{"name": "wave", "channels": [
+                {"property": "translation", "target": "bone", "track": {"keyframes": [
+                    {"time": 0.0, "value": [0.0, 0.0, 0.0]},
+                    {"time": 1.0, "value": [0.0, 2.0, 0.0]}
+                ]}},
+                {"property": "morph_weight", "target": "figure", "index": 0, "track": {"keyframes": [
+                    {"time": 0.0, "value": 0.0},
+                    {"time": 1.0, "value": 1.0}
+                ]}}
+            ]}]
+        }"#,
+    )
+    .unwrap();
+
+    let mut scene = scene_file.build(&directory).unwrap();
+    let mut clock = Clock::default();
+    clock.seek(Duration::from_secs_f32(0.5));
+    scene_file.animate(&mut scene, &clock);
+
+    let model = &scene.models()[0];
+    let skeleton = model.skin().unwrap().skeleton();
+
+    assert_eq!(skeleton.local(0).translation, TVec3::new(0.0, 1.0, 0.0));
+    assert_eq!(model.morph().unwrap().weights(), vec![0.5]);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,175 @@
+use nalgebra_glm::{identity, translation, TVec3};
+
+use std::path::Path;
+
+use graphics::error::SceneError;
+use graphics::geometry::Winding;
+use graphics::lod::{generate_lods, simplify, LodGroup};
+use graphics::model::Model;
+use graphics::morph::{Morph, MorphTarget};
+use graphics::scene::Scene;
+use graphics::scene_file::SceneFile;
+use graphics::scene_graph::Trs;
+use graphics::shapes;
+use graphics::skeleton::{Joint, Skeleton, Skin};
+use graphics::vertex::SkinVertex;
+use graphics::vp::VP;
+
+/// Every triangle faces away from the origin, as on a sphere around it
+fn assert_faces_outwards(model: &Model) {
+    let vertices = model.vertices();
+
+    for triangle in model.indices().chunks_exact(3) {
+        let corners = [0, 1, 2].map(|i| TVec3::from(vertices[triangle[i] as usize].position));
+        let centre: TVec3<f32> = corners.iter().sum();
+
+        assert!(
+            Winding::Clockwise.face_normal(corners).dot(&centre) > 0.0,
+            "{:?}",
+            corners
+        );
+    }
+}
+
+#[test]
+fn simplified_spheres_keep_their_shape() {
+    let sphere = shapes::uv_sphere(1.0, 32, 16);
+
+    for target in [400, 100, 20] {
+        let simplified = simplify(&sphere, target);
+        let triangles = simplified.indices().len() / 3;
+
+        assert!(triangles <= target, "{} > {}", triangles, target);
+        assert!(triangles > 0);
+        assert_faces_outwards(&simplified);
+    }
+}
+
+#[test]
+fn each_level_has_fewer_triangles() {
+    let lods = generate_lods(&shapes::icosphere(1.0, 3), 4, 0.5);
+
+    assert_eq!(lods.len(), 4);
+    for pair in lods.windows(2) {
+        assert!(pair[1].indices().len() <= pair[0].indices().len() / 2);
+    }
+    for lod in &lods {
+        assert_faces_outwards(lod);
+    }
+}
+
+/// A view from the origin along +z, where the screen size is the radius over the distance
+fn view() -> VP {
+    VP {
+        view: identity(),
+        proj: identity(),
+    }
+}
+
+#[test]
+fn selection_doesnt_flip_flop_near_a_threshold() {
+    let mut lod = LodGroup::generate(&shapes::uv_sphere(1.0, 32, 16), 3, 0.5);
+    let threshold = lod.screen_sizes()[0];
+    let at_size = |size: f32| translation(&TVec3::new(0.0, 0.0, 1.0 / size));
+
+    lod.select(&at_size(threshold * 2.0), &view());
+    assert_eq!(lod.current(), 0);
+
+    // Wobbling either side of the threshold by less than the hysteresis stays on one level
+    for i in 0..20 {
+        let wobble = if i % 2 == 0 { 1.05 } else { 0.95 };
+
+        assert!(!lod.select(&at_size(threshold * wobble), &view()));
+        assert_eq!(lod.current(), 0);
+    }
+
+    // Going well past it switches, then the same wobble keeps the new level
+    assert!(lod.select(&at_size(threshold * 0.8), &view()));
+    assert_eq!(lod.current(), 1);
+
+    for i in 0..20 {
+        let wobble = if i % 2 == 0 { 1.05 } else { 0.95 };
+
+        assert!(!lod.select(&at_size(threshold * wobble), &view()));
+        assert_eq!(lod.current(), 1);
+    }
+}
+
+#[test]
+#[should_panic]
+fn negative_hysteresis_is_rejected() {
+    LodGroup::generate(&shapes::icosphere(1.0, 1), 2, 0.5).set_hysteresis(-0.1);
+}
+
+#[test]
+#[should_panic]
+fn hysteresis_of_one_is_rejected() {
+    LodGroup::generate(&shapes::icosphere(1.0, 1), 2, 0.5).set_hysteresis(1.0);
+}
+
+#[test]
+fn switching_levels_keeps_the_pose() {
+    let mut sphere = shapes::uv_sphere(1.0, 32, 16);
+    let count = sphere.vertices().len();
+    let joint = Joint {
+        name: "root".to_string(),
+        parent: None,
+        local: Trs::default(),
+        inverse_bind: identity(),
+    };
+
+    sphere.set_skin(Some(Skin::new(
+        Skeleton::new(vec![joint], identity()).unwrap(),
+        vec![SkinVertex::new([0; 4], [1.0, 0.0, 0.0, 0.0]); count],
+    )));
+    sphere.set_morph(Some(Morph::new(vec![MorphTarget {
+        name: "bulge".to_string(),
+        positions: vec![[0.0; 3]; count],
+        normals: Vec::new(),
+    }])));
+
+    let lod = LodGroup::generate(&sphere, 3, 0.5);
+    let threshold = lod.screen_sizes()[0];
+
+    let mut scene = Scene::new();
+    let index = scene.add_model(sphere);
+    scene.set_lod(index, lod);
+
+    // Posed after the levels were generated, as animation does every frame
+    let model = scene.model_mut(index);
+    model.set_matrix(translation(&TVec3::new(0.0, 0.0, 1.0 / (threshold * 0.8))));
+    let moved = Trs::from_translation(TVec3::y());
+    model.skin_mut().unwrap().skeleton_mut().set_local(0, moved);
+    model.morph_mut().unwrap().set_weight(0, 0.75);
+
+    scene.select_lods(&view());
+
+    let model = &scene.models()[index];
+    assert_eq!(scene.lod(index).unwrap().current(), 1);
+    assert_eq!(model.skin().unwrap().skeleton().local(0), moved);
+    assert_eq!(model.morph().unwrap().weights(), vec![0.75]);
+}
+
+#[test]
+fn out_of_range_lods_in_scene_files_are_rejected() {
+    for lod in [
+        r#"{"levels": 0}"#,
+        r#"{"levels": 3, "ratio": 1.0}"#,
+        r#"{"levels": 3, "ratio": 0.0}"#,
+    ] {
+        let scene_file: SceneFile = serde_json::from_str(&format!(
+            r#"{{"models": [{{"mesh": {{"type": "cube"}}, "lod": {}}}]}}"#,
+            lod
+        ))
+        .unwrap();
+
+        assert!(
+            matches!(
+                scene_file.build(Path::new(".")),
+                Err(SceneError::InvalidScene(_))
+            ),
+            "{}",
+            lod
+        );
+    }
+}
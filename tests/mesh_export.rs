@@ -0,0 +1,331 @@
+use nalgebra_glm::TVec3;
+
+use std::env;
+use std::fs;
+
+use graphics::assets::import_mesh;
+use graphics::error::SceneError;
+use graphics::geometry::Winding;
+use graphics::model::{Model, ModelCollection};
+use graphics::obj::{parse_obj, save_obj, write_obj};
+use graphics::ply::{parse_ply, save_ply, write_ply, PlyFormat};
+use graphics::shapes;
+use graphics::stl::{parse_stl, save_stl, write_stl};
+use graphics::vertex::{Vertex, CUBE_VERTICES};
+
+const EPSILON: f32 = 1e-5;
+
+/// The cube with each face a different colour and texture coordinates across it
+fn coloured_cube() -> Model {
+    let vertices = CUBE_VERTICES
+        .iter()
+        .map(|v| {
+            let [x, y, z] = v.position;
+            let colour = v.normal.map(|n| n.abs());
+
+            v.with_colour(colour).with_uv([x + y + 0.5, z + 0.5])
+        })
+        .collect();
+
+    Model::new_cube(vertices)
+}
+
+fn close(a: &[f32], b: &[f32], epsilon: f32) -> bool {
+    a.iter().zip(b).all(|(a, b)| (a - b).abs() <= epsilon)
+}
+
+/// Both models have the same triangles in the same order, though their vertices may be stored
+/// differently
+fn assert_same_triangles(a: &Model, b: &Model, compare: impl Fn(&Vertex, &Vertex) -> bool) {
+    let (a_vertices, b_vertices) = (a.vertices(), b.vertices());
+    let (a_indices, b_indices) = (a.indices(), b.indices());
+
+    assert_eq!(a_indices.len(), b_indices.len());
+
+    for (&i, &j) in a_indices.iter().zip(&b_indices) {
+        let (a, b) = (&a_vertices[i as usize], &b_vertices[j as usize]);
+
+        assert!(compare(a, b), "{:?} != {:?}", a, b);
+    }
+}
+
+/// Every triangle faces along `normal` with the winding the pipelines draw as front facing
+fn assert_facing(model: &Model, normal: [f32; 3]) {
+    let vertices = model.vertices();
+
+    for triangle in model.indices().chunks_exact(3) {
+        let corners = [0, 1, 2].map(|i| TVec3::from(vertices[triangle[i] as usize].position));
+        let facing = Winding::Clockwise.face_normal(corners).normalize();
+
+        assert!(close(facing.as_slice(), &normal, EPSILON), "{:?}", facing);
+    }
+}
+
+/// Position and normal exactly, and texture coordinates as closely as flipping v allows
+fn same_vertex(a: &Vertex, b: &Vertex) -> bool {
+    a.position == b.position && a.normal == b.normal && close(&a.uv, &b.uv, EPSILON)
+}
+
+#[test]
+fn obj_round_trips() {
+    for model in [coloured_cube(), shapes::torus(1.0, 0.25, 12, 8)] {
+        let loaded = parse_obj(&write_obj(&model)).unwrap();
+
+        assert_eq!(loaded.vertices().len(), model.vertices().len());
+        assert_same_triangles(&model, &loaded, same_vertex);
+    }
+}
+
+#[test]
+fn obj_faces_keep_their_front() {
+    // Counter-clockwise seen from +z
+    let source = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n";
+    let model = parse_obj(source).unwrap();
+
+    assert_facing(&model, [0.0, 0.0, 1.0]);
+    for v in model.vertices() {
+        assert_eq!(v.normal, [0.0, 0.0, 1.0]);
+    }
+
+    // Written back counter-clockwise, so reading it again gives the same front
+    assert_facing(&parse_obj(&write_obj(&model)).unwrap(), [0.0, 0.0, 1.0]);
+}
+
+#[test]
+fn ply_round_trips_in_every_format() {
+    let model = coloured_cube();
+
+    for format in [
+        PlyFormat::Ascii,
+        PlyFormat::BinaryLittleEndian,
+        PlyFormat::BinaryBigEndian,
+    ] {
+        let loaded = parse_ply(&write_ply(&model, format)).unwrap();
+
+        assert_eq!(loaded.vertices().len(), model.vertices().len());
+        assert_eq!(loaded.indices(), model.indices());
+        assert_same_triangles(&model, &loaded, |a, b| {
+            same_vertex(a, b) && close(&a.colour, &b.colour, 0.5 / 255.0)
+        });
+    }
+}
+
+#[test]
+fn stl_round_trips() {
+    let model = shapes::uv_sphere(1.0, 12, 6);
+    let loaded = parse_stl(&write_stl(&model)).unwrap();
+
+    assert_same_triangles(&model, &loaded, |a, b| a.position == b.position);
+
+    // Every corner of a facet has the facet's normal, which faces the same way as the sphere's
+    for triangle in loaded.indices().chunks_exact(3) {
+        let corners = [0, 1, 2].map(|i| loaded.vertices()[triangle[i] as usize]);
+        let centre: TVec3<f32> = corners.iter().map(|v| TVec3::from(v.position)).sum();
+
+        assert!(corners.iter().all(|v| v.normal == corners[0].normal));
+        assert!(TVec3::from(corners[0].normal).dot(&centre) > 0.0);
+    }
+}
+
+#[test]
+fn stl_keeps_the_cube_normals() {
+    let loaded = parse_stl(&write_stl(&coloured_cube())).unwrap();
+
+    assert_same_triangles(&coloured_cube(), &loaded, |a, b| {
+        a.position == b.position && close(&a.normal, &b.normal, EPSILON)
+    });
+}
+
+#[test]
+fn ascii_stl_is_read() {
+    let source = "solid triangle
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 0 1 0
+      vertex 1 0 0
+    endloop
+  endfacet
+endsolid triangle
+";
+    let model = parse_stl(source.as_bytes()).unwrap();
+    let vertices = model.vertices();
+
+    assert_eq!(model.indices(), vec![0, 1, 2, 3, 4, 5]);
+    // Corners are reversed to the pipelines' clockwise winding
+    assert_eq!(vertices[1].position, [0.0, 1.0, 0.0]);
+    assert_eq!(vertices[2].position, [1.0, 0.0, 0.0]);
+    assert_eq!(vertices[0].normal, [0.0, 0.0, 1.0]);
+    // A missing normal comes from the counter-clockwise corners
+    assert_eq!(vertices[3].normal, [0.0, 0.0, -1.0]);
+}
+
+#[test]
+fn ply_without_normals_gets_smooth_ones() {
+    let source = "ply
+format ascii 1.0
+comment a unit square
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+4 0 1 2 3
+";
+    let model = parse_ply(source.as_bytes()).unwrap();
+
+    assert_eq!(model.indices(), vec![0, 2, 1, 0, 3, 2]);
+    for v in model.vertices() {
+        assert!(close(&v.normal, &[0.0, 0.0, 1.0], EPSILON));
+        assert_eq!(v.colour, [1.0, 1.0, 1.0]);
+    }
+}
+
+#[test]
+fn ply_and_stl_faces_keep_their_front() {
+    // Counter-clockwise seen from +z
+    let ply = "ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+4 0 1 2 3
+";
+    let stl = "solid square
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid square
+";
+
+    let from_ply = parse_ply(ply.as_bytes()).unwrap();
+    let from_stl = parse_stl(stl.as_bytes()).unwrap();
+
+    for model in [&from_ply, &from_stl] {
+        assert_facing(model, [0.0, 0.0, 1.0]);
+        for v in model.vertices() {
+            assert!(close(&v.normal, &[0.0, 0.0, 1.0], EPSILON));
+        }
+    }
+
+    // Written back counter-clockwise, so reading them again gives the same front
+    for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian] {
+        assert_facing(
+            &parse_ply(&write_ply(&from_ply, format)).unwrap(),
+            [0.0, 0.0, 1.0],
+        );
+    }
+    assert_facing(&parse_stl(&write_stl(&from_stl)).unwrap(), [0.0, 0.0, 1.0]);
+
+    // The facet normal STL writes for other tools follows their winding
+    let written = write_stl(&from_stl);
+    let normal = [0, 1, 2].map(|i| {
+        let offset = 84 + i * 4;
+        f32::from_le_bytes(written[offset..offset + 4].try_into().unwrap())
+    });
+    assert!(close(&normal, &[0.0, 0.0, 1.0], EPSILON));
+}
+
+#[test]
+fn malformed_files_are_rejected() {
+    assert!(parse_ply(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n").is_err());
+    assert!(parse_ply(b"not a ply file\n").is_err());
+
+    // A binary file cut short
+    let mut data = write_ply(&coloured_cube(), PlyFormat::BinaryLittleEndian);
+    data.truncate(data.len() - 1);
+    assert!(parse_ply(&data).is_err());
+
+    let mut data = write_stl(&coloured_cube());
+    data.truncate(data.len() - 1);
+    assert!(parse_stl(&data).is_err());
+}
+
+#[test]
+fn files_without_faces_are_rejected() {
+    assert!(matches!(
+        parse_obj("v 0 0 0\nv 1 0 0\nv 0 1 0\n"),
+        Err(SceneError::InvalidMesh(_))
+    ));
+    assert!(matches!(
+        parse_ply(&write_ply(&Model::default(), PlyFormat::Ascii)),
+        Err(SceneError::InvalidMesh(_))
+    ));
+    assert!(matches!(
+        parse_stl(&write_stl(&Model::default())),
+        Err(SceneError::InvalidMesh(_))
+    ));
+    assert!(matches!(
+        parse_stl(b"solid empty\nendsolid empty\n"),
+        Err(SceneError::InvalidMesh(_))
+    ));
+}
+
+#[test]
+fn posed_collections_export_in_world_space() {
+    let mut model = coloured_cube();
+    model.set_matrix(nalgebra_glm::translation(&TVec3::new(1.0, 2.0, 3.0)));
+
+    let posed = ModelCollection::from_vec(vec![model.clone()]).posed_model();
+    let loaded = parse_obj(&write_obj(&posed)).unwrap();
+
+    assert_same_triangles(&model, &loaded, |a, b| {
+        close(
+            &b.position,
+            &[
+                a.position[0] + 1.0,
+                a.position[1] + 2.0,
+                a.position[2] + 3.0,
+            ],
+            EPSILON,
+        )
+    });
+}
+
+#[test]
+fn saved_files_import_by_extension() {
+    let directory = env::temp_dir().join(format!("graphics-mesh-export-{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let model = coloured_cube();
+    let triangles = model.indices().len();
+
+    save_obj(&model, directory.join("cube.obj")).unwrap();
+    save_ply(&model, directory.join("cube.ply"), PlyFormat::default()).unwrap();
+    save_stl(&model, directory.join("cube.stl")).unwrap();
+
+    for name in ["cube.obj", "cube.ply", "cube.stl"] {
+        assert_eq!(
+            import_mesh(directory.join(name)).unwrap().indices().len(),
+            triangles
+        );
+    }
+
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,23 @@
+use nalgebra_glm::{translation, TVec3};
+
+use graphics::model::{Model, ModelCollection};
+use graphics::vertex::CUBE_VERTICES;
+
+#[test]
+fn moving_a_model_only_changes_its_deformation() {
+    let mut cube = Model::new_cube(CUBE_VERTICES.to_vec());
+    let before = ModelCollection::from_vec(vec![cube.clone()]);
+
+    cube.set_matrix(translation(&TVec3::new(0.0, 3.0, 0.0)));
+    let after = ModelCollection::from_vec(vec![cube.clone()]);
+
+    assert!(before.same_geometry(&after));
+    assert!(!before.same_deformation(&after));
+
+    // Posed on the CPU, the vertices are still where the matrix puts them
+    for (v, posed) in cube.vertices().iter().zip(after.posed_vertices()) {
+        let expected = TVec3::from(v.position) + TVec3::new(0.0, 3.0, 0.0);
+
+        assert_eq!(TVec3::from(posed.position), expected);
+    }
+}
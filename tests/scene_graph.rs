@@ -0,0 +1,115 @@
+use nalgebra_glm::{quat_angle_axis, TVec3};
+
+use graphics::light::Light;
+use graphics::model::Model;
+use graphics::scene_graph::{SceneGraph, Trs};
+use graphics::vertex::CUBE_VERTICES;
+
+const EPSILON: f32 = 1e-5;
+
+fn close(a: &TVec3<f32>, b: [f32; 3]) -> bool {
+    (a - TVec3::from(b)).norm() <= EPSILON
+}
+
+#[test]
+fn world_matrices_compose_down_the_hierarchy() {
+    let mut graph = SceneGraph::new();
+
+    // A quarter turn about y takes the child's +x offset to -z, then the parent's scale doubles it
+    let root = graph.add_node(
+        "root",
+        Trs {
+            translation: TVec3::new(1.0, 2.0, 3.0),
+            rotation: quat_angle_axis(std::f32::consts::FRAC_PI_2, &TVec3::y()),
+            scale: TVec3::new(2.0, 2.0, 2.0),
+        },
+        None,
+    );
+    let child = graph.add_node("child", Trs::from_translation(TVec3::x()), Some(root));
+    let grandchild = graph.add_node("grandchild", Trs::from_translation(TVec3::y()), Some(child));
+
+    graph.update();
+
+    assert!(close(&graph.world_position(root), [1.0, 2.0, 3.0]));
+    assert!(close(&graph.world_position(child), [1.0, 2.0, 1.0]));
+    assert!(close(&graph.world_position(grandchild), [1.0, 4.0, 1.0]));
+
+    let expected = graph.world_matrix(child) * graph.local(grandchild).matrix();
+    assert!((graph.world_matrix(grandchild) - expected).norm() <= EPSILON);
+}
+
+#[test]
+fn moving_a_node_moves_its_descendants_on_update() {
+    let mut graph = SceneGraph::new();
+    let root = graph.add_node("root", Trs::default(), None);
+    let child = graph.add_node("child", Trs::from_translation(TVec3::x()), Some(root));
+    let grandchild = graph.add_node("grandchild", Trs::from_translation(TVec3::x()), Some(child));
+    let other = graph.add_node("other", Trs::from_translation(TVec3::z()), None);
+
+    graph.update();
+    graph.set_translation(root, TVec3::new(0.0, 5.0, 0.0));
+
+    // World matrices are cached until the next update
+    assert!(close(&graph.world_position(grandchild), [2.0, 0.0, 0.0]));
+
+    graph.update();
+
+    assert!(close(&graph.world_position(child), [1.0, 5.0, 0.0]));
+    assert!(close(&graph.world_position(grandchild), [2.0, 5.0, 0.0]));
+    assert!(close(&graph.world_position(other), [0.0, 0.0, 1.0]));
+
+    // A change to an already dirty node's parent still reaches the node
+    graph.set_translation(grandchild, TVec3::new(0.0, 0.0, 1.0));
+    graph.set_translation(child, TVec3::new(3.0, 0.0, 0.0));
+    graph.update();
+
+    assert!(close(&graph.world_position(grandchild), [3.0, 5.0, 1.0]));
+}
+
+#[test]
+fn reparenting_keeps_the_local_transform() {
+    let mut graph = SceneGraph::new();
+    let a = graph.add_node("a", Trs::from_translation(TVec3::x()), None);
+    let b = graph.add_node("b", Trs::from_translation(TVec3::z()), None);
+    let child = graph.add_node("child", Trs::from_translation(TVec3::y()), Some(a));
+
+    graph.update();
+    graph.set_parent(child, Some(b));
+    graph.update();
+
+    assert_eq!(graph.children(a), vec![]);
+    assert_eq!(graph.children(b), vec![child]);
+    assert!(close(&graph.world_position(child), [0.0, 1.0, 1.0]));
+}
+
+#[test]
+#[should_panic]
+fn a_node_cant_be_parented_under_its_descendant() {
+    let mut graph = SceneGraph::new();
+    let root = graph.add_node("root", Trs::default(), None);
+    let child = graph.add_node("child", Trs::default(), Some(root));
+
+    graph.set_parent(root, Some(child));
+}
+
+#[test]
+fn the_scene_places_meshes_and_lights_in_world_space() {
+    let mut graph = SceneGraph::new();
+    let root = graph.add_node("root", Trs::from_translation(TVec3::x()), None);
+    let child = graph.add_node("child", Trs::from_translation(TVec3::y()), Some(root));
+
+    graph.attach_mesh(child, Model::new_cube(CUBE_VERTICES.to_vec()));
+    graph.attach_light(child, Light::new([0.0, 0.0, 1.0], [1.0; 3], 1.0));
+
+    let scene = graph.scene();
+    let matrix = scene.models()[0].matrix();
+
+    assert!(close(
+        &TVec3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]),
+        [1.0, 1.0, 0.0]
+    ));
+    assert!(close(
+        &TVec3::from(scene.lights()[0].position()),
+        [1.0, 1.0, 1.0]
+    ));
+}
@@ -0,0 +1,112 @@
+use nalgebra_glm::TVec3;
+
+use std::path::Path;
+
+use graphics::error::SceneError;
+use graphics::geometry::Winding;
+use graphics::model::Model;
+use graphics::scene_file::SceneFile;
+use graphics::shapes;
+
+const EPSILON: f32 = 1e-4;
+
+fn every_shape() -> Vec<(&'static str, Model)> {
+    vec![
+        ("uv sphere", shapes::uv_sphere(1.5, 16, 8)),
+        ("icosphere", shapes::icosphere(1.5, 2)),
+        ("plane", shapes::plane(2.0, 3.0, [4, 2])),
+        ("cylinder", shapes::cylinder(1.0, 2.0, 12)),
+        ("cone", shapes::cone(1.0, 2.0, 12)),
+        ("torus", shapes::torus(1.0, 0.25, 16, 8)),
+        ("capsule", shapes::capsule(0.5, 2.0, 12, 4)),
+    ]
+}
+
+#[test]
+fn normals_are_unit_length() {
+    for (name, model) in every_shape() {
+        for v in model.vertices() {
+            let length = TVec3::from(v.normal).norm();
+
+            assert!((length - 1.0).abs() <= EPSILON, "{}: {:?}", name, v);
+        }
+    }
+}
+
+#[test]
+fn sphere_normals_point_away_from_the_centre() {
+    for model in [shapes::uv_sphere(1.5, 16, 8), shapes::icosphere(1.5, 2)] {
+        for v in model.vertices() {
+            let outwards = TVec3::from(v.position) / 1.5;
+
+            assert!(
+                (outwards - TVec3::from(v.normal)).norm() <= EPSILON,
+                "{:?}",
+                v
+            );
+        }
+    }
+}
+
+#[test]
+fn texture_coordinates_stay_in_range() {
+    for (name, model) in every_shape() {
+        // The icosphere's seam triangles carry on past 1 rather than wrapping back to 0
+        let max_u = if name == "icosphere" { 1.5 } else { 1.0 };
+
+        for v in model.vertices() {
+            let [u, v] = v.uv;
+
+            assert!((0.0..=max_u).contains(&u), "{}: u = {}", name, u);
+            assert!((0.0..=1.0).contains(&v), "{}: v = {}", name, v);
+        }
+    }
+}
+
+#[test]
+fn triangles_face_along_their_normals() {
+    for (name, model) in every_shape() {
+        let vertices = model.vertices();
+        assert!(!model.indices().is_empty(), "{}", name);
+
+        for triangle in model.indices().chunks_exact(3) {
+            let corners = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+            let facing = Winding::Clockwise.face_normal(corners.map(|v| TVec3::from(v.position)));
+            let normal: TVec3<f32> = corners.iter().map(|v| TVec3::from(v.normal)).sum();
+
+            assert!(facing.dot(&normal) > 0.0, "{}: {:?}", name, triangle);
+        }
+    }
+}
+
+#[test]
+fn the_plane_faces_up() {
+    for v in shapes::plane(1.0, 1.0, [1, 1]).vertices() {
+        assert_eq!(v.normal, [0.0, -1.0, 0.0]);
+    }
+}
+
+#[test]
+fn out_of_range_shapes_in_scene_files_are_rejected() {
+    for mesh in [
+        r#"{"type": "uv_sphere", "radius": 1.0, "segments": 2, "rings": 4}"#,
+        r#"{"type": "icosphere", "radius": 1.0, "subdivisions": 20}"#,
+        r#"{"type": "plane", "width": 1.0, "depth": 1.0, "subdivisions": [0, 1]}"#,
+        r#"{"type": "cylinder", "radius": 1.0, "height": 1.0, "segments": 0}"#,
+        r#"{"type": "cone", "radius": 1.0, "height": 1.0, "segments": 2}"#,
+        r#"{"type": "torus", "major_radius": 1.0, "minor_radius": 0.5, "segments": 8, "sides": 1}"#,
+        r#"{"type": "capsule", "radius": 1.0, "height": 3.0, "segments": 8, "rings": 0}"#,
+    ] {
+        let scene_file: SceneFile =
+            serde_json::from_str(&format!(r#"{{"models": [{{"mesh": {}}}]}}"#, mesh)).unwrap();
+
+        assert!(
+            matches!(
+                scene_file.build(Path::new(".")),
+                Err(SceneError::InvalidScene(_))
+            ),
+            "{}",
+            mesh
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use nalgebra_glm::{identity, TVec3};
+
+use graphics::error::SceneError;
+use graphics::scene_graph::Trs;
+use graphics::skeleton::{Joint, Skeleton};
+
+/// Joints one unit along x from their parents
+fn joints(parents: &[Option<usize>]) -> Vec<Joint> {
+    parents
+        .iter()
+        .enumerate()
+        .map(|(i, &parent)| Joint {
+            name: format!("joint {}", i),
+            parent,
+            local: Trs::from_translation(TVec3::x()),
+            inverse_bind: identity(),
+        })
+        .collect()
+}
+
+#[test]
+fn world_matrices_follow_the_parents() {
+    // Listed out of order, with children before their parents
+    let skeleton = Skeleton::new(joints(&[Some(2), None, Some(1)]), identity()).unwrap();
+    let worlds = skeleton.world_matrices();
+
+    for (world, x) in worlds.iter().zip([3.0, 1.0, 2.0]) {
+        assert_eq!(world[(0, 3)], x);
+    }
+}
+
+#[test]
+fn parent_cycles_are_rejected() {
+    for parents in [
+        vec![Some(0)],
+        vec![Some(1), Some(0)],
+        // Joint 0's ancestors loop without ever reaching it
+        vec![Some(1), Some(2), Some(1)],
+    ] {
+        assert!(matches!(
+            Skeleton::new(joints(&parents), identity()),
+            Err(SceneError::InvalidSkeleton(_))
+        ));
+    }
+}
+
+#[test]
+fn parents_out_of_range_are_rejected() {
+    assert!(matches!(
+        Skeleton::new(joints(&[None, Some(2)]), identity()),
+        Err(SceneError::InvalidSkeleton(_))
+    ));
+}
+
+#[test]
+fn long_chains_dont_overflow_the_stack() {
+    let parents = (0..100_000usize)
+        .map(|i| i.checked_sub(1))
+        .collect::<Vec<_>>();
+    let skeleton = Skeleton::new(joints(&parents), identity()).unwrap();
+
+    assert_eq!(skeleton.world_matrices()[99_999][(0, 3)], 100_000.0);
+}
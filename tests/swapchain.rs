@@ -0,0 +1,110 @@
+use vulkano::image::ImageAccess;
+use vulkano::instance::{Instance, InstanceCreateInfo, InstanceExtensions};
+use vulkano::swapchain::PresentMode;
+
+use std::sync::Arc;
+
+use graphics::get_device_queue;
+use graphics::swapchain::{create_headless_surface, SwapchainResources};
+
+fn headless_instance() -> Option<Arc<Instance>> {
+    let supported = InstanceExtensions::supported_by_core().ok()?;
+
+    if !supported.khr_surface || !supported.ext_headless_surface {
+        return None;
+    }
+
+    Instance::new(InstanceCreateInfo {
+        enabled_extensions: InstanceExtensions {
+            khr_surface: true,
+            ext_headless_surface: true,
+            ..InstanceExtensions::none()
+        },
+        ..Default::default()
+    })
+    .ok()
+}
+
+#[test]
+fn resources_are_rebuilt_together() {
+    let instance = match headless_instance() {
+        Some(instance) => instance,
+        None => {
+            println!("VK_EXT_headless_surface not supported, skipping");
+            return;
+        }
+    };
+
+    let surface = create_headless_surface(instance.clone()).unwrap();
+    let (device, _queue) = get_device_queue(&instance, Some(&surface), None).unwrap();
+
+    let resources = SwapchainResources::new(
+        device,
+        surface,
+        winit::dpi::PhysicalSize::new(64, 48),
+        PresentMode::Fifo,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        resources.dimensions(),
+        winit::dpi::PhysicalSize::new(64, 48)
+    );
+
+    let resized = resources
+        .recreate(winit::dpi::PhysicalSize::new(128, 96))
+        .unwrap()
+        .unwrap();
+    assert_eq!(resized.dimensions(), winit::dpi::PhysicalSize::new(128, 96));
+    assert_eq!(
+        resized.image_count(),
+        resized.swapchain().image_count() as usize
+    );
+
+    // The format is unchanged so the pipelines can keep using the same render pass
+    assert!(Arc::ptr_eq(
+        &resources.render_pass(),
+        &resized.render_pass()
+    ));
+
+    let attachments = resized.attachments();
+    for image in [
+        attachments.depth_buffer().image().dimensions(),
+        attachments.normal_buffer().image().dimensions(),
+        attachments.colour_buffer().image().dimensions(),
+    ] {
+        assert_eq!(image.width_height(), [128, 96]);
+    }
+
+    for image_i in 0..attachments.framebuffer_count() {
+        assert_eq!(attachments.framebuffer(image_i).extent(), [128, 96]);
+    }
+}
+
+#[test]
+fn zero_extent_is_skipped() {
+    let instance = match headless_instance() {
+        Some(instance) => instance,
+        None => {
+            println!("VK_EXT_headless_surface not supported, skipping");
+            return;
+        }
+    };
+
+    let surface = create_headless_surface(instance.clone()).unwrap();
+    let (device, _queue) = get_device_queue(&instance, Some(&surface), None).unwrap();
+
+    let resources = SwapchainResources::new(
+        device,
+        surface,
+        winit::dpi::PhysicalSize::new(64, 48),
+        PresentMode::Fifo,
+    )
+    .unwrap()
+    .unwrap();
+
+    assert!(resources
+        .recreate(winit::dpi::PhysicalSize::new(0, 48))
+        .unwrap()
+        .is_none());
+}